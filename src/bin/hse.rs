@@ -1,27 +1,105 @@
 extern crate clap;
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Error, Read, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, BufWriter, Error, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-use html_streaming_editor::{report, HtmlRenderable, HtmlStreamingEditor};
+use html_streaming_editor::{
+    error_json, format_records, report, EditingSession, HtmlRenderable, HtmlStreamingEditor,
+    RecordFormat, StreamingEditorError,
+};
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// File name of the Input. `-` for stdin (default)
-    #[arg(short, long, value_name = "INPUT")]
+    #[arg(short, long, value_name = "INPUT", global = true)]
     input: Option<PathBuf>,
 
     /// File name of the Output. `-` for stdout (default)
     #[arg(short, long, value_name = "OUTPUT")]
     output: Option<PathBuf>,
 
+    /// Fail if a selector matches no elements, a command results in an empty result set
+    /// or multiple values are used where one is expected, instead of only logging a warning
+    #[arg(long)]
+    strict: bool,
+
+    /// Write a unified diff between the input and the result instead of the result
+    #[arg(long)]
+    diff: bool,
+
+    /// Don't write the result, but exit with code 1 if the pipeline would change the input
+    #[arg(long)]
+    check: bool,
+
+    /// Describe on stderr what each command did: the number of nodes it got and returned,
+    /// the paths of newly selected and of changed nodes
+    #[arg(long)]
+    explain: bool,
+
+    /// Write the resulting nodes (or their values, see --value) as structured data instead of HTML
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["diff", "check"])]
+    emit: Option<EmitFormat>,
+
+    /// String value pipeline (e.g. `THIS | GET-ATTR{href}`) run on each resulting node for --emit,
+    /// instead of using the node's HTML. Can be given multiple times for multiple values per node.
+    /// No value is written as `null` (JSON) or empty field, multiple values as JSON array;
+    /// csv and lines reject multiple values, combine them with e.g. `JOIN` first
+    #[arg(long, value_name = "PIPELINE", requires = "emit")]
+    value: Vec<String>,
+
+    /// How errors are written to stderr
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+
     /// Single string with the command pipeline to perform.
     /// If it starts with an @ the rest is treated as file name
     /// to read the pipeline definition from
-    pipeline: String,
+    #[arg(required = true)]
+    pipeline: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EmitFormat {
+    /// single JSON array
+    Json,
+    /// one JSON value per line
+    Jsonl,
+    /// comma separated values
+    Csv,
+    /// one line per node, multiple values separated by tabs
+    Lines,
+}
+
+impl From<EmitFormat> for RecordFormat {
+    fn from(format: EmitFormat) -> Self {
+        match format {
+            EmitFormat::Json => RecordFormat::Json,
+            EmitFormat::Jsonl => RecordFormat::JsonLines,
+            EmitFormat::Csv => RecordFormat::Csv,
+            EmitFormat::Lines => RecordFormat::Lines,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Load the input once and apply commands interactively, showing the matches of CSS selectors
+    Repl,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    /// human-readable message with causes
+    Text,
+    /// single line JSON object
+    Json,
 }
 
 fn main() {
@@ -29,34 +107,293 @@ fn main() {
 
     let cli = Cli::parse();
 
+    let error_format = cli.error_format;
+    if let Some(Command::Repl) = cli.command {
+        // only --input and --error-format apply to the REPL
+        let ignored = [
+            ("--output", cli.output.is_some()),
+            ("--strict", cli.strict),
+            ("--diff", cli.diff),
+            ("--check", cli.check),
+            ("--explain", cli.explain),
+            ("--emit", cli.emit.is_some()),
+            ("--value", !cli.value.is_empty()),
+            ("<PIPELINE>", cli.pipeline.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, given)| given.then_some(name))
+        .collect::<Vec<_>>();
+        if !ignored.is_empty() {
+            Cli::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!(
+                        "{} can't be used with the repl subcommand",
+                        ignored.join(", ")
+                    ),
+                )
+                .exit();
+        }
+        repl(
+            cli.input.unwrap_or_else(|| PathBuf::from("-")),
+            error_format,
+        );
+        return;
+    }
+
     let input_path = cli.input.unwrap_or_else(|| PathBuf::from("-"));
     let output_path = cli.output.unwrap_or_else(|| PathBuf::from("-"));
-    let mut pipeline_definition = cli.pipeline;
+    let pipeline_definition = cli.pipeline.unwrap_or_default();
+
+    let input_label = input_path.display().to_string();
+    let mut input_reader = open_input(input_path, error_format);
+    let mut explanation = String::new();
+    let mut editor = HtmlStreamingEditor::new(&mut input_reader).with_strict(cli.strict);
+    if cli.explain {
+        editor = editor.with_explanation(&mut explanation);
+    }
+
+    if cli.diff || cli.check {
+        let dry_run = match pipeline_definition.strip_prefix('@') {
+            Some(pipeline_file) => editor.dry_run_file(Path::new(pipeline_file)),
+            None => editor.dry_run(&pipeline_definition),
+        };
+        let dry_run = dry_run.and_then(|dry_run| {
+            if cli.diff {
+                let diff = dry_run.unified_diff(&input_label);
+                let mut output_writer = open_output(output_path, error_format);
+                output_writer
+                    .write_all(diff.as_bytes())
+                    .and_then(|_| output_writer.flush())
+                    .map_err(|source| StreamingEditorError::WritingOutputFailed { source })?;
+            }
+            Ok(dry_run)
+        });
+        eprint!("{}", explanation);
+
+        match dry_run {
+            Ok(dry_run) if cli.check && dry_run.has_changes() => fail(
+                error_format,
+                "input_would_change",
+                "The pipeline would change the input",
+                None,
+                CHANGES_FOUND,
+            ),
+            Ok(_) => {}
+            Err(e) => exit_with(&e, error_format),
+        }
+    } else if let Some(emit) = cli.emit {
+        let value_definitions = cli.value.iter().map(String::as_str).collect::<Vec<_>>();
+        let records = match pipeline_definition.strip_prefix('@') {
+            Some(pipeline_file) => {
+                editor.extract_file(Path::new(pipeline_file), &value_definitions)
+            }
+            None => editor.extract(&pipeline_definition, &value_definitions),
+        };
+        let result = records
+            .and_then(|records| format_records(&records, emit.into()))
+            .and_then(|formatted| {
+                let mut output_writer = open_output(output_path, error_format);
+                output_writer
+                    .write_all(formatted.as_bytes())
+                    .and_then(|_| output_writer.flush())
+                    .map_err(|source| StreamingEditorError::WritingOutputFailed { source })
+            });
+        eprint!("{}", explanation);
+
+        if let Err(e) = result {
+            exit_with(&e, error_format);
+        }
+    } else {
+        let result = match pipeline_definition.strip_prefix('@') {
+            Some(pipeline_file) => editor.run_file(Path::new(pipeline_file)),
+            None => editor.run(&pipeline_definition),
+        };
+        let result = result.and_then(|result| {
+            let mut output_writer = open_output(output_path, error_format);
+            render_result(&result, &mut output_writer)
+                .map_err(|source| StreamingEditorError::WritingOutputFailed { source })
+        });
+        eprint!("{}", explanation);
+
+        if let Err(e) = result {
+            exit_with(&e, error_format);
+        }
+    }
+}
 
-    if pipeline_definition.starts_with('@') {
-        pipeline_definition = read_pipeline_from_file(&pipeline_definition);
+/// exit code of `--check` if the pipeline would change the input, like `diff` uses for differences
+const CHANGES_FOUND: exitcode::ExitCode = 1;
+
+fn exit_with(error: &StreamingEditorError, error_format: ErrorFormat) -> ! {
+    print_error(error, error_format);
+    std::process::exit(exit_code(error));
+}
+
+fn print_error(error: &StreamingEditorError, error_format: ErrorFormat) {
+    match error_format {
+        ErrorFormat::Text => report(error),
+        ErrorFormat::Json => eprintln!("{}", error.to_json()),
+    }
+}
+
+const REPL_HELP: &str = "Enter pipeline commands to apply them to the current nodes, or CSS selectors to show their matches.
+  :show            print the current nodes
+  :pipeline        print the applied commands as one pipeline
+  :undo            revert the last applied command
+  :save <file>     write the applied commands into a pipeline file, e.g. `recipe.hsp`
+  :help            print this help
+  :quit            leave (as does end of input)";
+
+/// reads commands from stdin, so the input has to be a file
+fn repl(input_path: PathBuf, error_format: ErrorFormat) {
+    if input_path.to_str() == Some("-") {
+        fail(
+            error_format,
+            "missing_input_file",
+            "The REPL reads commands from stdin, give the HTML file with --input",
+            None,
+            exitcode::USAGE,
+        );
+    }
+
+    let mut input_reader = open_input(input_path, error_format);
+    let mut session = match EditingSession::load(&mut input_reader) {
+        Ok(session) => session,
+        Err(e) => exit_with(&e, error_format),
+    };
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        println!("{}", REPL_HELP);
     }
 
-    let mut input_reader = open_input(input_path);
-    let editor = HtmlStreamingEditor::new(&mut input_reader);
-    match editor.run(&pipeline_definition) {
-        Ok(result) => {
-            let mut output_writer = open_output(output_path);
-            if let Err(e) = render_result(&result, &mut output_writer) {
-                eprintln!("[ERROR] {}", e);
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("hse> ");
+            let _ = std::io::stdout().flush();
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let line = line.trim();
+        let (command, argument) = line
+            .split_once(' ')
+            .map_or((line, ""), |(command, argument)| (command, argument.trim()));
+
+        match command {
+            "" => {}
+            ":quit" => break,
+            ":help" => println!("{}", REPL_HELP),
+            ":show" => {
+                for node in session.current() {
+                    println!("{}", node.outer_html());
+                }
+            }
+            ":pipeline" => println!("{}", session.pipeline_definition()),
+            ":undo" => match session.undo() {
+                Ok(Some(undone)) => println!("undone: {}", undone),
+                Ok(None) => println!("nothing to undo"),
+                Err(e) => print_error(&e, error_format),
+            },
+            ":save" if !argument.is_empty() => {
+                match std::fs::write(argument, format!("{}\n", session.pipeline_definition())) {
+                    Ok(_) => println!("saved pipeline into {}", argument),
+                    Err(e) => eprintln!("[ERROR] Could not write {}: {}", argument, e),
+                }
             }
+            ":save" => eprintln!("[ERROR] Missing file name, use :save <file>"),
+            _ if command.starts_with(':') => {
+                eprintln!("[ERROR] Unknown REPL command {}, see :help", command)
+            }
+            _ => evaluate(&mut session, line, error_format),
         }
-        Err(e) => report(&e),
     }
 }
 
+/// applies the line as pipeline, or if it isn't one, shows the matches of it as CSS selector
+fn evaluate(session: &mut EditingSession, line: &str, error_format: ErrorFormat) {
+    match session.apply(line) {
+        Ok(1) => println!("1 node"),
+        Ok(count) => println!("{} nodes", count),
+        Err(error @ StreamingEditorError::ParsingPipelineFailed { .. }) => {
+            match session.query(line) {
+                Ok(matches) => {
+                    println!(
+                        "{} {}",
+                        matches.len(),
+                        if matches.len() == 1 {
+                            "match"
+                        } else {
+                            "matches"
+                        }
+                    );
+                    for found in matches {
+                        println!("  {}  {}", found.path(), abbreviate(found.html()));
+                    }
+                }
+                Err(_) => print_error(&error, error_format),
+            }
+        }
+        Err(error) => print_error(&error, error_format),
+    }
+}
+
+/// first line of the HTML, at most 60 characters
+fn abbreviate(html: &str) -> String {
+    let first_line = html.lines().next().unwrap_or_default();
+    if first_line.chars().count() > 60 || first_line.len() < html.len() {
+        format!("{}…", first_line.chars().take(60).collect::<String>())
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// distinct exit codes for errors in the pipeline definition, the input, running the pipeline and the output
+fn exit_code(error: &StreamingEditorError) -> exitcode::ExitCode {
+    match error {
+        StreamingEditorError::LoadingPipelineFailed { .. }
+        | StreamingEditorError::ParsingPipelineFailed { .. } => exitcode::USAGE,
+        StreamingEditorError::ReadingInputFailed { .. }
+        | StreamingEditorError::ParsingInputFailed { .. }
+        | StreamingEditorError::LoadingParsedHtmlFailed { .. } => exitcode::NOINPUT,
+        StreamingEditorError::RunningPipelineFailed { .. }
+        | StreamingEditorError::MultipleValuesInField { .. } => exitcode::DATAERR,
+        StreamingEditorError::WritingOutputFailed { .. } => exitcode::IOERR,
+    }
+}
+
+/// reports an error which happened outside the editor, e.g. opening files
+fn fail(
+    error_format: ErrorFormat,
+    kind: &str,
+    message: &str,
+    cause: Option<&dyn std::error::Error>,
+    code: exitcode::ExitCode,
+) -> ! {
+    match error_format {
+        ErrorFormat::Text => {
+            eprintln!("[ERROR] {}", message);
+            if let Some(cause) = cause {
+                eprintln!();
+                eprintln!("Caused by:");
+                eprintln!("   0: {}", cause);
+            }
+        }
+        ErrorFormat::Json => eprintln!("{}", error_json(kind, message, cause)),
+    }
+    std::process::exit(code);
+}
+
 fn render_result(
     result: &Vec<Box<dyn HtmlRenderable>>,
     output_writer: &mut Box<dyn Write>,
 ) -> Result<(), Error> {
     for node in result {
         let html = node.outer_html();
-        match output_writer.write((*html).as_bytes()) {
+        match output_writer.write_all((*html).as_bytes()) {
             Ok(_) => {}
             Err(e) => return Err(e),
         }
@@ -72,15 +409,19 @@ fn render_result(
     Ok(())
 }
 
-fn open_output(output_path: PathBuf) -> Box<dyn Write> {
+fn open_output(output_path: PathBuf, error_format: ErrorFormat) -> Box<dyn Write> {
     let output_writer: Box<dyn Write> = if output_path.to_str() == Some("-") {
         Box::new(std::io::stdout().lock())
     } else {
-        let output_file = if let Ok(file) = File::create(output_path) {
-            file
-        } else {
-            eprintln!("[ERROR] Could not open output file");
-            std::process::exit(exitcode::CANTCREAT);
+        let output_file = match File::create(&output_path) {
+            Ok(file) => file,
+            Err(e) => fail(
+                error_format,
+                "opening_output_failed",
+                &format!("Could not open output file {}", output_path.display()),
+                Some(&e),
+                exitcode::CANTCREAT,
+            ),
         };
 
         Box::new(BufWriter::new(output_file))
@@ -88,34 +429,22 @@ fn open_output(output_path: PathBuf) -> Box<dyn Write> {
     output_writer
 }
 
-fn open_input(input_path: PathBuf) -> Box<dyn BufRead> {
+fn open_input(input_path: PathBuf, error_format: ErrorFormat) -> Box<dyn BufRead> {
     let input_reader: Box<dyn BufRead> = if input_path.to_str() == Some("-") {
         Box::new(std::io::stdin().lock())
     } else {
-        let input_file = if let Ok(file) = File::open(input_path) {
-            file
-        } else {
-            eprintln!("[ERROR] Could not open input file");
-            std::process::exit(exitcode::NOINPUT);
+        let input_file = match File::open(&input_path) {
+            Ok(file) => file,
+            Err(e) => fail(
+                error_format,
+                "opening_input_failed",
+                &format!("Could not open input file {}", input_path.display()),
+                Some(&e),
+                exitcode::NOINPUT,
+            ),
         };
 
         Box::new(BufReader::new(input_file))
     };
     input_reader
 }
-
-fn read_pipeline_from_file(file_definition: &str) -> String {
-    let filename = file_definition.get(1..).unwrap().to_owned();
-    let mut pipeline_definition = String::new();
-    if let Ok(mut file) = File::open(filename) {
-        if let Err(e) = file.read_to_string(&mut pipeline_definition) {
-            eprintln!("[ERROR] Could not read pipeline definition: {}", e);
-            std::process::exit(exitcode::NOINPUT);
-        }
-    } else {
-        eprintln!("[ERROR] Could not open pipeline definition");
-        std::process::exit(exitcode::NOINPUT);
-    };
-
-    pipeline_definition
-}
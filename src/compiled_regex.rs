@@ -0,0 +1,64 @@
+use regex::Regex;
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+
+/// A regular expression of the pipeline definition, compiled once while parsing the pipeline.
+/// Two instances are equal if they were compiled from the same pattern.
+#[derive(Clone)]
+pub(crate) struct CompiledRegex<'a> {
+    pattern: &'a str,
+    regex: Regex,
+}
+
+impl<'a> CompiledRegex<'a> {
+    pub(crate) fn new(pattern: &'a str) -> Result<Self, regex::Error> {
+        Ok(CompiledRegex {
+            pattern,
+            regex: Regex::new(pattern)?,
+        })
+    }
+}
+
+impl<'a> Deref for CompiledRegex<'a> {
+    type Target = Regex;
+
+    fn deref(&self) -> &Self::Target {
+        &self.regex
+    }
+}
+
+impl<'a> PartialEq for CompiledRegex<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl<'a> Eq for CompiledRegex<'a> {}
+
+impl<'a> Debug for CompiledRegex<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CompiledRegex").field(&self.pattern).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CompiledRegex;
+
+    #[test]
+    fn equality_is_based_on_pattern() {
+        assert_eq!(
+            CompiledRegex::new("a+").unwrap(),
+            CompiledRegex::new("a+").unwrap()
+        );
+        assert_ne!(
+            CompiledRegex::new("a+").unwrap(),
+            CompiledRegex::new("a*").unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_fails() {
+        assert!(CompiledRegex::new("(").is_err());
+    }
+}
@@ -1,12 +1,17 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::ops::Index;
 
-use crate::html::{HtmlContent, HtmlQueryable};
+use crate::html::{node_pointer, HtmlContent, HtmlQueryable};
 use log::trace;
 
+mod style;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use style::StyleDeclarations;
+
 /// CSS [pseudo classes](https://developer.mozilla.org/en-US/docs/Web/CSS/Pseudo-classes) selector
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CssPseudoClass {
@@ -59,7 +64,7 @@ pub struct CssAttributeSelector<'a> {
     /// operator to use for matching
     pub(crate) operator: CssAttributeComparison,
     /// value the attribute has to match
-    pub(crate) value: Option<&'a str>,
+    pub(crate) value: Option<Cow<'a, str>>,
 }
 
 impl<'a> CssAttributeSelector<'a> {
@@ -71,7 +76,7 @@ impl<'a> CssAttributeSelector<'a> {
             return true;
         }
 
-        let expected_value = self.value.expect(
+        let expected_value = self.value.as_deref().expect(
             "If operator is not Exist a value must be given or the parser works incorrectly",
         );
 
@@ -352,4 +357,62 @@ impl<'a> CssSelectorList<'a> {
             .flat_map(|p| p.query(start))
             .collect::<Vec<_>>()
     }
+
+    /// Returns those of the given nodes which themselves match the selector.
+    /// The selector is evaluated in the context of the whole tree each node belongs to,
+    /// so combinators can reference ancestors and siblings outside the given nodes.
+    pub(crate) fn filter(
+        &self,
+        nodes: &[rctree::Node<HtmlContent>],
+    ) -> Vec<rctree::Node<HtmlContent>> {
+        trace!("Filtering using Selector {:#?}", &self.0);
+
+        let mut findings_per_root = HashMap::new();
+
+        nodes
+            .iter()
+            .filter(|node| {
+                self.findings_in_tree(node, &mut findings_per_root)
+                    .contains(&node_pointer(node))
+            })
+            .map(rctree::Node::clone)
+            .collect::<Vec<_>>()
+    }
+
+    /// Returns for each of the given nodes the node itself or the nearest of its ancestors matching the selector,
+    /// leaving out those without any
+    pub(crate) fn closest(
+        &self,
+        nodes: &[rctree::Node<HtmlContent>],
+    ) -> Vec<rctree::Node<HtmlContent>> {
+        let mut findings_per_root = HashMap::new();
+
+        nodes
+            .iter()
+            .filter_map(|node| {
+                let findings = self.findings_in_tree(node, &mut findings_per_root);
+                node.ancestors()
+                    .find(|ancestor| findings.contains(&node_pointer(ancestor)))
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// the nodes matching the selector in the whole tree the node belongs to,
+    /// the selector is queried once per tree and its findings are looked up by node
+    fn findings_in_tree<'c>(
+        &self,
+        node: &rctree::Node<HtmlContent>,
+        findings_per_root: &'c mut HashMap<*const HtmlContent, HashSet<*const HtmlContent>>,
+    ) -> &'c HashSet<*const HtmlContent> {
+        let root = node.ancestors().last().unwrap_or_else(|| node.clone());
+
+        findings_per_root
+            .entry(node_pointer(&root))
+            .or_insert_with(|| {
+                self.query(&[root])
+                    .iter()
+                    .map(node_pointer)
+                    .collect::<HashSet<_>>()
+            })
+    }
 }
@@ -0,0 +1,292 @@
+use html_escape::{decode_html_entities, encode_double_quoted_attribute};
+
+/// A single declaration of an inline `style` attribute, e.g. `max-width: 100% !important`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StyleDeclaration {
+    property: String,
+    value: String,
+    important: bool,
+    /// the declaration as written, `None` if it was set
+    text: Option<String>,
+}
+
+impl StyleDeclaration {
+    fn parse(declaration: &str) -> Option<Self> {
+        let (property, value) = declaration.split_once(':')?;
+        let property = property.trim();
+        if property.is_empty() {
+            return None;
+        }
+
+        let (value, important) = Self::split_important(value);
+
+        Some(StyleDeclaration {
+            property: String::from(property),
+            value,
+            important,
+            text: Some(String::from(declaration.trim())),
+        })
+    }
+
+    /// separates a trailing `!important` from the value
+    fn split_important(value: &str) -> (String, bool) {
+        let value = value.trim();
+
+        if let Some(position) = value.rfind('!') {
+            if value[position + 1..]
+                .trim()
+                .eq_ignore_ascii_case("important")
+            {
+                return (String::from(value[..position].trim_end()), true);
+            }
+        }
+
+        (String::from(value), false)
+    }
+
+    fn is_property(&self, property: &str) -> bool {
+        self.property.eq_ignore_ascii_case(property.trim())
+    }
+}
+
+/// The declarations of an inline `style` attribute.
+/// Only the requested properties are touched, all others keep their order and `!important` flags.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct StyleDeclarations(Vec<StyleDeclaration>);
+
+impl StyleDeclarations {
+    /// parses the (still HTML-escaped) value of a `style` attribute.
+    /// Semicolons inside strings and parentheses (e.g. `url(data:…;base64,…)`) don't end a declaration,
+    /// invalid declarations are dropped.
+    pub(crate) fn parse(style: &str) -> Self {
+        let style = decode_html_entities(style);
+        let (declarations, _) = split_declarations(&style);
+
+        StyleDeclarations(
+            declarations
+                .into_iter()
+                .filter_map(StyleDeclaration::parse)
+                .collect(),
+        )
+    }
+
+    /// whether the value can be set without ending its declaration, which would add others
+    pub(crate) fn is_valid_value(value: &str) -> bool {
+        let (declarations, closed) = split_declarations(value);
+
+        closed && declarations.len() == 1
+    }
+
+    /// returns the value of the last declaration of the property, without `!important`
+    pub(crate) fn get(&self, property: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .rev()
+            .find(|d| d.is_property(property))
+            .map(|d| d.value.as_str())
+    }
+
+    /// sets the value of the property, replacing all previous declarations of it in place.
+    /// The declaration stays `!important` if it was before or if the new value ends with `!important`
+    pub(crate) fn set(&mut self, property: &str, value: &str) {
+        let (value, important) = StyleDeclaration::split_important(value);
+
+        let important = important
+            || self
+                .0
+                .iter()
+                .any(|d| d.is_property(property) && d.important);
+        let declaration = StyleDeclaration {
+            property: String::from(property.trim()),
+            value,
+            important,
+            text: None,
+        };
+
+        match self.0.iter().position(|d| d.is_property(property)) {
+            Some(position) => {
+                self.remove(property);
+                self.0.insert(position, declaration);
+            }
+            None => self.0.push(declaration),
+        }
+    }
+
+    /// removes all declarations of the property
+    pub(crate) fn remove(&mut self, property: &str) {
+        self.0.retain(|d| !d.is_property(property));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// renders the declarations as (HTML-escaped) value of a `style` attribute,
+    /// those which weren't set as they were written
+    pub(crate) fn to_attribute_value(&self) -> String {
+        let style = self
+            .0
+            .iter()
+            .map(|d| match &d.text {
+                Some(text) => text.clone(),
+                None => format!(
+                    "{}: {}{}",
+                    d.property,
+                    d.value,
+                    if d.important { " !important" } else { "" }
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        String::from(encode_double_quoted_attribute(&style))
+    }
+}
+
+/// splits the style at the semicolons outside of strings and parentheses,
+/// also telling whether all of them are closed at its end
+fn split_declarations(style: &str) -> (Vec<&str>, bool) {
+    let mut declarations = vec![];
+    let mut start = 0;
+    let mut quote = None;
+    let mut parentheses = 0_usize;
+    let mut escaped = false;
+
+    for (index, c) in style.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if let Some(open_quote) = quote {
+            if c == open_quote {
+                quote = None;
+            }
+        } else {
+            match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => parentheses += 1,
+                ')' => parentheses = parentheses.saturating_sub(1),
+                ';' if parentheses == 0 => {
+                    declarations.push(&style[start..index]);
+                    start = index + 1;
+                }
+                _ => (),
+            }
+        }
+    }
+    declarations.push(&style[start..]);
+
+    (
+        declarations,
+        quote.is_none() && parentheses == 0 && !escaped,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::StyleDeclarations;
+
+    #[test]
+    fn parse_keeps_semicolons_in_strings_and_parentheses() {
+        let style = StyleDeclarations::parse(
+            r#"background: url(data:image/png;base64,AAA); content: "a;b"; color: red"#,
+        );
+
+        assert_eq!(
+            style.get("background"),
+            Some("url(data:image/png;base64,AAA)")
+        );
+        assert_eq!(style.get("content"), Some(r#""a;b""#));
+        assert_eq!(style.get("COLOR"), Some("red"));
+    }
+
+    #[test]
+    fn parse_decodes_html_entities() {
+        let style = StyleDeclarations::parse("font-family: &quot;Foo; Bar&quot;, serif");
+
+        assert_eq!(style.get("font-family"), Some(r#""Foo; Bar", serif"#));
+    }
+
+    #[test]
+    fn parse_drops_invalid_declarations() {
+        let style = StyleDeclarations::parse(";;display none; :red; color:red;");
+
+        assert_eq!(style.to_attribute_value(), "color:red");
+    }
+
+    #[test]
+    fn get_strips_important() {
+        let style = StyleDeclarations::parse("color: red ! IMPORTANT");
+
+        assert_eq!(style.get("color"), Some("red"));
+        assert_eq!(style.to_attribute_value(), "color: red ! IMPORTANT");
+    }
+
+    #[test]
+    fn set_replaces_in_place_and_keeps_important() {
+        let mut style =
+            StyleDeclarations::parse("color: red; max-width: 50% !important; margin: 0");
+
+        style.set("max-width", "100%");
+        style.set("display", "block !important");
+
+        assert_eq!(
+            style.to_attribute_value(),
+            "color: red; max-width: 100% !important; margin: 0; display: block !important"
+        );
+    }
+
+    #[test]
+    fn set_merges_duplicate_declarations() {
+        let mut style = StyleDeclarations::parse("color: red; margin: 0; color: blue");
+
+        style.set("color", "green");
+
+        assert_eq!(style.to_attribute_value(), "color: green; margin: 0");
+    }
+
+    #[test]
+    fn remove_drops_all_declarations_of_property() {
+        let mut style = StyleDeclarations::parse("display:none;color:red;DISPLAY: block");
+
+        style.remove("display");
+
+        assert_eq!(style.to_attribute_value(), "color:red");
+        assert!(!style.is_empty());
+    }
+
+    #[test]
+    fn untouched_declarations_keep_their_text() {
+        let mut style = StyleDeclarations::parse("COLOR:Red;  margin :0 auto ;font-family: 'A B'");
+
+        style.set("margin", "0");
+
+        assert_eq!(
+            style.to_attribute_value(),
+            "COLOR:Red; margin: 0; font-family: 'A B'"
+        );
+    }
+
+    #[test]
+    fn values_must_not_end_their_declaration() {
+        assert!(StyleDeclarations::is_valid_value(
+            "url(data:image/png;base64,AAA)"
+        ));
+        assert!(StyleDeclarations::is_valid_value(r#""a;b" !important"#));
+        assert!(!StyleDeclarations::is_valid_value("red; display: none"));
+        assert!(!StyleDeclarations::is_valid_value("'red"));
+        assert!(!StyleDeclarations::is_valid_value("url(a"));
+    }
+
+    #[test]
+    fn rendering_escapes_quotes() {
+        let mut style = StyleDeclarations::default();
+
+        style.set("font-family", r#""Foo Bar""#);
+
+        assert_eq!(
+            style.to_attribute_value(),
+            "font-family: &quot;Foo Bar&quot;"
+        );
+    }
+}
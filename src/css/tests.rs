@@ -42,7 +42,7 @@ fn parse_selector_attribute_equals() {
         Ok(CssSelector::for_attribute(CssAttributeSelector {
             attribute: "a",
             operator: CssAttributeComparison::EqualsExact,
-            value: Some("b"),
+            value: Some("b".into()),
         }))
     )
 }
@@ -60,7 +60,7 @@ fn parse_selector_mix() {
             attributes: vec![CssAttributeSelector {
                 attribute: "a",
                 operator: CssAttributeComparison::EqualsExact,
-                value: Some("b"),
+                value: Some("b".into()),
             }],
         })
     )
@@ -360,7 +360,7 @@ fn query_single_level_by_attribute_contains_term() {
         CssAttributeSelector {
             attribute: "data-test",
             operator: CssAttributeComparison::TermContains,
-            value: Some("me"),
+            value: Some("me".into()),
         },
     ))]);
 
@@ -389,7 +389,7 @@ fn query_single_level_by_attribute_contains_character() {
         CssAttributeSelector {
             attribute: "data-test",
             operator: CssAttributeComparison::CharacterContains,
-            value: Some("ts"),
+            value: Some("ts".into()),
         },
     ))]);
 
@@ -418,7 +418,7 @@ fn query_single_level_by_attribute_starts() {
         CssAttributeSelector {
             attribute: "data-test",
             operator: CssAttributeComparison::Starts,
-            value: Some("its"),
+            value: Some("its".into()),
         },
     ))]);
 
@@ -447,7 +447,7 @@ fn query_single_level_by_attribute_ends() {
         CssAttributeSelector {
             attribute: "data-test",
             operator: CssAttributeComparison::Ends,
-            value: Some("me"),
+            value: Some("me".into()),
         },
     ))]);
 
@@ -476,7 +476,7 @@ fn query_single_level_by_attribute_equals_exact() {
         CssAttributeSelector {
             attribute: "data-test",
             operator: CssAttributeComparison::EqualsExact,
-            value: Some("its a me"),
+            value: Some("its a me".into()),
         },
     ))]);
 
@@ -504,7 +504,7 @@ fn query_single_level_by_attribute_equals_till_hyphen() {
         CssAttributeSelector {
             attribute: "data-test",
             operator: CssAttributeComparison::EqualsTillHyphen,
-            value: Some("term"),
+            value: Some("term".into()),
         },
     ))]);
 
@@ -678,4 +678,92 @@ fn query_general_sibling() {
     );
 }
 
+#[test]
+fn filter_keeps_only_matching_nodes_themselves() {
+    let dom = tl::parse(
+        r#"<ul><li class="a">1</li><li>2<em class="a">!</em></li><li class="a">3</li></ul>"#,
+        tl::ParserOptions::default(),
+    )
+    .unwrap();
+    let content = HtmlContent::import(dom).unwrap();
+    let items = content.children().collect::<Vec<_>>();
+
+    let selector = CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_class("a"))]);
+
+    let result = selector.filter(&items);
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0], items[0]);
+    assert_eq!(result[1], items[2]);
+}
+
+#[test]
+fn filter_evaluates_combinators_in_context_of_whole_tree() {
+    let dom = tl::parse(
+        r#"<main><header><p id="element-under-test">1</p></header><p>2</p></main>"#,
+        tl::ParserOptions::default(),
+    )
+    .unwrap();
+    let content = HtmlContent::import(dom).unwrap();
+    let paragraphs =
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element("p"))])
+            .query(&[rctree::Node::clone(&content)]);
+
+    let selector = CssSelectorList::new(vec![CssSelectorPath::new(
+        CssSelector::for_element("header"),
+        vec![CssSelectorStep::direct_child(CssSelector::for_element("p"))],
+    )]);
+
+    let mut result = selector.filter(&paragraphs);
+
+    assert_eq!(result.len(), 1);
+    let first_result = result.pop().unwrap();
+    assert_eq!(
+        first_result.outer_html(),
+        String::from(r#"<p id="element-under-test">1</p>"#)
+    );
+}
+
+#[test]
+fn filter_many_nodes_of_separate_trees() {
+    let items = (0..20_000)
+        .map(|i| {
+            let class = if i % 2 == 0 { "a" } else { "b" };
+            let html = format!(r#"<li class="{}">{}</li>"#, class, i);
+            let dom = tl::parse(&html, tl::ParserOptions::default()).unwrap();
+            HtmlContent::import(dom).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let selector = CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_class("a"))]);
+
+    let result = selector.filter(&items);
+
+    assert_eq!(result.len(), 10_000);
+    assert_eq!(result[1], items[2]);
+}
+
+#[test]
+fn closest_of_many_nodes_in_one_tree() {
+    let html = format!(
+        r#"<div class="list"><ul>{}</ul></div>"#,
+        "<li><a>x</a></li>".repeat(20_000)
+    );
+    let dom = tl::parse(&html, tl::ParserOptions::default()).unwrap();
+    let root = HtmlContent::import(dom).unwrap();
+    let links = root
+        .descendants()
+        .filter(|n| n.borrow().is_tag() && n.first_child().is_some_and(|c| !c.borrow().is_tag()))
+        .collect::<Vec<_>>();
+
+    let selector = CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+        "li",
+    ))]);
+
+    let result = selector.closest(&links);
+
+    assert_eq!(result.len(), 20_000);
+    assert_eq!(result[0], links[0].parent().unwrap());
+}
+
 //TODO: query_or
@@ -0,0 +1,457 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// number of unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// A single step transforming the original lines into the modified ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// line kept, index in the original and in the modified lines
+    Equal(usize, usize),
+    /// line of the original removed
+    Delete(usize),
+    /// line of the modified inserted
+    Insert(usize),
+}
+
+/// Outcome of running a pipeline without writing anything: the input as it would be rendered
+/// without any changes and the rendered result, one line per resulting node like `hse` writes them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DryRun {
+    original: String,
+    result: String,
+}
+
+impl DryRun {
+    pub(crate) fn new(original: String, result: String) -> Self {
+        DryRun { original, result }
+    }
+
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+
+    /// if the pipeline changes anything in the rendered output
+    pub fn has_changes(&self) -> bool {
+        self.original != self.result
+    }
+
+    /// unified diff from the original to the result, empty if nothing changes
+    pub fn unified_diff(&self, label: &str) -> String {
+        unified_diff(&self.original, &self.result, label)
+    }
+}
+
+/// Creates a unified diff (as used by `diff -u` and `patch`) between the two texts,
+/// using the label in the `---`/`+++` header lines. Returns an empty string if both are equal.
+pub(crate) fn unified_diff(original: &str, modified: &str, label: &str) -> String {
+    let original_lines = original.lines().collect::<Vec<_>>();
+    let modified_lines = modified.lines().collect::<Vec<_>>();
+    let edits = diff_lines(&original_lines, &modified_lines);
+
+    let mut result = String::new();
+    for hunk in hunks(&edits) {
+        if result.is_empty() {
+            let _ = writeln!(result, "--- {}", label);
+            let _ = writeln!(result, "+++ {}", label);
+        }
+
+        let (original_start, modified_start) = line_positions(&edits[..hunk.start]);
+        let (original_count, modified_count) = line_positions(&edits[hunk.clone()]);
+        let _ = writeln!(
+            result,
+            "@@ -{},{} +{},{} @@",
+            hunk_start(original_start, original_count),
+            original_count,
+            hunk_start(modified_start, modified_count),
+            modified_count
+        );
+
+        for edit in &edits[hunk] {
+            let _ = match edit {
+                Edit::Equal(o, _) => writeln!(result, " {}", original_lines[*o]),
+                Edit::Delete(o) => writeln!(result, "-{}", original_lines[*o]),
+                Edit::Insert(m) => writeln!(result, "+{}", modified_lines[*m]),
+            };
+        }
+    }
+
+    result
+}
+
+/// empty ranges are denoted by the line before them
+fn hunk_start(lines_before: usize, count: usize) -> usize {
+    if count == 0 {
+        lines_before
+    } else {
+        lines_before + 1
+    }
+}
+
+/// number of original and modified lines covered by the edits
+fn line_positions(edits: &[Edit]) -> (usize, usize) {
+    edits.iter().fold((0, 0), |(o, m), edit| match edit {
+        Edit::Equal(..) => (o + 1, m + 1),
+        Edit::Delete(_) => (o + 1, m),
+        Edit::Insert(_) => (o, m + 1),
+    })
+}
+
+/// groups the changes with their surrounding context, merging groups whose context overlaps
+fn hunks(edits: &[Edit]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = vec![];
+
+    for (index, _) in edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Equal(..)))
+    {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + 1 + CONTEXT_LINES).min(edits.len());
+
+        match hunks.last_mut() {
+            Some(last) if last.end >= start => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    hunks
+}
+
+/// shortest edit script between the lines, using the algorithm of Eugene W. Myers
+/// in its linear space variant, splitting at the middle snake of each differing part
+fn diff_lines<'a>(original: &[&'a str], modified: &[&'a str]) -> Vec<Edit> {
+    // compare numbers instead of the lines themselves
+    let mut ids = HashMap::new();
+    let original_ids = line_ids(original, &mut ids);
+    let modified_ids = line_ids(modified, &mut ids);
+
+    // lines only found on one side are always deleted or inserted, so only the others need to be searched
+    let original_kept = kept_lines(&original_ids, &modified_ids);
+    let modified_kept = kept_lines(&modified_ids, &original_ids);
+    let mut kept_edits = vec![];
+    EditScript {
+        original: &original_kept
+            .iter()
+            .map(|&i| original_ids[i])
+            .collect::<Vec<_>>(),
+        modified: &modified_kept
+            .iter()
+            .map(|&i| modified_ids[i])
+            .collect::<Vec<_>>(),
+        edits: &mut kept_edits,
+    }
+    .between(0, 0, original_kept.len(), modified_kept.len());
+
+    // put the dropped lines back, in front of the next kept one
+    let mut edits = vec![];
+    let (mut x, mut y) = (0, 0);
+    for edit in kept_edits {
+        let (o, m) = match edit {
+            Edit::Equal(o, m) => (Some(original_kept[o]), Some(modified_kept[m])),
+            Edit::Delete(o) => (Some(original_kept[o]), None),
+            Edit::Insert(m) => (None, Some(modified_kept[m])),
+        };
+        if let Some(o) = o {
+            edits.extend((x..o).map(Edit::Delete));
+            x = o;
+        }
+        if let Some(m) = m {
+            edits.extend((y..m).map(Edit::Insert));
+            y = m;
+        }
+        edits.push(match edit {
+            Edit::Equal(..) => Edit::Equal(x, y),
+            Edit::Delete(_) => Edit::Delete(x),
+            Edit::Insert(_) => Edit::Insert(y),
+        });
+        x += o.map_or(0, |_| 1);
+        y += m.map_or(0, |_| 1);
+    }
+    edits.extend((x..original.len()).map(Edit::Delete));
+    edits.extend((y..modified.len()).map(Edit::Insert));
+
+    edits
+}
+
+/// indices of the lines also found in the other lines
+fn kept_lines(ids: &[usize], other_ids: &[usize]) -> Vec<usize> {
+    let other_ids = other_ids.iter().collect::<HashSet<_>>();
+
+    (0..ids.len())
+        .filter(|&i| other_ids.contains(&ids[i]))
+        .collect()
+}
+
+/// the same number for equal lines
+fn line_ids<'a>(lines: &[&'a str], ids: &mut HashMap<&'a str, usize>) -> Vec<usize> {
+    lines
+        .iter()
+        .map(|line| {
+            let next = ids.len();
+            *ids.entry(*line).or_insert(next)
+        })
+        .collect()
+}
+
+/// the part of the original lines `left..right` and of the modified lines `top..bottom` being compared
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+/// a diagonal run of equal lines from `start` to `end`, possibly preceded by a single deletion or insertion
+type Snake = ((usize, usize), (usize, usize));
+
+struct EditScript<'a> {
+    original: &'a [usize],
+    modified: &'a [usize],
+    edits: &'a mut Vec<Edit>,
+}
+
+impl EditScript<'_> {
+    fn between(&mut self, mut left: usize, mut top: usize, mut right: usize, mut bottom: usize) {
+        while left < right && top < bottom && self.original[left] == self.modified[top] {
+            self.edits.push(Edit::Equal(left, top));
+            left += 1;
+            top += 1;
+        }
+        let mut suffix = 0;
+        while left < right && top < bottom && self.original[right - 1] == self.modified[bottom - 1]
+        {
+            right -= 1;
+            bottom -= 1;
+            suffix += 1;
+        }
+
+        if left == right {
+            self.edits.extend((top..bottom).map(Edit::Insert));
+        } else if top == bottom {
+            self.edits.extend((left..right).map(Edit::Delete));
+        } else {
+            let ((x, y), (u, v)) = self.middle_snake(Area {
+                left,
+                top,
+                right,
+                bottom,
+            });
+            self.between(left, top, x, y);
+            self.walk(x, y, u, v);
+            self.between(u, v, right, bottom);
+        }
+
+        self.edits
+            .extend((0..suffix).map(|i| Edit::Equal(right + i, bottom + i)));
+    }
+
+    /// the edits along a snake: at most one deletion or insertion, surrounded by equal lines
+    fn walk(&mut self, mut x: usize, mut y: usize, u: usize, v: usize) {
+        while x < u && y < v && self.original[x] == self.modified[y] {
+            self.edits.push(Edit::Equal(x, y));
+            x += 1;
+            y += 1;
+        }
+        if u - x > v - y {
+            self.edits.push(Edit::Delete(x));
+            x += 1;
+        } else if v - y > u - x {
+            self.edits.push(Edit::Insert(y));
+            y += 1;
+        }
+        while x < u && y < v {
+            self.edits.push(Edit::Equal(x, y));
+            x += 1;
+            y += 1;
+        }
+    }
+
+    /// searches forward from the top left and backward from the bottom right corner at the same time,
+    /// until both paths overlap, keeping only the furthest positions per diagonal of the current step.
+    /// `area` must not be empty
+    fn middle_snake(&self, area: Area) -> Snake {
+        let (left, top) = (area.left as isize, area.top as isize);
+        let (right, bottom) = (area.right as isize, area.bottom as isize);
+        let equal = |x: isize, y: isize| self.original[x as usize] == self.modified[y as usize];
+
+        let delta = (right - left) - (bottom - top);
+        let max = ((right - left) + (bottom - top) + 1) / 2;
+        // forward diagonals k = (x - left) - (y - top) hold the furthest x,
+        // backward diagonals c = (x - right) - (y - bottom) = k - delta the furthest y
+        let offset = max + 1;
+        let at = |k: isize| (k + offset) as usize;
+        let mut forward = vec![0; 2 * offset as usize + 1];
+        let mut backward = vec![0; 2 * offset as usize + 1];
+        forward[at(1)] = left;
+        backward[at(1)] = bottom;
+
+        let point = |x: isize, y: isize| (x as usize, y as usize);
+        for d in 0..=max {
+            for k in (-d..=d).rev().step_by(2) {
+                let (previous_x, mut x) =
+                    if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                        (forward[at(k + 1)], forward[at(k + 1)])
+                    } else {
+                        (forward[at(k - 1)], forward[at(k - 1)] + 1)
+                    };
+                let mut y = top + (x - left) - k;
+                let previous_y = if d == 0 || x != previous_x { y } else { y - 1 };
+                while x < right && y < bottom && equal(x, y) {
+                    x += 1;
+                    y += 1;
+                }
+                forward[at(k)] = x;
+
+                let c = k - delta;
+                if delta % 2 != 0 && -d < c && c < d && y >= backward[at(c)] {
+                    return (point(previous_x, previous_y), point(x, y));
+                }
+            }
+
+            for c in (-d..=d).rev().step_by(2) {
+                let (previous_y, mut y) =
+                    if c == -d || (c != d && backward[at(c - 1)] > backward[at(c + 1)]) {
+                        (backward[at(c + 1)], backward[at(c + 1)])
+                    } else {
+                        (backward[at(c - 1)], backward[at(c - 1)] - 1)
+                    };
+                let k = c + delta;
+                let mut x = left + (y - top) + k;
+                let previous_x = if d == 0 || y != previous_y { x } else { x + 1 };
+                while x > left && y > top && equal(x - 1, y - 1) {
+                    x -= 1;
+                    y -= 1;
+                }
+                backward[at(c)] = y;
+
+                if delta % 2 == 0 && -d <= k && k <= d && x <= forward[at(k)] {
+                    return (point(x, y), point(previous_x, previous_y));
+                }
+            }
+        }
+
+        unreachable!(
+            "the forward and backward paths always overlap after (width + height) / 2 steps"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{diff_lines, unified_diff, Edit};
+
+    #[test]
+    fn equal_texts_have_no_diff() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "index.html"), "");
+    }
+
+    #[test]
+    fn edit_script_is_minimal() {
+        assert_eq!(
+            diff_lines(&["a", "b", "c"], &["a", "x", "c", "d"]),
+            vec![
+                Edit::Equal(0, 0),
+                Edit::Delete(1),
+                Edit::Insert(1),
+                Edit::Equal(2, 2),
+                Edit::Insert(3),
+            ]
+        );
+        assert_eq!(
+            diff_lines(&["a", "b"], &[]),
+            vec![Edit::Delete(0), Edit::Delete(1)]
+        );
+    }
+
+    #[test]
+    fn changed_line_is_shown_with_context() {
+        let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let modified = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+
+        assert_eq!(
+            unified_diff(original, modified, "index.html"),
+            "--- index.html
++++ index.html
+@@ -2,7 +2,7 @@
+ 2
+ 3
+ 4
+-5
++five
+ 6
+ 7
+ 8
+"
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let original = (1..=20).map(|i| format!("{}\n", i)).collect::<String>();
+        let modified = original
+            .replacen("\n2\n", "\ntwo\n", 1)
+            .replace("\n19\n", "\n19\nnew\n");
+
+        assert_eq!(
+            unified_diff(&original, &modified, "-"),
+            "--- -
++++ -
+@@ -1,5 +1,5 @@
+ 1
+-2
++two
+ 3
+ 4
+ 5
+@@ -17,4 +17,5 @@
+ 17
+ 18
+ 19
++new
+ 20
+"
+        );
+    }
+
+    #[test]
+    fn insertion_into_empty_text() {
+        assert_eq!(
+            unified_diff("", "a\n", "x"),
+            "--- x
++++ x
+@@ -0,0 +1,1 @@
++a
+"
+        );
+    }
+
+    #[test]
+    fn edit_script_of_large_texts_transforms_original_into_modified() {
+        let original = (0..4_000)
+            .map(|i| ["a", "b", "c", "d", "e", "f", "g"][i % 7])
+            .collect::<Vec<_>>();
+        let modified = (0..4_000)
+            .map(|i| ["b", "a", "d", "c", "e"][i % 5])
+            .collect::<Vec<_>>();
+
+        let mut result = vec![];
+        for edit in diff_lines(&original, &modified) {
+            match edit {
+                Edit::Equal(o, m) => {
+                    assert_eq!(original[o], modified[m]);
+                    result.push(original[o]);
+                }
+                Edit::Insert(m) => result.push(modified[m]),
+                Edit::Delete(_) => {}
+            }
+        }
+        assert_eq!(result, modified);
+    }
+}
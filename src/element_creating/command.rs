@@ -1,21 +1,44 @@
 use crate::html::HtmlTag;
-use crate::{load_html_file, CommandError, CssSelectorList, HtmlContent};
+use crate::{
+    load_html_file, parse_html_fragment, CommandError, CssSelectorList, HtmlContent, ValueSource,
+};
 use log::trace;
+use std::borrow::Cow;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ElementCreatingCommand<'a> {
     /// creates an HTML element of given type
     /// Returns the created element as result.
     CreateElement(&'a str),
     /// reads a different file into memory
     /// Returns the content of that file as result.
-    FromFile(&'a str),
-    /// Starting at the element being replaced run a sub-query
-    /// Returns all sub-elements that match the given CSS selector.
+    FromFile(Cow<'a, str>),
+    /// Starting at the element being replaced (or the target of an insertion) run a sub-query
+    /// Returns copies of all sub-elements that match the given CSS selector.
     FromReplaced(CssSelectorList<'a>),
+    /// parses an HTML fragment, rendered for each input element
+    /// Returns all top-level nodes of the fragments, including text and comments, as result.
+    ParseHtml(ValueSource<'a>),
 }
 
 impl<'a> ElementCreatingCommand<'a> {
+    /// canonical name of the command, as used in error messages
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ElementCreatingCommand::CreateElement(..) => "CREATE-ELEMENT",
+            ElementCreatingCommand::FromFile(..) => "LOAD-FILE",
+            ElementCreatingCommand::FromReplaced(..) => "QUERY-REPLACED",
+            ElementCreatingCommand::ParseHtml(..) => "PARSE-HTML",
+        }
+    }
+
+    /// lets rendering multiple values into a single string fail
+    pub(crate) fn make_strict(&mut self) {
+        if let ElementCreatingCommand::ParseHtml(value_source) = self {
+            value_source.make_strict();
+        }
+    }
+
     /// perform the action defined by the command on the set of nodes
     /// and return the calculated results.
     /// For some command the output can be equal to the input,
@@ -30,6 +53,9 @@ impl<'a> ElementCreatingCommand<'a> {
             }
             ElementCreatingCommand::FromFile(file_path) => Self::load_file(file_path),
             ElementCreatingCommand::FromReplaced(selector) => Self::query_replaced(input, selector),
+            ElementCreatingCommand::ParseHtml(value_source) => {
+                Self::parse_html(input, value_source)
+            }
         }
     }
 
@@ -59,6 +85,22 @@ impl<'a> ElementCreatingCommand<'a> {
             .map(|e| rctree::Node::clone(e).make_deep_copy())
             .collect::<Vec<_>>())
     }
+
+    fn parse_html(
+        input: &[rctree::Node<HtmlContent>],
+        value_source: &ValueSource,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running PARSE-HTML command with value: {:#?}", value_source);
+
+        let mut result = vec![];
+
+        for node in input {
+            let rendered_value = value_source.render_joined(node)?;
+            result.append(&mut parse_html_fragment(&rendered_value)?);
+        }
+
+        Ok(result)
+    }
 }
 
 #[cfg(test)]
@@ -67,7 +109,7 @@ mod tests {
     use crate::html::HtmlTag;
     use crate::{
         load_inline_html, CssSelector, CssSelectorList, CssSelectorPath, HtmlContent,
-        HtmlRenderable,
+        HtmlRenderable, ValueSource,
     };
     use std::collections::BTreeMap;
 
@@ -101,7 +143,7 @@ mod tests {
 
     #[test]
     fn load_file_read_file_content() {
-        let command = ElementCreatingCommand::FromFile("tests/source.html");
+        let command = ElementCreatingCommand::FromFile("tests/source.html".into());
         let mut result = command.execute(&[]).unwrap();
 
         assert_eq!(result.len(), 1);
@@ -213,4 +255,35 @@ mod tests {
 
         assert_eq!(result.len(), 0);
     }
+
+    #[test]
+    fn parse_html_returns_all_top_level_nodes() {
+        let command = ElementCreatingCommand::ParseHtml(ValueSource::StringValue(
+            "<b>bold</b> text <!-- comment --><br>".into(),
+        ));
+        let root = load_inline_html(r#"<div></div>"#);
+
+        let result = command.execute(&[root]).unwrap();
+        let result = result.iter().map(|n| n.outer_html()).collect::<Vec<_>>();
+
+        assert_eq!(
+            result,
+            vec![
+                String::from("<b>bold</b>"),
+                String::from(" text "),
+                String::from("<!-- comment -->"),
+                String::from("<br>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_html_returns_empty_on_empty_fragment() {
+        let command = ElementCreatingCommand::ParseHtml(ValueSource::StringValue("".into()));
+        let root = load_inline_html(r#"<div></div>"#);
+
+        let result = command.execute(&[root]).unwrap();
+
+        assert_eq!(result.len(), 0);
+    }
 }
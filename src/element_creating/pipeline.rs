@@ -1,14 +1,20 @@
 use crate::element_creating::ElementCreatingCommand;
 use crate::element_processing::ElementProcessingCommand;
-use crate::{CommandFailedSnafu, HtmlContent, PipelineError};
-use log::{trace, warn};
+use crate::explain::Explanation;
+use crate::{check_result_set, CommandFailedSnafu, HtmlContent, PipelineError};
+use log::trace;
 use snafu::ResultExt;
+use std::ops::Range;
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) struct ElementCreatingPipeline<'a>(
-    ElementCreatingCommand<'a>,
-    Vec<ElementProcessingCommand<'a>>,
-);
+/// The spans are only used to report errors, they are not compared.
+#[derive(Debug, Clone)]
+pub(crate) struct ElementCreatingPipeline<'a> {
+    creation: ElementCreatingCommand<'a>,
+    processing: Vec<ElementProcessingCommand<'a>>,
+    spans: Vec<Range<usize>>,
+    strict: bool,
+    explanation: Option<Explanation>,
+}
 
 /// The command pipeline: a list of individual commands
 /// each to execute on the result of the previous command
@@ -17,7 +23,37 @@ impl<'a> ElementCreatingPipeline<'a> {
         creation: ElementCreatingCommand<'a>,
         processing: Option<Vec<ElementProcessingCommand<'a>>>,
     ) -> Self {
-        ElementCreatingPipeline(creation, processing.unwrap_or_default())
+        ElementCreatingPipeline {
+            creation,
+            processing: processing.unwrap_or_default(),
+            spans: vec![],
+            strict: false,
+            explanation: None,
+        }
+    }
+
+    /// adds the position of each command within the pipeline definition,
+    /// starting with the creating command
+    pub fn with_spans(self, spans: Vec<Range<usize>>) -> Self {
+        ElementCreatingPipeline { spans, ..self }
+    }
+
+    /// lets this pipeline and all its sub-pipelines fail if a selector matches nothing
+    /// or a command results in an empty result set
+    pub(crate) fn make_strict(&mut self) {
+        self.strict = true;
+        self.creation.make_strict();
+        for command in self.processing.iter_mut() {
+            command.make_strict();
+        }
+    }
+
+    /// lets this pipeline and all its sub-pipelines record what each command did
+    pub(crate) fn explain_into(&mut self, explanation: &Explanation) {
+        self.explanation = Some(explanation.clone());
+        for command in self.processing.iter_mut() {
+            command.explain_into(&explanation.nested());
+        }
     }
 
     /// execute the pipeline on the given nodes by
@@ -28,30 +64,58 @@ impl<'a> ElementCreatingPipeline<'a> {
         &self,
         nodes: Vec<rctree::Node<HtmlContent>>,
     ) -> Result<Vec<rctree::Node<HtmlContent>>, PipelineError> {
-        let mut intermediate = self
-            .0
-            .execute(&nodes)
-            .context(CommandFailedSnafu { index: 0_usize })?;
-
-        if intermediate.is_empty() {
-            warn!("Command resulted in an empty result set");
+        let span = self.spans.first().cloned();
+        let create = |input: &Vec<rctree::Node<HtmlContent>>| {
+            self.creation
+                .execute(input)
+                .and_then(|result| check_result_set(result, self.strict))
+        };
+        let mut intermediate = match &self.explanation {
+            Some(explanation) => {
+                explanation.record(self.creation.name(), 0, span.clone(), &nodes, create)
+            }
+            None => create(&nodes),
         }
+        .with_context(|_| CommandFailedSnafu {
+            command: self.creation.name(),
+            index: 0_usize,
+            span,
+            spans: self.spans.clone(),
+        })?;
 
-        let mut command_index: usize = 1;
-        for command in self.1.iter() {
+        for (index, command) in self.processing.iter().enumerate() {
+            // the creating command is index 0
+            let command_index = index + 1;
             trace!("Running Next: {:#?}", &command);
             trace!("Current Element Set: {:#?}", &intermediate);
 
-            intermediate = command.execute(&intermediate).context(CommandFailedSnafu {
+            let span = self.spans.get(command_index).cloned();
+            intermediate = match &self.explanation {
+                Some(explanation) => explanation.record(
+                    command.name(),
+                    command_index,
+                    span.clone(),
+                    &intermediate,
+                    |input| command.execute_checked(input, self.strict),
+                ),
+                None => command.execute_checked(&intermediate, self.strict),
+            }
+            .with_context(|_| CommandFailedSnafu {
+                command: command.name(),
                 index: command_index,
+                span,
+                spans: self.spans.clone(),
             })?;
-            command_index += 1;
-
-            if intermediate.is_empty() {
-                warn!("Command resulted in an empty result set");
-            }
         }
 
         Ok(intermediate)
     }
 }
+
+impl<'a> PartialEq for ElementCreatingPipeline<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.creation == other.creation
+            && self.processing == other.processing
+            && self.strict == other.strict
+    }
+}
@@ -0,0 +1,79 @@
+use crate::CompiledRegex;
+
+/// Selects attributes by their name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AttributePattern<'a> {
+    /// exactly this attribute name
+    Name(&'a str),
+    /// attribute names matching a glob, where `*` matches any number and `?` exactly one character
+    Glob(&'a str),
+    /// attribute names containing a match of the regular expression
+    Regex(CompiledRegex<'a>),
+}
+
+impl<'a> AttributePattern<'a> {
+    /// checks if the attribute name is selected by the pattern
+    pub(crate) fn matches(&self, attribute: &str) -> bool {
+        match self {
+            AttributePattern::Name(name) => *name == attribute,
+            AttributePattern::Glob(glob) => Self::matches_glob(
+                &glob.chars().collect::<Vec<_>>(),
+                &attribute.chars().collect::<Vec<_>>(),
+            ),
+            AttributePattern::Regex(regex) => regex.is_match(attribute),
+        }
+    }
+
+    fn matches_glob(glob: &[char], value: &[char]) -> bool {
+        match glob.split_first() {
+            None => value.is_empty(),
+            Some(('*', rest)) => {
+                (0..=value.len()).any(|skip| Self::matches_glob(rest, &value[skip..]))
+            }
+            Some(('?', rest)) => !value.is_empty() && Self::matches_glob(rest, &value[1..]),
+            Some((c, rest)) => value.first() == Some(c) && Self::matches_glob(rest, &value[1..]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::element_processing::AttributePattern;
+    use crate::CompiledRegex;
+
+    #[test]
+    fn name_matches_only_exact_name() {
+        let pattern = AttributePattern::Name("data-src");
+
+        assert!(pattern.matches("data-src"));
+        assert!(!pattern.matches("data-srcset"));
+    }
+
+    #[test]
+    fn glob_matches_wildcards() {
+        let pattern = AttributePattern::Glob("data-v-*");
+
+        assert!(pattern.matches("data-v-3a1f"));
+        assert!(pattern.matches("data-v-"));
+        assert!(!pattern.matches("data-value"));
+
+        let pattern = AttributePattern::Glob("on????");
+
+        assert!(pattern.matches("onload"));
+        assert!(!pattern.matches("onclick"));
+
+        let pattern = AttributePattern::Glob("*-*-id");
+
+        assert!(pattern.matches("aria-x-id"));
+        assert!(!pattern.matches("aria-id"));
+    }
+
+    #[test]
+    fn regex_is_not_anchored() {
+        let pattern = AttributePattern::Regex(CompiledRegex::new("^on|-old$").unwrap());
+
+        assert!(pattern.matches("onclick"));
+        assert!(pattern.matches("class-old"));
+        assert!(!pattern.matches("class"));
+    }
+}
@@ -2,15 +2,25 @@
 mod tests;
 
 use html_escape::{encode_double_quoted_attribute, encode_text};
-use log::trace;
-use snafu::ResultExt;
+use log::{trace, warn};
+use snafu::{ensure, ResultExt};
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::ops::Add;
 
+use super::attribute_pattern::AttributePattern;
+use super::condition::ElementCondition;
 use super::pipeline::ElementProcessingPipeline;
+use super::position::InsertPosition;
+use crate::css::StyleDeclarations;
 use crate::element_creating::ElementCreatingPipeline;
-use crate::html::HtmlContent;
-use crate::{CommandError, CssSelectorList, SubpipelineFailedSnafu, ValueSource};
+use crate::explain::Explanation;
+use crate::html::{node_pointer, HtmlContent};
+use crate::{
+    check_result_set, parse_html_fragment, CommandError, CssSelectorList, InvalidStyleValueSnafu,
+    InvalidTagNameSnafu, SelectorMatchedNothingSnafu, SubpipelineFailedSnafu,
+    UnexpectedMatchCountSnafu, ValueSource,
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum ElementProcessingCommand<'a> {
@@ -21,18 +31,104 @@ pub(crate) enum ElementProcessingCommand<'a> {
     /// and remove them from their parent nodes.
     /// Returns the input as result.
     RemoveElement(CssSelectorList<'a>),
+    /// Keep only those of the currently selected nodes which themselves match the given CSS selector
+    /// Returns the remaining (non-copied) nodes as result.
+    Filter(CssSelectorList<'a>),
+    /// Keep only those of the currently selected nodes which themselves do *not* match the given CSS selector
+    /// Returns the remaining (non-copied) nodes as result.
+    FilterNot(CssSelectorList<'a>),
+    /// Keep only the first of the currently selected nodes
+    /// Returns the remaining (non-copied) node as result.
+    First,
+    /// Keep only the last of the currently selected nodes
+    /// Returns the remaining (non-copied) node as result.
+    Last,
+    /// Keep only the n-th (starting at 1) of the currently selected nodes
+    /// Returns the remaining (non-copied) node as result.
+    Nth(usize),
+    /// Keep only the currently selected nodes from the first to the second position (starting at 1, both inclusive).
+    /// A missing start means "from the first", a missing end "till the last" node.
+    /// Returns the remaining (non-copied) nodes as result.
+    Slice(Option<usize>, Option<usize>),
+    /// Select the parents of the currently selected nodes
+    /// Returns the (de-duplicated) parents as result.
+    Parent,
+    /// Select the currently selected nodes themselves or their nearest ancestors matching the given CSS selector
+    /// Returns the (de-duplicated) matches as result.
+    Closest(CssSelectorList<'a>),
+    /// Select the element children of the currently selected nodes, optionally only those matching the given CSS selector
+    /// Returns the children as result.
+    Children(Option<CssSelectorList<'a>>),
+    /// Select the next element sibling of the currently selected nodes, skipping text and comments
+    /// Returns the siblings as result.
+    NextSibling,
+    /// Select the previous element sibling of the currently selected nodes, skipping text and comments
+    /// Returns the siblings as result.
+    PreviousSibling,
+    /// Select the root of the trees the currently selected nodes belong to
+    /// Returns the (de-duplicated) roots as result.
+    Root,
     /// runs a sub-pipeline on each element matching the given CSS selector
     /// Returns the input as result.
     ForEach(CssSelectorList<'a>, ElementProcessingPipeline<'a>),
+    /// runs the first sub-pipeline on each currently selected element satisfying the condition,
+    /// the (optional) second sub-pipeline on all others
+    /// Returns the input as result.
+    If(
+        ElementCondition<'a>,
+        ElementProcessingPipeline<'a>,
+        Option<ElementProcessingPipeline<'a>>,
+    ),
+    /// runs the first sub-pipeline on each currently selected element *not* satisfying the condition,
+    /// the (optional) second sub-pipeline on all others
+    /// Returns the input as result.
+    Unless(
+        ElementCondition<'a>,
+        ElementProcessingPipeline<'a>,
+        Option<ElementProcessingPipeline<'a>>,
+    ),
     /// runs a sub-pipeline and replaces each element matching the given CSS selector with the result of the pipeline
     /// Returns the input as result.
     ReplaceElement(CssSelectorList<'a>, ElementCreatingPipeline<'a>),
     /// Remove the given attribute from all currently selected nodes
     /// Returns the input as result.
     ClearAttribute(&'a str),
+    /// Remove all attributes matching any of the given patterns from all currently selected nodes
+    /// Returns the input as result.
+    ClearAttributes(Vec<AttributePattern<'a>>),
+    /// Remove all attributes *not* matching any of the given patterns from all currently selected nodes
+    /// Returns the input as result.
+    ClearAllAttributesExcept(Vec<AttributePattern<'a>>),
+    /// Rename the first given attribute to the second one, overwriting its previous value
+    /// Returns the input as result.
+    RenameAttribute(&'a str, &'a str),
+    /// Change the tag name of all currently selected nodes, keeping their attributes and children
+    /// Returns the input as result.
+    RenameElement(ValueSource<'a>),
     /// Remove all children of the currently selected nodes
     /// Returns the input as result
     ClearContent,
+    /// Add or Reset a single declaration in the `style` attribute, keeping all others.
+    /// An empty (or blank) value removes the declaration, like `RemoveStyle`.
+    /// Returns the input as result.
+    SetStyle(&'a str, ValueSource<'a>),
+    /// Remove all declarations of the given property from the `style` attribute,
+    /// dropping the attribute if no declaration is left
+    /// Returns the input as result.
+    RemoveStyle(&'a str),
+    /// Add the given classes to the `class` attribute of all currently selected nodes, unless already present
+    /// Returns the input as result.
+    AddClass(ValueSource<'a>),
+    /// Remove the given classes from the `class` attribute of all currently selected nodes,
+    /// dropping the attribute if no class is left
+    /// Returns the input as result.
+    RemoveClass(ValueSource<'a>),
+    /// Remove the given classes from the currently selected nodes having them, add them to all others
+    /// Returns the input as result.
+    ToggleClass(ValueSource<'a>),
+    /// Replace the first class by the second one in all currently selected nodes having it, keeping its position
+    /// Returns the input as result.
+    ReplaceClass(ValueSource<'a>, ValueSource<'a>),
     /// Add or Reset a given attribute with a new value
     /// Returns the input as result.
     SetAttribute(&'a str, ValueSource<'a>),
@@ -45,21 +141,218 @@ pub(crate) enum ElementProcessingCommand<'a> {
     /// adds a new comment as last child
     /// Returns the input as result.
     AppendComment(ValueSource<'a>),
-    /// runs a sub-pipeline and adds the result as last child
+    /// runs a sub-pipeline for each currently selected node and adds all resulting nodes as last children
     /// Returns the input as result.
     AppendElement(ElementCreatingPipeline<'a>),
+    /// Remove all children of the currently selected nodes and add the nodes parsed from an HTML fragment instead
+    /// Returns the input as result.
+    SetInnerHtml(ValueSource<'a>),
+    /// adds the nodes parsed from an HTML fragment as last children
+    /// Returns the input as result.
+    AppendHtml(ValueSource<'a>),
+    /// adds the nodes parsed from an HTML fragment as first children
+    /// Returns the input as result.
+    PrependHtml(ValueSource<'a>),
     /// adds a new text as first child
     /// Returns the input as result.
     PrependTextContent(ValueSource<'a>),
     /// adds a new comment as first child
     /// Returns the input as result.
     PrependComment(ValueSource<'a>),
-    /// runs a sub-pipeline and adds the result as first child
+    /// runs a sub-pipeline for each currently selected node and adds all resulting nodes as first children
     /// Returns the input as result.
     PrependElement(ElementCreatingPipeline<'a>),
+    /// runs a sub-pipeline for each currently selected node and adds all resulting nodes as previous siblings
+    /// Returns the input as result.
+    InsertBefore(ElementCreatingPipeline<'a>),
+    /// runs a sub-pipeline for each currently selected node and adds all resulting nodes as next siblings
+    /// Returns the input as result.
+    InsertAfter(ElementCreatingPipeline<'a>),
+    /// runs a sub-pipeline and moves each currently selected node into the first resulting element,
+    /// which takes the place of the node
+    /// Returns the input as result.
+    Wrap(ElementCreatingPipeline<'a>),
+    /// runs a sub-pipeline and moves all children of each currently selected node into the first resulting element,
+    /// which then becomes the only child
+    /// Returns the input as result.
+    WrapInner(ElementCreatingPipeline<'a>),
+    /// Replace each currently selected node by its children
+    /// Returns the moved children as result.
+    Unwrap,
+    /// Find all nodes matching the first CSS selector and move them, in document order,
+    /// to the given position relative to the first node matching the second CSS selector.
+    /// Both selectors are evaluated beginning at the input, before anything is moved.
+    /// Returns the input as result.
+    Move(CssSelectorList<'a>, CssSelectorList<'a>, InsertPosition),
+    /// Find all nodes matching the first CSS selector and insert copies of them, in document order,
+    /// at the given position relative to every node matching the second CSS selector.
+    /// Both selectors are evaluated beginning at the input, before anything is copied.
+    /// Returns the input as result.
+    Copy(CssSelectorList<'a>, CssSelectorList<'a>, InsertPosition),
+    /// Find all nodes, beginning at the input, that match the given CSS selector
+    /// and fail unless there are at least as many as the minimum and (if given) at most as many as the maximum.
+    /// Like `ExpectCount`, a single count given in the pipeline definition is both minimum and maximum,
+    /// without any count there has to be at least one node.
+    /// Returns the input as result.
+    Require(CssSelectorList<'a>, usize, Option<usize>),
+    /// Fail unless there are at least as many input nodes as the minimum
+    /// and (if given) at most as many as the maximum.
+    /// Returns the input as result.
+    ExpectCount(usize, Option<usize>),
 }
 
 impl<'a> ElementProcessingCommand<'a> {
+    /// canonical name of the command, as used in error messages
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ElementProcessingCommand::ExtractElement(..) => "EXTRACT-ELEMENT",
+            ElementProcessingCommand::RemoveElement(..) => "REMOVE-ELEMENT",
+            ElementProcessingCommand::Filter(..) => "FILTER",
+            ElementProcessingCommand::FilterNot(..) => "NOT",
+            ElementProcessingCommand::First => "FIRST",
+            ElementProcessingCommand::Last => "LAST",
+            ElementProcessingCommand::Nth(..) => "NTH",
+            ElementProcessingCommand::Slice(..) => "SLICE",
+            ElementProcessingCommand::Parent => "PARENT",
+            ElementProcessingCommand::Closest(..) => "CLOSEST",
+            ElementProcessingCommand::Children(..) => "CHILDREN",
+            ElementProcessingCommand::NextSibling => "NEXT-SIBLING",
+            ElementProcessingCommand::PreviousSibling => "PREV-SIBLING",
+            ElementProcessingCommand::Root => "ROOT",
+            ElementProcessingCommand::ForEach(..) => "FOR-EACH",
+            ElementProcessingCommand::If(..) => "IF",
+            ElementProcessingCommand::Unless(..) => "UNLESS",
+            ElementProcessingCommand::ReplaceElement(..) => "REPLACE-ELEMENT",
+            ElementProcessingCommand::ClearAttribute(..) => "CLEAR-ATTR",
+            ElementProcessingCommand::ClearAttributes(..) => "CLEAR-ATTR",
+            ElementProcessingCommand::ClearAllAttributesExcept(..) => "CLEAR-ALL-ATTRS-EXCEPT",
+            ElementProcessingCommand::RenameAttribute(..) => "RENAME-ATTR",
+            ElementProcessingCommand::RenameElement(..) => "RENAME-ELEMENT",
+            ElementProcessingCommand::ClearContent => "CLEAR-CONTENT",
+            ElementProcessingCommand::SetStyle(..) => "SET-STYLE",
+            ElementProcessingCommand::RemoveStyle(..) => "REMOVE-STYLE",
+            ElementProcessingCommand::AddClass(..) => "ADD-CLASS",
+            ElementProcessingCommand::RemoveClass(..) => "REMOVE-CLASS",
+            ElementProcessingCommand::ToggleClass(..) => "TOGGLE-CLASS",
+            ElementProcessingCommand::ReplaceClass(..) => "REPLACE-CLASS",
+            ElementProcessingCommand::SetAttribute(..) => "SET-ATTR",
+            ElementProcessingCommand::SetTextContent(..) => "SET-TEXT-CONTENT",
+            ElementProcessingCommand::AppendTextContent(..) => "APPEND-TEXT-CONTENT",
+            ElementProcessingCommand::AppendComment(..) => "APPEND-COMMENT",
+            ElementProcessingCommand::AppendElement(..) => "APPEND-ELEMENT",
+            ElementProcessingCommand::SetInnerHtml(..) => "SET-INNER-HTML",
+            ElementProcessingCommand::AppendHtml(..) => "APPEND-HTML",
+            ElementProcessingCommand::PrependHtml(..) => "PREPEND-HTML",
+            ElementProcessingCommand::PrependTextContent(..) => "PREPEND-TEXT-CONTENT",
+            ElementProcessingCommand::PrependComment(..) => "PREPEND-COMMENT",
+            ElementProcessingCommand::PrependElement(..) => "PREPEND-ELEMENT",
+            ElementProcessingCommand::InsertBefore(..) => "INSERT-BEFORE",
+            ElementProcessingCommand::InsertAfter(..) => "INSERT-AFTER",
+            ElementProcessingCommand::Wrap(..) => "WRAP",
+            ElementProcessingCommand::WrapInner(..) => "WRAP-INNER",
+            ElementProcessingCommand::Unwrap => "UNWRAP",
+            ElementProcessingCommand::Move(..) => "MOVE",
+            ElementProcessingCommand::Copy(..) => "COPY",
+            ElementProcessingCommand::Require(..) => "REQUIRE",
+            ElementProcessingCommand::ExpectCount(..) => "EXPECT-COUNT",
+        }
+    }
+
+    /// lets all sub-pipelines of the command fail if a selector matches nothing,
+    /// a command results in an empty result set or multiple values would be concatenated
+    pub(crate) fn make_strict(&mut self) {
+        match self {
+            ElementProcessingCommand::RenameElement(value_source)
+            | ElementProcessingCommand::SetStyle(_, value_source)
+            | ElementProcessingCommand::SetAttribute(_, value_source)
+            | ElementProcessingCommand::SetTextContent(value_source)
+            | ElementProcessingCommand::AppendTextContent(value_source)
+            | ElementProcessingCommand::AppendComment(value_source)
+            | ElementProcessingCommand::SetInnerHtml(value_source)
+            | ElementProcessingCommand::AppendHtml(value_source)
+            | ElementProcessingCommand::PrependHtml(value_source)
+            | ElementProcessingCommand::PrependTextContent(value_source)
+            | ElementProcessingCommand::PrependComment(value_source) => value_source.make_strict(),
+            ElementProcessingCommand::ForEach(_, pipeline) => pipeline.make_strict(),
+            ElementProcessingCommand::If(_, then_pipeline, else_pipeline)
+            | ElementProcessingCommand::Unless(_, then_pipeline, else_pipeline) => {
+                then_pipeline.make_strict();
+                if let Some(else_pipeline) = else_pipeline {
+                    else_pipeline.make_strict();
+                }
+            }
+            ElementProcessingCommand::ReplaceElement(_, pipeline)
+            | ElementProcessingCommand::AppendElement(pipeline)
+            | ElementProcessingCommand::PrependElement(pipeline)
+            | ElementProcessingCommand::InsertBefore(pipeline)
+            | ElementProcessingCommand::InsertAfter(pipeline)
+            | ElementProcessingCommand::Wrap(pipeline)
+            | ElementProcessingCommand::WrapInner(pipeline) => pipeline.make_strict(),
+            _ => (),
+        }
+    }
+
+    /// lets all sub-pipelines of the command record what each of their commands did
+    pub(crate) fn explain_into(&mut self, explanation: &Explanation) {
+        match self {
+            ElementProcessingCommand::ForEach(_, pipeline) => pipeline.explain_into(explanation),
+            ElementProcessingCommand::If(_, then_pipeline, else_pipeline)
+            | ElementProcessingCommand::Unless(_, then_pipeline, else_pipeline) => {
+                then_pipeline.explain_into(explanation);
+                if let Some(else_pipeline) = else_pipeline {
+                    else_pipeline.explain_into(explanation);
+                }
+            }
+            ElementProcessingCommand::ReplaceElement(_, pipeline)
+            | ElementProcessingCommand::AppendElement(pipeline)
+            | ElementProcessingCommand::PrependElement(pipeline)
+            | ElementProcessingCommand::InsertBefore(pipeline)
+            | ElementProcessingCommand::InsertAfter(pipeline)
+            | ElementProcessingCommand::Wrap(pipeline)
+            | ElementProcessingCommand::WrapInner(pipeline) => pipeline.explain_into(explanation),
+            _ => (),
+        }
+    }
+
+    /// executes the command, in strict mode failing if one of its selectors matches nothing
+    /// or if the result set is empty
+    pub(crate) fn execute_checked(
+        &self,
+        input: &Vec<rctree::Node<HtmlContent>>,
+        strict: bool,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        if strict {
+            self.ensure_selectors_match(input)?;
+        }
+
+        self.execute(input)
+            .and_then(|result| check_result_set(result, strict))
+    }
+
+    /// checks those selectors which are queried beginning at the input
+    fn ensure_selectors_match(
+        &self,
+        input: &[rctree::Node<HtmlContent>],
+    ) -> Result<(), CommandError> {
+        let matches_anything = match self {
+            ElementProcessingCommand::ExtractElement(selector)
+            | ElementProcessingCommand::RemoveElement(selector)
+            | ElementProcessingCommand::ForEach(selector, _)
+            | ElementProcessingCommand::ReplaceElement(selector, _) => {
+                !selector.query(input).is_empty()
+            }
+            ElementProcessingCommand::Move(source, target, position)
+            | ElementProcessingCommand::Copy(source, target, position) => {
+                !source.query(input).is_empty()
+                    && !Self::find_insert_targets(input, target, position).is_empty()
+            }
+            _ => true,
+        };
+        ensure!(matches_anything, SelectorMatchedNothingSnafu);
+
+        Ok(())
+    }
+
     /// perform the action defined by the command on the set of nodes
     /// and return the calculated results.
     /// For some command the output can be equal to the input,
@@ -72,6 +365,12 @@ impl<'a> ElementProcessingCommand<'a> {
             ElementProcessingCommand::ForEach(selector, pipeline) => {
                 Self::for_each(input, selector, pipeline)
             }
+            ElementProcessingCommand::If(condition, then_pipeline, else_pipeline) => {
+                Self::if_condition(input, condition, true, then_pipeline, else_pipeline)
+            }
+            ElementProcessingCommand::Unless(condition, then_pipeline, else_pipeline) => {
+                Self::if_condition(input, condition, false, then_pipeline, else_pipeline)
+            }
             ElementProcessingCommand::ReplaceElement(selector, pipeline) => {
                 Self::replace_element(input, selector, pipeline)
             }
@@ -81,13 +380,61 @@ impl<'a> ElementProcessingCommand<'a> {
             ElementProcessingCommand::RemoveElement(selector) => {
                 Self::remove_element(input, selector)
             }
+            ElementProcessingCommand::Filter(selector) => Self::filter(input, selector),
+            ElementProcessingCommand::FilterNot(selector) => Self::filter_not(input, selector),
+            ElementProcessingCommand::First => Self::slice(input, None, Some(1)),
+            ElementProcessingCommand::Last => Self::last(input),
+            ElementProcessingCommand::Nth(position) => {
+                Self::slice(input, Some(*position), Some(*position))
+            }
+            ElementProcessingCommand::Slice(start, end) => Self::slice(input, *start, *end),
+            ElementProcessingCommand::Parent => Self::parent(input),
+            ElementProcessingCommand::Closest(selector) => Self::closest(input, selector),
+            ElementProcessingCommand::Children(selector) => Self::children(input, selector),
+            ElementProcessingCommand::NextSibling => Self::next_sibling(input),
+            ElementProcessingCommand::PreviousSibling => Self::previous_sibling(input),
+            ElementProcessingCommand::Root => Self::root(input),
             ElementProcessingCommand::ClearAttribute(attribute) => {
                 Self::clear_attr(input, attribute)
             }
             ElementProcessingCommand::SetAttribute(attribute, value_source) => {
                 Self::set_attr(input, attribute, value_source)
             }
+            ElementProcessingCommand::ClearAttributes(patterns) => {
+                Self::clear_attrs(input, "CLEAR-ATTR", patterns, true)
+            }
+            ElementProcessingCommand::ClearAllAttributesExcept(patterns) => {
+                Self::clear_attrs(input, "CLEAR-ALL-ATTRS-EXCEPT", patterns, false)
+            }
+            ElementProcessingCommand::RenameAttribute(from, to) => {
+                Self::rename_attr(input, from, to)
+            }
+            ElementProcessingCommand::RenameElement(value_source) => {
+                Self::rename_element(input, value_source)
+            }
             ElementProcessingCommand::ClearContent => Self::clear_content(input),
+            ElementProcessingCommand::SetStyle(property, value_source) => {
+                Self::set_style(input, property, value_source)
+            }
+            ElementProcessingCommand::RemoveStyle(property) => Self::remove_style(input, property),
+            ElementProcessingCommand::AddClass(value_source) => {
+                Self::change_classes(input, "ADD-CLASS", value_source, HtmlContent::add_class)
+            }
+            ElementProcessingCommand::RemoveClass(value_source) => Self::change_classes(
+                input,
+                "REMOVE-CLASS",
+                value_source,
+                HtmlContent::remove_class,
+            ),
+            ElementProcessingCommand::ToggleClass(value_source) => Self::change_classes(
+                input,
+                "TOGGLE-CLASS",
+                value_source,
+                HtmlContent::toggle_class,
+            ),
+            ElementProcessingCommand::ReplaceClass(old_class, new_class) => {
+                Self::replace_class(input, old_class, new_class)
+            }
             ElementProcessingCommand::SetTextContent(value_source) => {
                 Self::set_text_content(input, value_source)
             }
@@ -100,6 +447,21 @@ impl<'a> ElementProcessingCommand<'a> {
             ElementProcessingCommand::AppendElement(pipeline) => {
                 Self::append_element(input, pipeline)
             }
+            ElementProcessingCommand::SetInnerHtml(value_source) => {
+                Self::insert_html(input, "SET-INNER-HTML", value_source, None)
+            }
+            ElementProcessingCommand::AppendHtml(value_source) => Self::insert_html(
+                input,
+                "APPEND-HTML",
+                value_source,
+                Some(InsertPosition::Append),
+            ),
+            ElementProcessingCommand::PrependHtml(value_source) => Self::insert_html(
+                input,
+                "PREPEND-HTML",
+                value_source,
+                Some(InsertPosition::Prepend),
+            ),
             ElementProcessingCommand::PrependTextContent(value_source) => {
                 Self::prepend_text_content(input, value_source)
             }
@@ -109,6 +471,25 @@ impl<'a> ElementProcessingCommand<'a> {
             ElementProcessingCommand::PrependElement(pipeline) => {
                 Self::prepend_element(input, pipeline)
             }
+            ElementProcessingCommand::InsertBefore(pipeline) => {
+                Self::insert_before(input, pipeline)
+            }
+            ElementProcessingCommand::InsertAfter(pipeline) => Self::insert_after(input, pipeline),
+            ElementProcessingCommand::Wrap(pipeline) => Self::wrap(input, pipeline),
+            ElementProcessingCommand::WrapInner(pipeline) => Self::wrap_inner(input, pipeline),
+            ElementProcessingCommand::Unwrap => Self::unwrap(input),
+            ElementProcessingCommand::Move(source, target, position) => {
+                Self::move_elements(input, source, target, position)
+            }
+            ElementProcessingCommand::Copy(source, target, position) => {
+                Self::copy_elements(input, source, target, position)
+            }
+            ElementProcessingCommand::Require(selector, min, max) => {
+                Self::require(input, selector, *min, *max)
+            }
+            ElementProcessingCommand::ExpectCount(min, max) => {
+                Self::expect_count(input, *min, *max)
+            }
         }
     }
 
@@ -118,7 +499,66 @@ impl<'a> ElementProcessingCommand<'a> {
         pipeline: &ElementProcessingPipeline,
     ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
         let queried_elements = selector.query(input);
-        let _ = pipeline.run_on(queried_elements);
+        pipeline
+            .run_on(queried_elements)
+            .context(SubpipelineFailedSnafu)?;
+
+        Ok(input.to_owned())
+    }
+
+    fn require(
+        input: &[rctree::Node<HtmlContent>],
+        selector: &CssSelectorList<'a>,
+        min: usize,
+        max: Option<usize>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running REQUIRE command using selector: {:#?}", selector);
+
+        Self::expect_count(&selector.query(input), min, max)?;
+
+        Ok(input.to_owned())
+    }
+
+    fn expect_count(
+        input: &[rctree::Node<HtmlContent>],
+        min: usize,
+        max: Option<usize>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        let count = input.len();
+        ensure!(
+            count >= min && max.is_none_or(|max| count <= max),
+            UnexpectedMatchCountSnafu { count, min, max }
+        );
+
+        Ok(input.to_owned())
+    }
+
+    fn if_condition(
+        input: &[rctree::Node<HtmlContent>],
+        condition: &ElementCondition<'a>,
+        expected: bool,
+        then_pipeline: &ElementProcessingPipeline,
+        else_pipeline: &Option<ElementProcessingPipeline>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running {} command using condition: {:#?}",
+            if expected { "IF" } else { "UNLESS" },
+            condition
+        );
+
+        for node in input {
+            let branch = if condition.evaluate(node)? == expected {
+                Some(then_pipeline)
+            } else {
+                else_pipeline.as_ref()
+            };
+
+            if let Some(pipeline) = branch {
+                pipeline
+                    .run_on(vec![rctree::Node::clone(node)])
+                    .context(SubpipelineFailedSnafu)?;
+            }
+        }
 
         Ok(input.to_owned())
     }
@@ -154,6 +594,137 @@ impl<'a> ElementProcessingCommand<'a> {
         Ok(input.to_owned())
     }
 
+    fn filter(
+        input: &[rctree::Node<HtmlContent>],
+        selector: &CssSelectorList<'a>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running FILTER command using selector: {:#?}", selector);
+
+        Ok(selector.filter(input))
+    }
+
+    fn filter_not(
+        input: &[rctree::Node<HtmlContent>],
+        selector: &CssSelectorList<'a>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running NOT command using selector: {:#?}", selector);
+
+        let findings = selector
+            .filter(input)
+            .iter()
+            .map(node_pointer)
+            .collect::<HashSet<_>>();
+
+        Ok(input
+            .iter()
+            .filter(|n| !findings.contains(&node_pointer(n)))
+            .map(rctree::Node::clone)
+            .collect::<Vec<_>>())
+    }
+
+    fn last(
+        input: &[rctree::Node<HtmlContent>],
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running LAST command");
+
+        Ok(input.last().cloned().into_iter().collect())
+    }
+
+    fn slice(
+        input: &[rctree::Node<HtmlContent>],
+        start: Option<usize>,
+        end: Option<usize>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running SLICE command from {:?} to {:?}", start, end);
+
+        // positions start at 1, so 0 can never be part of the result
+        let start = start.unwrap_or(1).max(1);
+        let end = end.unwrap_or(input.len());
+
+        Ok(input
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| (start..=end).contains(&(index + 1)))
+            .map(|(_, n)| rctree::Node::clone(n))
+            .collect::<Vec<_>>())
+    }
+
+    fn parent(
+        input: &[rctree::Node<HtmlContent>],
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running PARENT command");
+
+        Ok(Self::deduplicate(input.iter().filter_map(|n| n.parent())))
+    }
+
+    fn closest(
+        input: &[rctree::Node<HtmlContent>],
+        selector: &CssSelectorList<'a>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running CLOSEST command using selector: {:#?}", selector);
+
+        Ok(Self::deduplicate(selector.closest(input).into_iter()))
+    }
+
+    fn children(
+        input: &[rctree::Node<HtmlContent>],
+        selector: &Option<CssSelectorList<'a>>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running CHILDREN command using selector: {:#?}", selector);
+
+        let children = input
+            .iter()
+            .flat_map(|n| n.children())
+            .filter(|c| c.borrow().is_tag())
+            .collect::<Vec<_>>();
+
+        Ok(match selector {
+            Some(selector) => selector.filter(&children),
+            None => children,
+        })
+    }
+
+    fn next_sibling(
+        input: &[rctree::Node<HtmlContent>],
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running NEXT-SIBLING command");
+
+        Ok(Self::deduplicate(input.iter().filter_map(|n| {
+            n.following_siblings().skip(1).find(|s| s.borrow().is_tag())
+        })))
+    }
+
+    fn previous_sibling(
+        input: &[rctree::Node<HtmlContent>],
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running PREV-SIBLING command");
+
+        Ok(Self::deduplicate(input.iter().filter_map(|n| {
+            n.preceding_siblings().skip(1).find(|s| s.borrow().is_tag())
+        })))
+    }
+
+    fn root(
+        input: &[rctree::Node<HtmlContent>],
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running ROOT command");
+
+        Ok(Self::deduplicate(
+            input.iter().filter_map(|n| n.ancestors().last()),
+        ))
+    }
+
+    /// collects the nodes, keeping only the first occurrence of each node
+    fn deduplicate(
+        nodes: impl Iterator<Item = rctree::Node<HtmlContent>>,
+    ) -> Vec<rctree::Node<HtmlContent>> {
+        let mut seen = HashSet::new();
+
+        nodes
+            .filter(|node| seen.insert(node_pointer(node)))
+            .collect()
+    }
+
     fn replace_element(
         input: &[rctree::Node<HtmlContent>],
         selector: &CssSelectorList<'a>,
@@ -193,6 +764,81 @@ impl<'a> ElementProcessingCommand<'a> {
         Ok(input.clone())
     }
 
+    fn clear_attrs(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        command_name: &str,
+        patterns: &[AttributePattern],
+        remove_matching: bool,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running {} command for patterns: {:#?}",
+            command_name,
+            patterns
+        );
+
+        for node in input {
+            let mut data = node.borrow_mut();
+            for attribute in data.get_attribute_names() {
+                if patterns.iter().any(|p| p.matches(&attribute)) == remove_matching {
+                    data.clear_attribute(&attribute);
+                }
+            }
+        }
+
+        Ok(input.clone())
+    }
+
+    fn rename_attr(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running RENAME-ATTR command from {:#?} to {:#?}", from, to);
+        let from = String::from(from);
+
+        for node in input {
+            let mut data = node.borrow_mut();
+            if let Some(value) = data.get_attribute(&from) {
+                data.clear_attribute(&from);
+                data.set_attribute(to, value);
+            }
+        }
+
+        Ok(input.clone())
+    }
+
+    fn rename_element(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        value_source: &ValueSource,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running RENAME-ELEMENT command with value: {:#?}",
+            value_source
+        );
+
+        for node in input {
+            let name = value_source.render_joined(node)?;
+            let name = name.trim();
+
+            let mut characters = name.chars();
+            let is_valid = characters.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && characters.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !is_valid {
+                return InvalidTagNameSnafu { name }.fail();
+            }
+
+            node.borrow_mut().set_tag_name(name);
+            if node.borrow().is_void_element() && node.has_children() {
+                warn!(
+                    "Renamed element to void element {}, its children will be lost",
+                    name
+                );
+            }
+        }
+
+        Ok(input.clone())
+    }
+
     fn clear_content(
         input: &Vec<rctree::Node<HtmlContent>>,
     ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
@@ -207,6 +853,155 @@ impl<'a> ElementProcessingCommand<'a> {
         Ok(input.clone())
     }
 
+    fn set_style(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        property: &str,
+        value_source: &ValueSource,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running SET-STYLE command for property: {:#?} with value: {:#?}",
+            property,
+            value_source
+        );
+
+        for node in input {
+            let rendered_value = value_source.render_joined(node)?;
+            if !StyleDeclarations::is_valid_value(&rendered_value) {
+                return InvalidStyleValueSnafu {
+                    property,
+                    value: rendered_value,
+                }
+                .fail();
+            }
+
+            Self::change_style(node, |style| {
+                if rendered_value.trim().is_empty() {
+                    style.remove(property)
+                } else {
+                    style.set(property, &rendered_value)
+                }
+            });
+        }
+
+        Ok(input.clone())
+    }
+
+    fn remove_style(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        property: &str,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running REMOVE-STYLE command for property: {:#?}", property);
+
+        for node in input {
+            Self::change_style(node, |style| style.remove(property));
+        }
+
+        Ok(input.clone())
+    }
+
+    /// parses the `style` attribute of the node, applies the change and writes it back,
+    /// removing the attribute if no declaration is left
+    fn change_style(node: &rctree::Node<HtmlContent>, change: impl FnOnce(&mut StyleDeclarations)) {
+        let attribute = String::from("style");
+        let mut data = node.borrow_mut();
+        if !data.is_tag() {
+            return;
+        }
+
+        let mut style = data
+            .get_attribute(&attribute)
+            .map(|s| StyleDeclarations::parse(&s))
+            .unwrap_or_default();
+
+        let unchanged = style.clone();
+        change(&mut style);
+
+        if style == unchanged {
+            return;
+        }
+        if style.is_empty() {
+            data.clear_attribute(&attribute);
+        } else {
+            data.set_attribute(attribute, style.to_attribute_value());
+        }
+    }
+
+    fn change_classes(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        command_name: &str,
+        value_source: &ValueSource,
+        change: fn(&mut HtmlContent, &str),
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running {} command with value: {:#?}",
+            command_name,
+            value_source
+        );
+
+        for node in input {
+            for class in Self::render_classes(node, value_source)? {
+                change(&mut node.borrow_mut(), &class);
+            }
+        }
+
+        Ok(input.clone())
+    }
+
+    fn replace_class(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        old_class: &ValueSource,
+        new_class: &ValueSource,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running REPLACE-CLASS command for class: {:#?} with value: {:#?}",
+            old_class,
+            new_class
+        );
+
+        for node in input {
+            let old_classes = Self::render_classes(node, old_class)?;
+            let new_classes = Self::render_classes(node, new_class)?;
+
+            let mut data = node.borrow_mut();
+            for old_class in &old_classes {
+                if !data.has_class(old_class) {
+                    continue;
+                }
+
+                // the first new class takes the position of the old one, all others are appended
+                match new_classes.split_first() {
+                    Some((first, others)) => {
+                        data.replace_class(old_class, first);
+                        for other in others {
+                            data.add_class(other);
+                        }
+                    }
+                    None => data.remove_class(old_class),
+                }
+            }
+        }
+
+        Ok(input.clone())
+    }
+
+    /// renders the value source and splits the result into single, escaped class names
+    fn render_classes(
+        node: &rctree::Node<HtmlContent>,
+        value_source: &ValueSource,
+    ) -> Result<Vec<String>, CommandError> {
+        Ok(value_source
+            .render(node)
+            .context(SubpipelineFailedSnafu)?
+            .iter()
+            .flat_map(|value| {
+                value
+                    .split_ascii_whitespace()
+                    .map(|class| String::from(encode_double_quoted_attribute(class)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>())
+    }
+
     fn set_attr(
         input: &Vec<rctree::Node<HtmlContent>>,
         attribute: &str,
@@ -219,8 +1014,7 @@ impl<'a> ElementProcessingCommand<'a> {
         );
 
         for node in input {
-            let rendered_value = value_source.render(node).context(SubpipelineFailedSnafu)?;
-            let rendered_value = rendered_value.join("");
+            let rendered_value = value_source.render_joined(node)?;
             let rendered_value = String::from(encode_double_quoted_attribute(&rendered_value));
             let rendered_value = rendered_value.replace('\n', "\\n");
 
@@ -242,8 +1036,7 @@ impl<'a> ElementProcessingCommand<'a> {
         );
 
         for node in input {
-            let rendered_value = value_source.render(node).context(SubpipelineFailedSnafu)?;
-            let rendered_value = rendered_value.join("");
+            let rendered_value = value_source.render_joined(node)?;
             let rendered_value = String::from(encode_text(&rendered_value));
 
             let working_copy = rctree::Node::clone(node);
@@ -268,8 +1061,7 @@ impl<'a> ElementProcessingCommand<'a> {
         );
 
         for node in input {
-            let rendered_value = value_source.render(node).context(SubpipelineFailedSnafu)?;
-            let rendered_value = rendered_value.join("");
+            let rendered_value = value_source.render_joined(node)?;
             let rendered_value = String::from(encode_text(&rendered_value));
 
             let working_copy = rctree::Node::clone(node);
@@ -289,8 +1081,7 @@ impl<'a> ElementProcessingCommand<'a> {
         );
 
         for node in input {
-            let rendered_value = value_source.render(node).context(SubpipelineFailedSnafu)?;
-            let rendered_value = rendered_value.join("");
+            let rendered_value = value_source.render_joined(node)?;
             let rendered_value = rendered_value.replace("--", "\\x2D\\x2D");
 
             let working_copy = rctree::Node::clone(node);
@@ -307,14 +1098,39 @@ impl<'a> ElementProcessingCommand<'a> {
         trace!("Running APPEND-ELEMENT command");
 
         for node in input {
-            if let Some(new_element) = pipeline
-                .run_on(vec![])
-                .context(SubpipelineFailedSnafu)?
-                .pop()
-            {
-                let working_copy = rctree::Node::clone(node);
-                working_copy.append(new_element);
+            InsertPosition::Append.insert(node, Self::create_elements_for(node, pipeline)?);
+        }
+
+        Ok(input.clone())
+    }
+
+    /// parses the rendered HTML fragment for each node and inserts the resulting nodes at the given position,
+    /// replacing all children if there is no position
+    fn insert_html(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        command_name: &str,
+        value_source: &ValueSource,
+        position: Option<InsertPosition>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running {} command with value: {:#?}",
+            command_name,
+            value_source
+        );
+
+        for node in input {
+            let rendered_value = value_source.render_joined(node)?;
+            let fragment = parse_html_fragment(&rendered_value)?;
+
+            if position.is_none() {
+                for child in node.children() {
+                    child.detach()
+                }
             }
+
+            position
+                .unwrap_or(InsertPosition::Append)
+                .insert(node, fragment);
         }
 
         Ok(input.clone())
@@ -330,8 +1146,7 @@ impl<'a> ElementProcessingCommand<'a> {
         );
 
         for node in input {
-            let rendered_value = value_source.render(node).context(SubpipelineFailedSnafu)?;
-            let rendered_value = rendered_value.join("");
+            let rendered_value = value_source.render_joined(node)?;
             let rendered_value = String::from(encode_text(&rendered_value));
 
             let working_copy = rctree::Node::clone(node);
@@ -351,8 +1166,7 @@ impl<'a> ElementProcessingCommand<'a> {
         );
 
         for node in input {
-            let rendered_value = value_source.render(node).context(SubpipelineFailedSnafu)?;
-            let rendered_value = rendered_value.join("");
+            let rendered_value = value_source.render_joined(node)?;
             let rendered_value = rendered_value.replace("--", "\\x2D\\x2D");
 
             let working_copy = rctree::Node::clone(node);
@@ -366,21 +1180,269 @@ impl<'a> ElementProcessingCommand<'a> {
         input: &Vec<rctree::Node<HtmlContent>>,
         pipeline: &ElementCreatingPipeline,
     ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
-        trace!("Running APPEND-ELEMENT command");
+        trace!("Running PREPEND-ELEMENT command");
+
+        for node in input {
+            InsertPosition::Prepend.insert(node, Self::create_elements_for(node, pipeline)?);
+        }
+
+        Ok(input.clone())
+    }
+
+    /// runs the sub-pipeline with the target node as input
+    /// and returns deep copies of all resulting nodes, ready to be inserted
+    fn create_elements_for(
+        target: &rctree::Node<HtmlContent>,
+        pipeline: &ElementCreatingPipeline,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        Ok(pipeline
+            .run_on(vec![rctree::Node::clone(target)])
+            .context(SubpipelineFailedSnafu)?
+            .iter()
+            .map(|n| n.make_deep_copy())
+            .collect::<Vec<_>>())
+    }
+
+    fn insert_before(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        pipeline: &ElementCreatingPipeline,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running INSERT-BEFORE command");
 
         for node in input {
-            if let Some(new_element) = pipeline
-                .run_on(vec![])
-                .context(SubpipelineFailedSnafu)?
-                .pop()
-            {
-                let working_copy = rctree::Node::clone(node);
-                working_copy.prepend(new_element);
+            if node.parent().is_none() {
+                warn!("Can't insert siblings of a node without parent, skipping it");
+                continue;
             }
+
+            InsertPosition::Before.insert(node, Self::create_elements_for(node, pipeline)?);
         }
 
         Ok(input.clone())
     }
+
+    fn insert_after(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        pipeline: &ElementCreatingPipeline,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running INSERT-AFTER command");
+
+        for node in input {
+            if node.parent().is_none() {
+                warn!("Can't insert siblings of a node without parent, skipping it");
+                continue;
+            }
+
+            InsertPosition::After.insert(node, Self::create_elements_for(node, pipeline)?);
+        }
+
+        Ok(input.clone())
+    }
+
+    fn wrap(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        pipeline: &ElementCreatingPipeline,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running WRAP command");
+
+        for node in input {
+            if node.parent().is_none() {
+                warn!("Can't wrap a node without parent, skipping it");
+                continue;
+            }
+
+            if let Some(wrapper) = Self::create_wrapper(node, pipeline)? {
+                node.insert_before(rctree::Node::clone(&wrapper));
+                InsertPosition::Append.insert(&wrapper, vec![rctree::Node::clone(node)]);
+            }
+        }
+
+        Ok(input.clone())
+    }
+
+    fn wrap_inner(
+        input: &Vec<rctree::Node<HtmlContent>>,
+        pipeline: &ElementCreatingPipeline,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running WRAP-INNER command");
+
+        for node in input {
+            if let Some(wrapper) = Self::create_wrapper(node, pipeline)? {
+                for child in node.children().collect::<Vec<_>>() {
+                    child.detach();
+                    wrapper.append(child);
+                }
+
+                node.append(wrapper);
+            }
+        }
+
+        Ok(input.clone())
+    }
+
+    /// runs the sub-pipeline and returns the first created tag, if any
+    fn create_wrapper(
+        target: &rctree::Node<HtmlContent>,
+        pipeline: &ElementCreatingPipeline,
+    ) -> Result<Option<rctree::Node<HtmlContent>>, CommandError> {
+        let wrapper = Self::create_elements_for(target, pipeline)?
+            .into_iter()
+            .find(|n| n.borrow().is_tag());
+
+        if wrapper.is_none() {
+            warn!("Sub-Pipeline did not create any element to wrap with");
+        }
+
+        Ok(wrapper)
+    }
+
+    fn unwrap(
+        input: &Vec<rctree::Node<HtmlContent>>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!("Running UNWRAP command");
+
+        let mut result = vec![];
+
+        for node in input {
+            if node.parent().is_none() {
+                warn!("Can't unwrap a node without parent");
+                result.push(rctree::Node::clone(node));
+                continue;
+            }
+
+            for child in node.children().collect::<Vec<_>>() {
+                child.detach();
+                node.insert_before(rctree::Node::clone(&child));
+                result.push(child);
+            }
+
+            node.detach();
+        }
+
+        Ok(result)
+    }
+
+    fn move_elements(
+        input: &[rctree::Node<HtmlContent>],
+        source: &CssSelectorList<'a>,
+        target: &CssSelectorList<'a>,
+        position: &InsertPosition,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running MOVE command from {:#?} to {:#?} at {:?}",
+            source,
+            target,
+            position
+        );
+
+        let sources = Self::in_document_order(source.query(input));
+        let Some(target) = Self::find_insert_targets(input, target, position)
+            .into_iter()
+            .next()
+        else {
+            return Ok(input.to_owned());
+        };
+
+        // nested sources are moved along with their ancestor
+        let moved = Self::outermost(&sources)
+            .into_iter()
+            .filter(|s| {
+                let contains_target = target.ancestors().any(|a| a == *s);
+                if contains_target {
+                    warn!("Can't move a node into itself, skipping it");
+                }
+                !contains_target
+            })
+            .collect::<Vec<_>>();
+
+        position.insert(&target, moved);
+
+        Ok(input.to_owned())
+    }
+
+    fn copy_elements(
+        input: &[rctree::Node<HtmlContent>],
+        source: &CssSelectorList<'a>,
+        target: &CssSelectorList<'a>,
+        position: &InsertPosition,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+        trace!(
+            "Running COPY command from {:#?} to {:#?} at {:?}",
+            source,
+            target,
+            position
+        );
+
+        // nested sources are copied along with their ancestor
+        let sources = Self::outermost(&Self::in_document_order(source.query(input)));
+
+        // copy everything first, so copies never contain other copies
+        let copies = Self::find_insert_targets(input, target, position)
+            .into_iter()
+            .map(|t| {
+                let copies = sources.iter().map(|s| s.make_deep_copy()).collect();
+                (t, copies)
+            })
+            .collect::<Vec<_>>();
+
+        for (target, copies) in copies {
+            position.insert(&target, copies);
+        }
+
+        Ok(input.to_owned())
+    }
+
+    /// queries the targets of MOVE and COPY in document order,
+    /// skipping those nodes that can't have siblings if the position requires them
+    fn find_insert_targets(
+        input: &[rctree::Node<HtmlContent>],
+        target: &CssSelectorList<'a>,
+        position: &InsertPosition,
+    ) -> Vec<rctree::Node<HtmlContent>> {
+        let targets = Self::in_document_order(target.query(input))
+            .into_iter()
+            .filter(|t| match position {
+                InsertPosition::Before | InsertPosition::After if t.parent().is_none() => {
+                    warn!("Can't insert siblings of a node without parent, skipping it");
+                    false
+                }
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+
+        if targets.is_empty() {
+            warn!("No target found for {:#?}", target);
+        }
+
+        targets
+    }
+
+    /// the nodes which aren't descendants of other ones of them
+    fn outermost(nodes: &[rctree::Node<HtmlContent>]) -> Vec<rctree::Node<HtmlContent>> {
+        let all = nodes.iter().map(node_pointer).collect::<HashSet<_>>();
+
+        nodes
+            .iter()
+            .filter(|n| {
+                !n.ancestors()
+                    .skip(1)
+                    .any(|a| all.contains(&node_pointer(&a)))
+            })
+            .map(rctree::Node::clone)
+            .collect()
+    }
+
+    /// sorts the nodes by their position in their trees and removes duplicates
+    fn in_document_order(nodes: Vec<rctree::Node<HtmlContent>>) -> Vec<rctree::Node<HtmlContent>> {
+        let roots = Self::deduplicate(nodes.iter().filter_map(|n| n.ancestors().last()));
+        let wanted = nodes.iter().map(node_pointer).collect::<HashSet<_>>();
+
+        roots
+            .iter()
+            .flat_map(|r| r.descendants())
+            .filter(|d| wanted.contains(&node_pointer(d)))
+            .collect::<Vec<_>>()
+    }
 }
 
 impl<'a> Add<ElementProcessingCommand<'a>> for ElementProcessingCommand<'a> {
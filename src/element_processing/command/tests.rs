@@ -2,9 +2,12 @@ use crate::element_creating::{ElementCreatingCommand, ElementCreatingPipeline};
 use crate::html::HtmlRenderable;
 use crate::string_creating::{ElementSelectingCommand, ValueExtractingCommand};
 use crate::{
-    element_processing::{command::ElementProcessingCommand, pipeline::ElementProcessingPipeline},
-    load_inline_html, CssSelector, CssSelectorList, CssSelectorPath, CssSelectorStep,
-    StringValueCreatingPipeline, ValueSource,
+    element_processing::{
+        command::ElementProcessingCommand, pipeline::ElementProcessingPipeline, AttributePattern,
+        ElementCondition, InsertPosition,
+    },
+    load_inline_html, CommandError, CompiledRegex, CssSelector, CssSelectorList, CssSelectorPath,
+    CssSelectorStep, StringValueCreatingPipeline, ValueSource,
 };
 
 const TEST_HTML_DOCUMENT: &str = r#"<html>
@@ -113,8 +116,10 @@ fn clear_content() {
 
 #[test]
 fn set_attribute_from_string_over_existing_attr() {
-    let command =
-        ElementProcessingCommand::SetAttribute("data-test", ValueSource::StringValue("some text"));
+    let command = ElementProcessingCommand::SetAttribute(
+        "data-test",
+        ValueSource::StringValue("some text".into()),
+    );
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -130,8 +135,10 @@ fn set_attribute_from_string_over_existing_attr() {
 
 #[test]
 fn set_attribute_from_string_as_new_attr() {
-    let command =
-        ElementProcessingCommand::SetAttribute("data-fubar", ValueSource::StringValue("some text"));
+    let command = ElementProcessingCommand::SetAttribute(
+        "data-fubar",
+        ValueSource::StringValue("some text".into()),
+    );
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -172,7 +179,7 @@ fn set_attribute_from_other_attr_as_new_attr() {
 #[test]
 fn set_text_content_from_string_for_tag() {
     let command =
-        ElementProcessingCommand::SetTextContent(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::SetTextContent(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -189,7 +196,7 @@ fn set_text_content_from_string_for_tag() {
 #[test]
 fn set_text_content_from_string_for_empty_tag() {
     let command =
-        ElementProcessingCommand::SetTextContent(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::SetTextContent(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
 
@@ -227,7 +234,7 @@ fn set_text_content_from_attr_for_empty_tag() {
 #[test]
 fn set_text_content_from_string_for_tag_with_multiple_children() {
     let command =
-        ElementProcessingCommand::SetTextContent(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::SetTextContent(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(
         r#"<div data-test="foo" class="bar">Some <em>special</em> Content. <!-- rightly so --></div>"#,
@@ -245,8 +252,9 @@ fn set_text_content_from_string_for_tag_with_multiple_children() {
 
 #[test]
 fn append_text_content_from_string_for_tag() {
-    let command =
-        ElementProcessingCommand::AppendTextContent(ValueSource::StringValue("Other Content"));
+    let command = ElementProcessingCommand::AppendTextContent(ValueSource::StringValue(
+        "Other Content".into(),
+    ));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -283,8 +291,9 @@ fn append_text_content_from_string_for_empty_tag() {
 
 #[test]
 fn append_text_content_from_attr_for_empty_tag() {
-    let command =
-        ElementProcessingCommand::AppendTextContent(ValueSource::StringValue("Other Content"));
+    let command = ElementProcessingCommand::AppendTextContent(ValueSource::StringValue(
+        "Other Content".into(),
+    ));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
 
@@ -300,8 +309,9 @@ fn append_text_content_from_attr_for_empty_tag() {
 
 #[test]
 fn append_text_content_from_string_for_tag_with_multiple_children() {
-    let command =
-        ElementProcessingCommand::AppendTextContent(ValueSource::StringValue("Other Content"));
+    let command = ElementProcessingCommand::AppendTextContent(ValueSource::StringValue(
+        "Other Content".into(),
+    ));
 
     let root = load_inline_html(
         r#"<div data-test="foo" class="bar">Some <em>special</em> Content. <!-- rightly so --></div>"#,
@@ -322,7 +332,7 @@ fn append_text_content_from_string_for_tag_with_multiple_children() {
 #[test]
 fn append_comment_from_string_for_tag() {
     let command =
-        ElementProcessingCommand::AppendComment(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::AppendComment(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -341,7 +351,7 @@ fn append_comment_from_string_for_tag() {
 #[test]
 fn append_comment_from_string_for_empty_tag() {
     let command =
-        ElementProcessingCommand::AppendComment(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::AppendComment(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
 
@@ -379,7 +389,7 @@ fn append_comment_from_attr_for_empty_tag() {
 #[test]
 fn append_comment_from_string_for_tag_with_multiple_children() {
     let command =
-        ElementProcessingCommand::AppendComment(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::AppendComment(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(
         r#"<div data-test="foo" class="bar"><!-- rightly so -->Some <em>special</em> Content.</div>"#,
@@ -424,7 +434,7 @@ fn for_each_on_ul() {
         ))]),
         ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
             "data-test",
-            ValueSource::StringValue("x"),
+            ValueSource::StringValue("x".into()),
         )]),
     );
 
@@ -472,7 +482,7 @@ fn replace_element_using_load_file() {
             "replace-me",
         ))]),
         ElementCreatingPipeline::new(
-            ElementCreatingCommand::FromFile("tests/single_div.html"),
+            ElementCreatingCommand::FromFile("tests/single_div.html".into()),
             None,
         ),
     );
@@ -526,8 +536,9 @@ fn replace_element_using_query_replaced() {
 
 #[test]
 fn prepend_text_content_from_string_for_tag() {
-    let command =
-        ElementProcessingCommand::PrependTextContent(ValueSource::StringValue("Other Content"));
+    let command = ElementProcessingCommand::PrependTextContent(ValueSource::StringValue(
+        "Other Content".into(),
+    ));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -564,8 +575,9 @@ fn prepend_text_content_from_string_for_empty_tag() {
 
 #[test]
 fn prepend_text_content_from_attr_for_empty_tag() {
-    let command =
-        ElementProcessingCommand::PrependTextContent(ValueSource::StringValue("Other Content"));
+    let command = ElementProcessingCommand::PrependTextContent(ValueSource::StringValue(
+        "Other Content".into(),
+    ));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
 
@@ -581,8 +593,9 @@ fn prepend_text_content_from_attr_for_empty_tag() {
 
 #[test]
 fn prepend_text_content_from_string_for_tag_with_multiple_children() {
-    let command =
-        ElementProcessingCommand::PrependTextContent(ValueSource::StringValue("Other Content"));
+    let command = ElementProcessingCommand::PrependTextContent(ValueSource::StringValue(
+        "Other Content".into(),
+    ));
 
     let root = load_inline_html(
         r#"<div data-test="foo" class="bar">Some <em>special</em> Content. <!-- rightly so --></div>"#,
@@ -603,7 +616,7 @@ fn prepend_text_content_from_string_for_tag_with_multiple_children() {
 #[test]
 fn prepend_comment_from_string_for_tag() {
     let command =
-        ElementProcessingCommand::PrependComment(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::PrependComment(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar">Some Content</div>"#);
 
@@ -622,7 +635,7 @@ fn prepend_comment_from_string_for_tag() {
 #[test]
 fn prepend_comment_from_string_for_empty_tag() {
     let command =
-        ElementProcessingCommand::PrependComment(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::PrependComment(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
 
@@ -660,7 +673,7 @@ fn prepend_comment_from_attr_for_empty_tag() {
 #[test]
 fn prepend_comment_from_string_for_tag_with_multiple_children() {
     let command =
-        ElementProcessingCommand::PrependComment(ValueSource::StringValue("Other Content"));
+        ElementProcessingCommand::PrependComment(ValueSource::StringValue("Other Content".into()));
 
     let root = load_inline_html(
         r#"<div data-test="foo" class="bar"><!-- rightly so -->Some <em>special</em> Content.</div>"#,
@@ -696,3 +709,967 @@ fn prepend_element_from_create_for_tag() {
         String::from(r#"<div class="bar" data-test="foo"><div></div>Some Content</div>"#)
     );
 }
+
+#[test]
+fn if_runs_then_branch_on_matching_elements_only() {
+    let command = ElementProcessingCommand::If(
+        ElementCondition::HasAttribute("data-test"),
+        ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
+            "data-found",
+            ValueSource::StringValue("yes".into()),
+        )]),
+        None,
+    );
+
+    let root = load_inline_html(r#"<ul><li data-test="">1</li><li>2</li></ul>"#);
+    let items = root.children().collect::<Vec<_>>();
+
+    let result = command.execute(&items).unwrap();
+
+    assert_eq!(result, items);
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<ul><li data-found="yes" data-test="">1</li><li>2</li></ul>"#)
+    );
+}
+
+#[test]
+fn if_runs_else_branch_on_other_elements() {
+    let command = ElementProcessingCommand::If(
+        ElementCondition::Selects(ElementSelectingCommand::QueryElement(CssSelectorList::new(
+            vec![CssSelectorPath::single(CssSelector::for_element("em"))],
+        ))),
+        ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
+            "class",
+            ValueSource::StringValue("emphasized".into()),
+        )]),
+        Some(ElementProcessingPipeline::new(vec![
+            ElementProcessingCommand::SetAttribute(
+                "class",
+                ValueSource::StringValue("plain".into()),
+            ),
+        ])),
+    );
+
+    let root = load_inline_html(r#"<div><p><em>1</em></p><p>2</p></div>"#);
+    let paragraphs = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&paragraphs).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><p class="emphasized"><em>1</em></p><p class="plain">2</p></div>"#)
+    );
+}
+
+#[test]
+fn unless_runs_then_branch_on_non_matching_elements() {
+    let command = ElementProcessingCommand::Unless(
+        ElementCondition::Selects(ElementSelectingCommand::QueryElement(CssSelectorList::new(
+            vec![CssSelectorPath::single(CssSelector::for_element("meta"))],
+        ))),
+        ElementProcessingPipeline::new(vec![ElementProcessingCommand::AppendElement(
+            ElementCreatingPipeline::new(ElementCreatingCommand::CreateElement("meta"), None),
+        )]),
+        None,
+    );
+
+    let root = load_inline_html(r#"<html><head></head><body></body></html>"#);
+    let head = root.first_child().unwrap().first_child().unwrap();
+
+    let _ = command.execute(&vec![rctree::Node::clone(&head)]).unwrap();
+    let _ = command.execute(&vec![rctree::Node::clone(&head)]).unwrap();
+
+    assert_eq!(head.outer_html(), String::from(r#"<head><meta></head>"#));
+}
+
+#[test]
+fn filter_keeps_identity_of_matching_nodes() {
+    let command =
+        ElementProcessingCommand::Filter(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_class("keep"),
+        )]));
+
+    let root =
+        load_inline_html(r#"<ul><li class="keep">1</li><li>2</li><li class="keep">3</li></ul>"#);
+    let items = root.children().collect::<Vec<_>>();
+
+    let result = command.execute(&items).unwrap();
+
+    assert_eq!(result, vec![items[0].clone(), items[2].clone()]);
+}
+
+#[test]
+fn filter_not_keeps_identity_of_non_matching_nodes() {
+    let command =
+        ElementProcessingCommand::FilterNot(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_class("keep"),
+        )]));
+
+    let root =
+        load_inline_html(r#"<ul><li class="keep">1</li><li>2</li><li class="keep">3</li></ul>"#);
+    let items = root.children().collect::<Vec<_>>();
+
+    let result = command.execute(&items).unwrap();
+
+    assert_eq!(result, vec![items[1].clone()]);
+}
+
+#[test]
+fn filter_not_many_nodes() {
+    let command =
+        ElementProcessingCommand::FilterNot(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_class("keep"),
+        )]));
+
+    let html = format!(
+        "<ul>{}</ul>",
+        r#"<li class="keep">1</li><li>2</li>"#.repeat(10_000)
+    );
+    let root = load_inline_html(&html);
+    let items = root.children().collect::<Vec<_>>();
+
+    let result = command.execute(&items).unwrap();
+
+    assert_eq!(result.len(), 10_000);
+    assert_eq!(result[0], items[1]);
+}
+
+#[test]
+fn first_last_and_nth_pick_single_node() {
+    let root = load_inline_html(r#"<ul><li>1</li><li>2</li><li>3</li></ul>"#);
+    let items = root.children().collect::<Vec<_>>();
+
+    assert_eq!(
+        ElementProcessingCommand::First.execute(&items).unwrap(),
+        vec![items[0].clone()]
+    );
+    assert_eq!(
+        ElementProcessingCommand::Last.execute(&items).unwrap(),
+        vec![items[2].clone()]
+    );
+    assert_eq!(
+        ElementProcessingCommand::Nth(2).execute(&items).unwrap(),
+        vec![items[1].clone()]
+    );
+    assert!(ElementProcessingCommand::Nth(0)
+        .execute(&items)
+        .unwrap()
+        .is_empty());
+    assert!(ElementProcessingCommand::Nth(4)
+        .execute(&items)
+        .unwrap()
+        .is_empty());
+    assert!(ElementProcessingCommand::First
+        .execute(&vec![])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn slice_picks_inclusive_range() {
+    let root = load_inline_html(r#"<ul><li>1</li><li>2</li><li>3</li><li>4</li></ul>"#);
+    let items = root.children().collect::<Vec<_>>();
+
+    assert_eq!(
+        ElementProcessingCommand::Slice(Some(2), Some(3))
+            .execute(&items)
+            .unwrap(),
+        items[1..3].to_vec()
+    );
+    assert_eq!(
+        ElementProcessingCommand::Slice(None, Some(2))
+            .execute(&items)
+            .unwrap(),
+        items[0..2].to_vec()
+    );
+    assert_eq!(
+        ElementProcessingCommand::Slice(Some(3), None)
+            .execute(&items)
+            .unwrap(),
+        items[2..].to_vec()
+    );
+    assert!(ElementProcessingCommand::Slice(Some(3), Some(2))
+        .execute(&items)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn parent_returns_deduplicated_parents() {
+    let root = load_inline_html(r#"<div><ul><li>1</li><li>2</li></ul><p></p></div>"#);
+    let list = root.first_child().unwrap();
+    let items = list.children().collect::<Vec<_>>();
+
+    let result = ElementProcessingCommand::Parent.execute(&items).unwrap();
+
+    assert_eq!(result, vec![list]);
+}
+
+#[test]
+fn parent_deduplicates_many_siblings() {
+    let root = load_inline_html(&format!("<ul>{}</ul>", "<li></li>".repeat(20_000)));
+    let items = root.children().collect::<Vec<_>>();
+
+    let result = ElementProcessingCommand::Parent.execute(&items).unwrap();
+
+    assert_eq!(result, vec![root]);
+}
+
+#[test]
+fn closest_returns_self_or_nearest_matching_ancestor() {
+    let command =
+        ElementProcessingCommand::Closest(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_element("figure"),
+        )]));
+
+    let root = load_inline_html(
+        r#"<main><figure id="outer"><figure id="inner"><div><img src=""></div></figure></figure><img src=""></main>"#,
+    );
+    let images = CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+        "img",
+    ))])
+    .query(&[rctree::Node::clone(&root)]);
+
+    let mut result = command.execute(&images).unwrap();
+
+    assert_eq!(result.len(), 1);
+    let first_result = result.pop().unwrap();
+    assert_eq!(
+        first_result.outer_html(),
+        String::from(r#"<figure id="inner"><div><img src=""></div></figure>"#)
+    );
+}
+
+#[test]
+fn children_returns_element_children() {
+    let root = load_inline_html(r#"<ul>Text<li class="a">1</li><!-- Comment --><li>2</li></ul>"#);
+    let items = root
+        .children()
+        .filter(|c| c.borrow().is_tag())
+        .collect::<Vec<_>>();
+
+    let all = ElementProcessingCommand::Children(None)
+        .execute(&vec![rctree::Node::clone(&root)])
+        .unwrap();
+    let filtered = ElementProcessingCommand::Children(Some(CssSelectorList::new(vec![
+        CssSelectorPath::single(CssSelector::for_class("a")),
+    ])))
+    .execute(&vec![rctree::Node::clone(&root)])
+    .unwrap();
+
+    assert_eq!(all, items);
+    assert_eq!(filtered, vec![items[0].clone()]);
+}
+
+#[test]
+fn siblings_skip_text_and_comments() {
+    let root = load_inline_html(r#"<ul><li>1</li> <!-- Comment --> <li>2</li> <li>3</li></ul>"#);
+    let items = root
+        .children()
+        .filter(|c| c.borrow().is_tag())
+        .collect::<Vec<_>>();
+
+    let next = ElementProcessingCommand::NextSibling
+        .execute(&items)
+        .unwrap();
+    let previous = ElementProcessingCommand::PreviousSibling
+        .execute(&items)
+        .unwrap();
+
+    assert_eq!(next, vec![items[1].clone(), items[2].clone()]);
+    assert_eq!(previous, vec![items[0].clone(), items[1].clone()]);
+}
+
+#[test]
+fn root_returns_root_of_tree() {
+    let root = load_inline_html(r#"<div><ul><li>1</li><li>2</li></ul></div>"#);
+    let items = root.first_child().unwrap().children().collect::<Vec<_>>();
+
+    let result = ElementProcessingCommand::Root.execute(&items).unwrap();
+
+    assert_eq!(result, vec![root]);
+}
+
+#[test]
+fn insert_before_adds_previous_sibling() {
+    let command = ElementProcessingCommand::InsertBefore(ElementCreatingPipeline::new(
+        ElementCreatingCommand::CreateElement("hr"),
+        None,
+    ));
+
+    let root = load_inline_html(r#"<div><p>1</p><p>2</p></div>"#);
+    let paragraphs = root.children().collect::<Vec<_>>();
+
+    let result = command.execute(&paragraphs).unwrap();
+
+    assert_eq!(result, paragraphs);
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><hr><p>1</p><hr><p>2</p></div>"#)
+    );
+}
+
+#[test]
+fn insert_after_adds_next_sibling() {
+    let command = ElementProcessingCommand::InsertAfter(ElementCreatingPipeline::new(
+        ElementCreatingCommand::CreateElement("hr"),
+        None,
+    ));
+
+    let root = load_inline_html(r#"<div><p>1</p><p>2</p></div>"#);
+    let paragraphs = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&paragraphs).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><p>1</p><hr><p>2</p><hr></div>"#)
+    );
+}
+
+#[test]
+fn insert_before_and_after_skip_node_without_parent() {
+    for command in [
+        ElementProcessingCommand::InsertBefore(ElementCreatingPipeline::new(
+            ElementCreatingCommand::CreateElement("hr"),
+            None,
+        )),
+        ElementProcessingCommand::InsertAfter(ElementCreatingPipeline::new(
+            ElementCreatingCommand::CreateElement("hr"),
+            None,
+        )),
+    ] {
+        let root = load_inline_html(r#"<div><p>1</p></div>"#);
+
+        let result = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+        assert_eq!(result, vec![rctree::Node::clone(&root)]);
+        assert!(root.previous_sibling().is_none());
+        assert!(root.next_sibling().is_none());
+        assert_eq!(root.outer_html(), String::from(r#"<div><p>1</p></div>"#));
+    }
+}
+
+#[test]
+fn wrap_moves_node_into_new_element() {
+    let command = ElementProcessingCommand::Wrap(ElementCreatingPipeline::new(
+        ElementCreatingCommand::CreateElement("div"),
+        Some(vec![ElementProcessingCommand::SetAttribute(
+            "class",
+            ValueSource::StringValue("table-scroll".into()),
+        )]),
+    ));
+
+    let root = load_inline_html(r#"<body><table><tr><td>1</td></tr></table><p></p></body>"#);
+    let table = root.first_child().unwrap();
+
+    let result = command.execute(&vec![rctree::Node::clone(&table)]).unwrap();
+
+    assert_eq!(result, vec![table]);
+    assert_eq!(
+        root.outer_html(),
+        String::from(
+            r#"<body><div class="table-scroll"><table><tr><td>1</td></tr></table></div><p></p></body>"#
+        )
+    );
+}
+
+#[test]
+fn wrap_skips_node_without_parent() {
+    let command = ElementProcessingCommand::Wrap(ElementCreatingPipeline::new(
+        ElementCreatingCommand::CreateElement("div"),
+        None,
+    ));
+
+    let root = load_inline_html(r#"<table><tr><td>1</td></tr></table>"#);
+
+    let result = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(result, vec![rctree::Node::clone(&root)]);
+    assert!(root.parent().is_none());
+    assert!(root.previous_sibling().is_none());
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<table><tr><td>1</td></tr></table>"#)
+    );
+}
+
+#[test]
+fn wrap_inner_moves_children_into_new_element() {
+    let command = ElementProcessingCommand::WrapInner(ElementCreatingPipeline::new(
+        ElementCreatingCommand::CreateElement("span"),
+        None,
+    ));
+
+    let root = load_inline_html(r#"<p>Some <em>first</em> text</p>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<p><span>Some <em>first</em> text</span></p>"#)
+    );
+}
+
+#[test]
+fn unwrap_replaces_node_by_its_children() {
+    let command = ElementProcessingCommand::Unwrap;
+
+    let root = load_inline_html(r#"<p>Some <font color="red">first <em>red</em></font> text</p>"#);
+    let font = root.children().nth(1).unwrap();
+
+    let result = command.execute(&vec![font]).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].outer_html(), String::from(r#"<em>red</em>"#));
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<p>Some first <em>red</em> text</p>"#)
+    );
+}
+
+#[test]
+fn append_element_inserts_all_created_nodes_into_each_target() {
+    let command = ElementProcessingCommand::AppendElement(ElementCreatingPipeline::new(
+        ElementCreatingCommand::FromReplaced(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_element("em"),
+        )])),
+        None,
+    ));
+
+    let root = load_inline_html(r#"<div><p><em>1</em><em>2</em></p><p><em>3</em></p></div>"#);
+    let paragraphs = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&paragraphs).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(
+            r#"<div><p><em>1</em><em>2</em><em>1</em><em>2</em></p><p><em>3</em><em>3</em></p></div>"#
+        )
+    );
+}
+
+#[test]
+fn prepend_element_keeps_order_of_created_nodes() {
+    let command = ElementProcessingCommand::PrependElement(ElementCreatingPipeline::new(
+        ElementCreatingCommand::FromReplaced(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_element("em"),
+        )])),
+        None,
+    ));
+
+    let root = load_inline_html(r#"<p>Text <em>1</em><em>2</em></p>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<p><em>1</em><em>2</em>Text <em>1</em><em>2</em></p>"#)
+    );
+}
+
+#[test]
+fn move_relocates_sources_in_document_order() {
+    let command = ElementProcessingCommand::Move(
+        CssSelectorList::new(vec![
+            CssSelectorPath::single(CssSelector::for_element("b")),
+            CssSelectorPath::single(CssSelector::for_element("a")),
+        ]),
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "footer",
+        ))]),
+        InsertPosition::Append,
+    );
+
+    let root = load_inline_html(r#"<div><a>1</a><b>2</b><a>3</a><footer></footer></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><footer><a>1</a><b>2</b><a>3</a></footer></div>"#)
+    );
+}
+
+#[test]
+fn move_many_sources_in_document_order() {
+    let command = ElementProcessingCommand::Move(
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "li",
+        ))]),
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "ol",
+        ))]),
+        InsertPosition::Append,
+    );
+
+    let items = (0..10_000)
+        .map(|i| format!("<li>{}</li>", i))
+        .collect::<String>();
+    let root = load_inline_html(&format!("<div><ul>{}</ul><ol></ol></div>", items));
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        format!("<div><ul></ul><ol>{}</ol></div>", items)
+    );
+}
+
+#[test]
+fn move_uses_only_first_target() {
+    let command = ElementProcessingCommand::Move(
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "em",
+        ))]),
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element("p"))]),
+        InsertPosition::Before,
+    );
+
+    let root = load_inline_html(r#"<div><p>1</p><p>2 <em>a</em><em>b</em></p></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><em>a</em><em>b</em><p>1</p><p>2 </p></div>"#)
+    );
+}
+
+#[test]
+fn move_skips_source_containing_target() {
+    let command = ElementProcessingCommand::Move(
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "section",
+        ))]),
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element("p"))]),
+        InsertPosition::Append,
+    );
+
+    let root = load_inline_html(r#"<div><section><p>1</p></section><section></section></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><section><p>1<section></section></p></section></div>"#)
+    );
+}
+
+#[test]
+fn copy_inserts_copies_at_every_target() {
+    let command = ElementProcessingCommand::Copy(
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "em",
+        ))]),
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element("p"))]),
+        InsertPosition::After,
+    );
+
+    let root = load_inline_html(r#"<div><p><em>1</em></p><p>2</p></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><p><em>1</em></p><em>1</em><p>2</p><em>1</em></div>"#)
+    );
+}
+
+#[test]
+fn copy_keeps_nested_sources_inside_their_ancestor() {
+    let command = ElementProcessingCommand::Copy(
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "li",
+        ))]),
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "ol",
+        ))]),
+        InsertPosition::Prepend,
+    );
+
+    let root = load_inline_html(r#"<div><ul><li>1<ul><li>2</li></ul></li></ul><ol></ol></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(
+            r#"<div><ul><li>1<ul><li>2</li></ul></li></ul><ol><li>1<ul><li>2</li></ul></li></ol></div>"#
+        )
+    );
+}
+
+#[test]
+fn add_class_adds_each_rendered_class_once() {
+    let command = ElementProcessingCommand::AddClass(ValueSource::StringValue("b  c a".into()));
+
+    let root = load_inline_html(r#"<div class="a"></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div class="a b c"></div>"#)
+    );
+}
+
+#[test]
+fn add_class_uses_sub_pipeline() {
+    let command = ElementProcessingCommand::AddClass(ValueSource::SubPipeline(
+        StringValueCreatingPipeline::new(
+            ElementSelectingCommand::UseElement,
+            ValueExtractingCommand::GetAttribute("data-state"),
+        ),
+    ));
+
+    let root = load_inline_html(r#"<div data-state="open"></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div class="open" data-state="open"></div>"#)
+    );
+}
+
+#[test]
+fn remove_class_removes_all_occurrences() {
+    let command = ElementProcessingCommand::RemoveClass(ValueSource::StringValue("a".into()));
+
+    let root = load_inline_html(r#"<div class="a b a"></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(root.outer_html(), String::from(r#"<div class="b"></div>"#));
+}
+
+#[test]
+fn toggle_class_works_per_element() {
+    let command = ElementProcessingCommand::ToggleClass(ValueSource::StringValue("open".into()));
+
+    let root = load_inline_html(r#"<ul><li class="open">1</li><li>2</li></ul>"#);
+    let items = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&items).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<ul><li>1</li><li class="open">2</li></ul>"#)
+    );
+}
+
+#[test]
+fn replace_class_only_changes_elements_having_the_class() {
+    let command = ElementProcessingCommand::ReplaceClass(
+        ValueSource::StringValue("btn-default".into()),
+        ValueSource::StringValue("btn-primary".into()),
+    );
+
+    let root = load_inline_html(
+        r#"<div><a class="btn btn-default large">1</a><a class="btn">2</a></div>"#,
+    );
+    let links = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&links).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><a class="btn btn-primary large">1</a><a class="btn">2</a></div>"#)
+    );
+}
+
+#[test]
+fn set_style_keeps_other_declarations() {
+    let command =
+        ElementProcessingCommand::SetStyle("max-width", ValueSource::StringValue("100%".into()));
+
+    let root = load_inline_html(r#"<img style="border:0;max-width:50% !important">"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<img style="border:0; max-width: 100% !important">"#)
+    );
+}
+
+#[test]
+fn set_style_with_empty_value_removes_declaration() {
+    let root = load_inline_html(
+        r#"<div><p style="color:red;margin:0">1</p><p style="color:blue">2</p></div>"#,
+    );
+    let paragraphs = root.children().collect::<Vec<_>>();
+
+    for value in ["", "  "] {
+        let command =
+            ElementProcessingCommand::SetStyle("color", ValueSource::StringValue(value.into()));
+
+        let _ = command.execute(&paragraphs).unwrap();
+    }
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><p style="margin:0">1</p><p>2</p></div>"#)
+    );
+}
+
+#[test]
+fn set_style_rejects_value_adding_declarations() {
+    let command = ElementProcessingCommand::SetStyle(
+        "color",
+        ValueSource::StringValue("red; display:none".into()),
+    );
+
+    let root = load_inline_html(r#"<p style="margin:0">1</p>"#);
+
+    let result = command.execute(&vec![rctree::Node::clone(&root)]);
+
+    assert!(matches!(
+        result,
+        Err(CommandError::InvalidStyleValue { .. })
+    ));
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<p style="margin:0">1</p>"#)
+    );
+}
+
+#[test]
+fn set_style_adds_style_attribute() {
+    let command =
+        ElementProcessingCommand::SetStyle("display", ValueSource::StringValue("none".into()));
+
+    let root = load_inline_html(r#"<div></div>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div style="display: none"></div>"#)
+    );
+}
+
+#[test]
+fn remove_style_drops_empty_style_attribute() {
+    let command = ElementProcessingCommand::RemoveStyle("display");
+
+    let root = load_inline_html(
+        r#"<div><p style="display:none">1</p><p style="display: none; color: red">2</p></div>"#,
+    );
+    let paragraphs = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&paragraphs).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><p>1</p><p style="color: red">2</p></div>"#)
+    );
+}
+
+#[test]
+fn clear_attrs_removes_all_matching_attributes() {
+    let command = ElementProcessingCommand::ClearAttributes(vec![
+        AttributePattern::Glob("on*"),
+        AttributePattern::Regex(CompiledRegex::new("^data-v-").unwrap()),
+    ]);
+
+    let root = load_inline_html(
+        r#"<a href="/" onclick="track()" onmouseover="x()" data-v-1a2b="" data-id="1">Link</a>"#,
+    );
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<a data-id="1" href="/">Link</a>"#)
+    );
+}
+
+#[test]
+fn clear_all_attrs_except_keeps_matching_attributes() {
+    let command = ElementProcessingCommand::ClearAllAttributesExcept(vec![
+        AttributePattern::Name("href"),
+        AttributePattern::Glob("aria-*"),
+    ]);
+
+    let root = load_inline_html(
+        r#"<a href="/" class="link" style="color: red" aria-label="Home" target="_blank">Link</a>"#,
+    );
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<a aria-label="Home" href="/">Link</a>"#)
+    );
+}
+
+#[test]
+fn rename_attr_overwrites_target_attribute() {
+    let command = ElementProcessingCommand::RenameAttribute("data-src", "src");
+
+    let root = load_inline_html(
+        r#"<div><img src="placeholder.gif" data-src="photo.jpg"><img src="logo.png"></div>"#,
+    );
+    let images = root.children().collect::<Vec<_>>();
+
+    let _ = command.execute(&images).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<div><img src="photo.jpg"><img src="logo.png"></div>"#)
+    );
+}
+
+#[test]
+fn rename_element_keeps_attributes_and_children() {
+    let command =
+        ElementProcessingCommand::RenameElement(ValueSource::StringValue("strong".into()));
+
+    let root = load_inline_html(r#"<p>Some <b class="x">bold <i>text</i></b></p>"#);
+    let bold = root.children().nth(1).unwrap();
+
+    let _ = command.execute(&vec![bold]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<p>Some <strong class="x">bold <i>text</i></strong></p>"#)
+    );
+}
+
+#[test]
+fn rename_element_fails_on_invalid_name() {
+    let command = ElementProcessingCommand::RenameElement(ValueSource::StringValue("a b".into()));
+
+    let root = load_inline_html(r#"<b>bold</b>"#);
+
+    assert!(command.execute(&vec![root]).is_err());
+}
+
+#[test]
+fn set_inner_html_replaces_children() {
+    let command = ElementProcessingCommand::SetInnerHtml(ValueSource::StringValue(
+        "<em>new</em> content".into(),
+    ));
+
+    let root = load_inline_html(r#"<p>Some <b>old</b> content</p>"#);
+
+    let _ = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<p><em>new</em> content</p>"#)
+    );
+}
+
+#[test]
+fn append_and_prepend_html_keep_existing_children() {
+    let root = load_inline_html(r#"<ul><li>2</li></ul>"#);
+
+    let _ = ElementProcessingCommand::AppendHtml(ValueSource::StringValue(
+        "<li>3</li><li>4</li>".into(),
+    ))
+    .execute(&vec![rctree::Node::clone(&root)])
+    .unwrap();
+    let _ = ElementProcessingCommand::PrependHtml(ValueSource::StringValue(
+        "<li>0</li><li>1</li>".into(),
+    ))
+    .execute(&vec![rctree::Node::clone(&root)])
+    .unwrap();
+
+    assert_eq!(
+        root.outer_html(),
+        String::from(r#"<ul><li>0</li><li>1</li><li>2</li><li>3</li><li>4</li></ul>"#)
+    );
+}
+
+#[test]
+fn require_passes_input_if_count_matches() {
+    let command = ElementProcessingCommand::Require(
+        CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+            "li",
+        ))]),
+        3,
+        Some(3),
+    );
+
+    let root = load_inline_html(TEST_HTML_DOCUMENT);
+
+    let result = command.execute(&vec![rctree::Node::clone(&root)]).unwrap();
+
+    assert_eq!(result, vec![root]);
+}
+
+#[test]
+fn require_fails_if_count_deviates() {
+    let root = load_inline_html(TEST_HTML_DOCUMENT);
+    let selector = CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+        "li",
+    ))]);
+
+    for (min, max) in [(4, None), (1, Some(2))] {
+        let command = ElementProcessingCommand::Require(selector.clone(), min, max);
+
+        assert!(matches!(
+            command.execute(&vec![rctree::Node::clone(&root)]),
+            Err(CommandError::UnexpectedMatchCount { count: 3, .. })
+        ));
+    }
+}
+
+#[test]
+fn expect_count_checks_input() {
+    let root = load_inline_html(TEST_HTML_DOCUMENT);
+    let input = vec![root.clone(), root];
+
+    assert!(ElementProcessingCommand::ExpectCount(2, Some(2))
+        .execute(&input)
+        .is_ok());
+    assert!(ElementProcessingCommand::ExpectCount(1, Some(1))
+        .execute(&input)
+        .is_err());
+    assert!(ElementProcessingCommand::ExpectCount(3, None)
+        .execute(&input)
+        .is_err());
+}
+
+#[test]
+fn strict_mode_fails_if_selector_matches_nothing() {
+    let command = ElementProcessingCommand::RemoveElement(CssSelectorList::new(vec![
+        CssSelectorPath::single(CssSelector::for_id("cookie-banner")),
+    ]));
+
+    let root = load_inline_html(TEST_HTML_DOCUMENT);
+
+    assert!(command
+        .execute_checked(&vec![rctree::Node::clone(&root)], false)
+        .is_ok());
+    assert!(matches!(
+        command.execute_checked(&vec![rctree::Node::clone(&root)], true),
+        Err(CommandError::SelectorMatchedNothing { .. })
+    ));
+}
+
+#[test]
+fn strict_mode_fails_on_empty_result_set() {
+    let command =
+        ElementProcessingCommand::Filter(CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_element("p"),
+        )]));
+
+    let root = load_inline_html(TEST_HTML_DOCUMENT);
+
+    assert_eq!(
+        command
+            .execute_checked(&vec![rctree::Node::clone(&root)], false)
+            .unwrap(),
+        vec![]
+    );
+    assert!(matches!(
+        command.execute_checked(&vec![rctree::Node::clone(&root)], true),
+        Err(CommandError::EmptyResultSet { .. })
+    ));
+}
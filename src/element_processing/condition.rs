@@ -0,0 +1,196 @@
+use log::trace;
+use snafu::ResultExt;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use crate::html::HtmlContent;
+use crate::string_creating::{ElementSelectingCommand, StringValueCreatingPipeline};
+use crate::{CommandError, SubpipelineFailedSnafu};
+
+/// Operator used to compare the result of a string value creating pipeline against a fixed value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueComparison {
+    /// `=`: both values are exactly equal
+    Equals,
+    /// `!=`: both values differ
+    NotEquals,
+    /// `^=`: the value starts with the given string
+    StartsWith,
+    /// `$=`: the value ends with the given string
+    EndsWith,
+    /// `*=`: the value contains the given string
+    Contains,
+    /// `<`: the value is less than the given one
+    LessThan,
+    /// `<=`: the value is less than or equal to the given one
+    LessOrEqual,
+    /// `>`: the value is greater than the given one
+    GreaterThan,
+    /// `>=`: the value is greater than or equal to the given one
+    GreaterOrEqual,
+}
+
+impl ValueComparison {
+    pub(crate) fn matches(&self, value: &str, expected: &str) -> bool {
+        match self {
+            ValueComparison::Equals => value == expected,
+            ValueComparison::NotEquals => value != expected,
+            ValueComparison::StartsWith => value.starts_with(expected),
+            ValueComparison::EndsWith => value.ends_with(expected),
+            ValueComparison::Contains => value.contains(expected),
+            ValueComparison::LessThan => Self::compare(value, expected) == Ordering::Less,
+            ValueComparison::LessOrEqual => Self::compare(value, expected) != Ordering::Greater,
+            ValueComparison::GreaterThan => Self::compare(value, expected) == Ordering::Greater,
+            ValueComparison::GreaterOrEqual => Self::compare(value, expected) != Ordering::Less,
+        }
+    }
+
+    /// compares numerically if both sides are numbers, lexicographically otherwise
+    fn compare(value: &str, expected: &str) -> Ordering {
+        match (value.trim().parse::<f64>(), expected.trim().parse::<f64>()) {
+            (Ok(value), Ok(expected)) => value.partial_cmp(&expected).unwrap_or(Ordering::Equal),
+            _ => value.cmp(expected),
+        }
+    }
+}
+
+/// Predicate deciding which branch of `IF` / `UNLESS` is run for an element
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum ElementCondition<'a> {
+    /// Satisfied if the element selecting command finds at least one element
+    Selects(ElementSelectingCommand<'a>),
+    /// Satisfied if the element has the given attribute, regardless of its value
+    HasAttribute(&'a str),
+    /// Satisfied if at least one value created by the sub-pipeline matches the comparison
+    Compares(
+        StringValueCreatingPipeline<'a>,
+        ValueComparison,
+        Cow<'a, str>,
+    ),
+}
+
+impl<'a> ElementCondition<'a> {
+    /// check whether the given element satisfies the condition
+    pub(crate) fn evaluate(
+        &self,
+        element: &rctree::Node<HtmlContent>,
+    ) -> Result<bool, CommandError> {
+        trace!("Evaluating condition {:#?}", self);
+
+        match self {
+            ElementCondition::Selects(selection) => Ok(!selection.execute(element)?.is_empty()),
+            ElementCondition::HasAttribute(attribute) => Ok(element
+                .borrow()
+                .get_attribute(&String::from(*attribute))
+                .is_some()),
+            ElementCondition::Compares(pipeline, comparison, expected) => Ok(pipeline
+                .run_on(element)
+                .context(SubpipelineFailedSnafu)?
+                .iter()
+                .any(|value| comparison.matches(value, expected))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::element_processing::condition::{ElementCondition, ValueComparison};
+    use crate::string_creating::{
+        ElementSelectingCommand, StringValueCreatingPipeline, ValueExtractingCommand,
+    };
+    use crate::{load_inline_html, CssSelector, CssSelectorList, CssSelectorPath};
+
+    #[test]
+    fn numbers_are_compared_numerically() {
+        assert!(ValueComparison::GreaterThan.matches("1200", "400"));
+        assert!(!ValueComparison::LessThan.matches("1200", "400"));
+        assert!(ValueComparison::LessOrEqual.matches("400", "400.0"));
+        assert!(ValueComparison::GreaterOrEqual.matches(" 400 ", "400"));
+    }
+
+    #[test]
+    fn non_numbers_are_compared_lexicographically() {
+        assert!(ValueComparison::LessThan.matches("abc", "abd"));
+        assert!(ValueComparison::GreaterThan.matches("auto", "400"));
+    }
+
+    #[test]
+    fn string_comparisons_work_on_substrings() {
+        assert!(ValueComparison::StartsWith.matches("data-test", "data-"));
+        assert!(ValueComparison::EndsWith.matches("image.png", ".png"));
+        assert!(ValueComparison::Contains.matches("some text", "e t"));
+        assert!(ValueComparison::NotEquals.matches("foo", "bar"));
+        assert!(!ValueComparison::Equals.matches("foo", "bar"));
+    }
+
+    #[test]
+    fn selects_is_satisfied_by_matching_descendant() {
+        let root = load_inline_html(r#"<head><meta name="robots"></head>"#);
+        let condition =
+            ElementCondition::Selects(ElementSelectingCommand::QueryElement(CssSelectorList::new(
+                vec![CssSelectorPath::single(CssSelector::for_element("meta"))],
+            )));
+
+        assert!(condition.evaluate(&root).unwrap());
+    }
+
+    #[test]
+    fn selects_is_not_satisfied_without_match() {
+        let root = load_inline_html(r#"<head><title>Title</title></head>"#);
+        let condition =
+            ElementCondition::Selects(ElementSelectingCommand::QueryElement(CssSelectorList::new(
+                vec![CssSelectorPath::single(CssSelector::for_element("meta"))],
+            )));
+
+        assert!(!condition.evaluate(&root).unwrap());
+    }
+
+    #[test]
+    fn has_attribute_checks_presence_only() {
+        let root = load_inline_html(r#"<img src="" alt>"#);
+
+        assert!(ElementCondition::HasAttribute("alt")
+            .evaluate(&root)
+            .unwrap());
+        assert!(!ElementCondition::HasAttribute("width")
+            .evaluate(&root)
+            .unwrap());
+    }
+
+    #[test]
+    fn compares_checks_any_created_value() {
+        let root = load_inline_html(r#"<img src="" width="800">"#);
+        let pipeline = StringValueCreatingPipeline::new(
+            ElementSelectingCommand::UseElement,
+            ValueExtractingCommand::GetAttribute("width"),
+        );
+
+        assert!(ElementCondition::Compares(
+            pipeline.clone(),
+            ValueComparison::GreaterThan,
+            "400".into()
+        )
+        .evaluate(&root)
+        .unwrap());
+        assert!(
+            !ElementCondition::Compares(pipeline, ValueComparison::LessThan, "400".into())
+                .evaluate(&root)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn compares_is_not_satisfied_without_values() {
+        let root = load_inline_html(r#"<img src="">"#);
+        let pipeline = StringValueCreatingPipeline::new(
+            ElementSelectingCommand::UseElement,
+            ValueExtractingCommand::GetAttribute("width"),
+        );
+
+        assert!(
+            !ElementCondition::Compares(pipeline, ValueComparison::NotEquals, "400".into())
+                .evaluate(&root)
+                .unwrap()
+        );
+    }
+}
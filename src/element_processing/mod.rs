@@ -1,5 +1,11 @@
+mod attribute_pattern;
 mod command;
+mod condition;
 mod pipeline;
+mod position;
 
+pub(crate) use attribute_pattern::AttributePattern;
 pub(crate) use command::ElementProcessingCommand;
+pub(crate) use condition::{ElementCondition, ValueComparison};
 pub(crate) use pipeline::ElementProcessingPipeline;
+pub(crate) use position::InsertPosition;
@@ -1,19 +1,54 @@
 use crate::element_processing::command::ElementProcessingCommand;
-use log::{trace, warn};
+use log::trace;
 use snafu::ResultExt;
 use std::fmt::Debug;
+use std::ops::Range;
 
+use crate::explain::Explanation;
 use crate::html::HtmlContent;
 use crate::{CommandFailedSnafu, PipelineError};
 
-#[derive(Debug, PartialEq, Clone)]
-pub(crate) struct ElementProcessingPipeline<'a>(Vec<ElementProcessingCommand<'a>>);
+/// The spans are only used to report errors, they are not compared.
+#[derive(Debug, Clone)]
+pub(crate) struct ElementProcessingPipeline<'a> {
+    commands: Vec<ElementProcessingCommand<'a>>,
+    spans: Vec<Range<usize>>,
+    strict: bool,
+    explanation: Option<Explanation>,
+}
 
 /// The command pipeline: a list of individual commands
 /// each to execute on the result of the previous command
 impl<'a> ElementProcessingPipeline<'a> {
     pub fn new(content: Vec<ElementProcessingCommand<'a>>) -> Self {
-        ElementProcessingPipeline(content)
+        ElementProcessingPipeline {
+            commands: content,
+            spans: vec![],
+            strict: false,
+            explanation: None,
+        }
+    }
+
+    /// adds the position of each command within the pipeline definition
+    pub fn with_spans(self, spans: Vec<Range<usize>>) -> Self {
+        ElementProcessingPipeline { spans, ..self }
+    }
+
+    /// lets this pipeline and all its sub-pipelines fail if a selector matches nothing
+    /// or a command results in an empty result set
+    pub(crate) fn make_strict(&mut self) {
+        self.strict = true;
+        for command in self.commands.iter_mut() {
+            command.make_strict();
+        }
+    }
+
+    /// lets this pipeline and all its sub-pipelines record what each command did
+    pub(crate) fn explain_into(&mut self, explanation: &Explanation) {
+        self.explanation = Some(explanation.clone());
+        for command in self.commands.iter_mut() {
+            command.explain_into(&explanation.nested());
+        }
     }
 
     /// execute the pipeline on the given nodes by
@@ -25,31 +60,49 @@ impl<'a> ElementProcessingPipeline<'a> {
         nodes: Vec<rctree::Node<HtmlContent>>,
     ) -> Result<Vec<rctree::Node<HtmlContent>>, PipelineError> {
         let mut intermediate = nodes;
-        for (command_index, command) in self.0.iter().enumerate() {
+        for (command_index, command) in self.commands.iter().enumerate() {
             trace!("Running Next: {:#?}", &command);
             trace!("Current Element Set: {:#?}", &intermediate);
 
-            intermediate = command.execute(&intermediate).context(CommandFailedSnafu {
+            let span = self.spans.get(command_index).cloned();
+            intermediate = match &self.explanation {
+                Some(explanation) => explanation.record(
+                    command.name(),
+                    command_index,
+                    span.clone(),
+                    &intermediate,
+                    |input| command.execute_checked(input, self.strict),
+                ),
+                None => command.execute_checked(&intermediate, self.strict),
+            }
+            .with_context(|_| CommandFailedSnafu {
+                command: command.name(),
                 index: command_index,
+                span,
+                spans: self.spans.clone(),
             })?;
-
-            if intermediate.is_empty() {
-                warn!("Command resulted in an empty result set");
-            }
         }
 
         Ok(intermediate)
     }
 }
 
+impl<'a> PartialEq for ElementProcessingPipeline<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.commands == other.commands && self.strict == other.strict
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::html::HtmlRenderable;
+    use crate::parsing::ExpandedDefinition;
     use crate::{
         element_processing::{
             command::ElementProcessingCommand, pipeline::ElementProcessingPipeline,
         },
-        CssSelector, CssSelectorList, CssSelectorPath, CssSelectorStep, HtmlContent,
+        running_pipeline_failed, CssSelector, CssSelectorList, CssSelectorPath, CssSelectorStep,
+        HtmlContent,
     };
 
     const TEST_HTML_DOCUMENT: &str = r#"<html>
@@ -121,4 +174,23 @@ mod tests {
             String::from(r#"<p id="first-para">Some  text</p>"#)
         );
     }
+
+    #[test]
+    fn error_of_command_without_span_has_no_position() {
+        let pipeline =
+            ElementProcessingPipeline::new(vec![ElementProcessingCommand::ExpectCount(2, None)]);
+
+        let dom = tl::parse(TEST_HTML_DOCUMENT, tl::ParserOptions::default()).unwrap();
+        let starting_elements = HtmlContent::import(dom).unwrap();
+        let error = running_pipeline_failed(
+            &ExpandedDefinition::unexpanded(""),
+            pipeline.run_on(vec![starting_elements]).unwrap_err(),
+        );
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to run pipeline: EXPECT-COUNT[0] failed"
+        );
+        assert!(!error.to_json().contains(r#""line""#));
+    }
 }
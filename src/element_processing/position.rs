@@ -0,0 +1,55 @@
+use crate::html::HtmlContent;
+
+/// Where nodes get inserted relative to a target node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum InsertPosition {
+    /// as last children of the target
+    #[default]
+    Append,
+    /// as first children of the target
+    Prepend,
+    /// as previous siblings of the target
+    Before,
+    /// as next siblings of the target
+    After,
+}
+
+impl InsertPosition {
+    /// inserts the nodes relative to the target, keeping their order.
+    /// Nodes still attached somewhere else are detached beforehand.
+    pub(crate) fn insert(
+        &self,
+        target: &rctree::Node<HtmlContent>,
+        nodes: Vec<rctree::Node<HtmlContent>>,
+    ) {
+        // rctree can't move a node next to a related one, so it has to be detached first
+        for node in &nodes {
+            node.detach();
+        }
+
+        match self {
+            InsertPosition::Append => {
+                for node in nodes {
+                    target.append(node);
+                }
+            }
+            InsertPosition::Prepend => {
+                for node in nodes.into_iter().rev() {
+                    target.prepend(node);
+                }
+            }
+            InsertPosition::Before => {
+                for node in nodes {
+                    target.insert_before(node);
+                }
+            }
+            InsertPosition::After => {
+                let mut predecessor = rctree::Node::clone(target);
+                for node in nodes {
+                    predecessor.insert_after(rctree::Node::clone(&node));
+                    predecessor = node;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::json::quote;
+use crate::{MultipleValuesInFieldSnafu, StreamingEditorError};
+
+/// How the records of `HtmlStreamingEditor::extract` are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// a single JSON array
+    Json,
+    /// one JSON value per line
+    JsonLines,
+    /// one comma separated line per record, quoted as in RFC 4180
+    Csv,
+    /// one tab separated line per record, without any quoting
+    Lines,
+}
+
+/// What a value pipeline returned for a node, one field of an extracted record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractedValue {
+    /// the value pipeline returned no value, written as `null` (JSON) or empty field
+    Missing,
+    Single(String),
+    /// written as array (JSON), can't be written as CSV or lines
+    Multiple(Vec<String>),
+}
+
+impl From<Vec<String>> for ExtractedValue {
+    fn from(mut values: Vec<String>) -> Self {
+        match values.len() {
+            0 => ExtractedValue::Missing,
+            1 => ExtractedValue::Single(values.remove(0)),
+            _ => ExtractedValue::Multiple(values),
+        }
+    }
+}
+
+/// Writes the records in the given format. Records with a single value are written as plain values,
+/// records with multiple values as arrays (JSON) or multiple columns (CSV, lines).
+/// Fails for CSV and lines if a value pipeline returned multiple values, they have to be combined (e.g. by `JOIN`) first
+pub fn format_records(
+    records: &[Vec<ExtractedValue>],
+    format: RecordFormat,
+) -> Result<String, StreamingEditorError> {
+    let formatted = match format {
+        RecordFormat::Json => format!(
+            "[{}]\n",
+            records
+                .iter()
+                .map(|record| json_record(record))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        RecordFormat::JsonLines => records
+            .iter()
+            .map(|record| format!("{}\n", json_record(record)))
+            .collect(),
+        RecordFormat::Csv => records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| {
+                let fields = plain_fields(index, record)?
+                    .into_iter()
+                    .map(csv_field)
+                    .collect::<Vec<_>>();
+                Ok(format!("{}\r\n", fields.join(",")))
+            })
+            .collect::<Result<_, _>>()?,
+        RecordFormat::Lines => records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| Ok(format!("{}\n", plain_fields(index, record)?.join("\t"))))
+            .collect::<Result<_, _>>()?,
+    };
+
+    Ok(formatted)
+}
+
+fn json_record(record: &[ExtractedValue]) -> String {
+    match record {
+        [value] => json_value(value),
+        values => format!(
+            "[{}]",
+            values.iter().map(json_value).collect::<Vec<_>>().join(",")
+        ),
+    }
+}
+
+fn json_value(value: &ExtractedValue) -> String {
+    match value {
+        ExtractedValue::Missing => String::from("null"),
+        ExtractedValue::Single(value) => quote(value),
+        ExtractedValue::Multiple(values) => format!(
+            "[{}]",
+            values
+                .iter()
+                .map(|v| quote(v))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+    }
+}
+
+/// the fields of a record, missing values as empty ones
+fn plain_fields(
+    index: usize,
+    record: &[ExtractedValue],
+) -> Result<Vec<&str>, StreamingEditorError> {
+    record
+        .iter()
+        .enumerate()
+        .map(|(position, value)| match value {
+            ExtractedValue::Missing => Ok(""),
+            ExtractedValue::Single(value) => Ok(value.as_str()),
+            ExtractedValue::Multiple(values) => MultipleValuesInFieldSnafu {
+                record: index + 1,
+                value: position + 1,
+                count: values.len(),
+            }
+            .fail(),
+        })
+        .collect()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emit::{format_records, ExtractedValue, RecordFormat};
+    use crate::StreamingEditorError;
+
+    fn records(values: &[&[&[&str]]]) -> Vec<Vec<ExtractedValue>> {
+        values
+            .iter()
+            .map(|record| {
+                record
+                    .iter()
+                    .map(|field| {
+                        ExtractedValue::from(
+                            field.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_values_are_written_plain() {
+        let records = records(&[&[&["/home"]], &[&["/about \"us\""]]]);
+
+        assert_eq!(
+            format_records(&records, RecordFormat::Json).unwrap(),
+            "[\"/home\",\"/about \\\"us\\\"\"]\n"
+        );
+        assert_eq!(
+            format_records(&records, RecordFormat::JsonLines).unwrap(),
+            "\"/home\"\n\"/about \\\"us\\\"\"\n"
+        );
+        assert_eq!(
+            format_records(&records, RecordFormat::Csv).unwrap(),
+            "/home\r\n\"/about \"\"us\"\"\"\r\n"
+        );
+        assert_eq!(
+            format_records(&records, RecordFormat::Lines).unwrap(),
+            "/home\n/about \"us\"\n"
+        );
+    }
+
+    #[test]
+    fn multiple_fields_are_arrays_or_columns() {
+        let records = records(&[&[&["/home"], &["Home, sweet home"]], &[&["/about"], &[""]]]);
+
+        assert_eq!(
+            format_records(&records, RecordFormat::Json).unwrap(),
+            "[[\"/home\",\"Home, sweet home\"],[\"/about\",\"\"]]\n"
+        );
+        assert_eq!(
+            format_records(&records, RecordFormat::Csv).unwrap(),
+            "/home,\"Home, sweet home\"\r\n/about,\r\n"
+        );
+        assert_eq!(
+            format_records(&records, RecordFormat::Lines).unwrap(),
+            "/home\tHome, sweet home\n/about\t\n"
+        );
+    }
+
+    #[test]
+    fn missing_values_are_null_or_empty() {
+        let records = records(&[&[&["/home"], &[]]]);
+
+        assert_eq!(
+            format_records(&records, RecordFormat::JsonLines).unwrap(),
+            "[\"/home\",null]\n"
+        );
+        assert_eq!(
+            format_records(&records, RecordFormat::Csv).unwrap(),
+            "/home,\r\n"
+        );
+    }
+
+    #[test]
+    fn multiple_values_of_a_field_are_arrays_or_rejected() {
+        let records = records(&[&[&["/home"]], &[&["/"], &["a", "b"]]]);
+
+        assert_eq!(
+            format_records(&records, RecordFormat::Json).unwrap(),
+            "[\"/home\",[\"/\",[\"a\",\"b\"]]]\n"
+        );
+        for format in [RecordFormat::Csv, RecordFormat::Lines] {
+            assert!(matches!(
+                format_records(&records, format),
+                Err(StreamingEditorError::MultipleValuesInField {
+                    record: 2,
+                    value: 2,
+                    count: 2,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn no_records() {
+        assert_eq!(format_records(&[], RecordFormat::Json).unwrap(), "[]\n");
+        assert_eq!(format_records(&[], RecordFormat::Csv).unwrap(), "");
+    }
+}
@@ -0,0 +1,385 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::html::{node_pointer, HtmlContent};
+use crate::parsing::ExpandedDefinition;
+
+/// how many node paths are listed per step, before only counting the rest
+const LISTED_NODES: usize = 3;
+
+/// Records what each command of a pipeline, including those of its sub-pipelines, did.
+/// Clones share the same record, each sub-pipeline gets one with increased depth.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Explanation {
+    steps: Rc<RefCell<Vec<ExplainedStep>>>,
+    depth: usize,
+}
+
+#[derive(Debug)]
+struct ExplainedStep {
+    depth: usize,
+    command: &'static str,
+    index: usize,
+    span: Option<Range<usize>>,
+    input_count: usize,
+    /// `None` if the command failed
+    outcome: Option<StepOutcome>,
+}
+
+#[derive(Debug)]
+struct StepOutcome {
+    result_count: usize,
+    /// paths of the resulting nodes, if they differ from the input
+    selected: Vec<String>,
+    /// paths of the first input nodes (or their parents) that changed
+    changed: Vec<String>,
+    changed_count: usize,
+}
+
+/// Markers of the input nodes before running a command, to find out what it changed.
+/// A node is compared with its whole subtree, its surrounding by its parent and direct siblings
+struct Snapshot {
+    nodes: Vec<NodeSnapshot>,
+}
+
+struct NodeSnapshot {
+    node: rctree::Node<HtmlContent>,
+    parent: Option<rctree::Node<HtmlContent>>,
+    content: u64,
+    surrounding: u64,
+}
+
+impl Explanation {
+    /// the explanation to be used by the sub-pipelines of a command
+    pub(crate) fn nested(&self) -> Self {
+        Explanation {
+            steps: Rc::clone(&self.steps),
+            depth: self.depth + 1,
+        }
+    }
+
+    /// runs the command, recording its input, the selected nodes and the nodes it changed
+    pub(crate) fn record<E>(
+        &self,
+        command: &'static str,
+        index: usize,
+        span: Option<Range<usize>>,
+        input: &Vec<rctree::Node<HtmlContent>>,
+        execute: impl FnOnce(
+            &Vec<rctree::Node<HtmlContent>>,
+        ) -> Result<Vec<rctree::Node<HtmlContent>>, E>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, E> {
+        // reserve the position before running, so steps of sub-pipelines are listed after their command
+        let position = {
+            let mut steps = self.steps.borrow_mut();
+            steps.push(ExplainedStep {
+                depth: self.depth,
+                command,
+                index,
+                span,
+                input_count: input.len(),
+                outcome: None,
+            });
+            steps.len() - 1
+        };
+        let snapshot = Snapshot::take(input);
+
+        let result = execute(input)?;
+
+        let selected = if &result == input {
+            vec![]
+        } else {
+            result
+                .iter()
+                .take(LISTED_NODES)
+                .map(short_css_path)
+                .collect()
+        };
+        let changed = snapshot.changed_nodes();
+        self.steps.borrow_mut()[position].outcome = Some(StepOutcome {
+            result_count: result.len(),
+            selected,
+            changed: changed
+                .iter()
+                .take(LISTED_NODES)
+                .map(short_css_path)
+                .collect(),
+            changed_count: changed.len(),
+        });
+
+        Ok(result)
+    }
+
+    /// one line per command, indented by the depth of its pipeline, followed by the selected and changed nodes
+    /// with the positions where the commands were written, before expanding the definition
+    pub(crate) fn render(&self, pipeline_definition: &ExpandedDefinition) -> String {
+        let mut rendered = String::new();
+
+        for step in self.steps.borrow().iter() {
+            let indent = "  ".repeat(step.depth);
+            let _ = write!(rendered, "{}{}[{}]", indent, step.command, step.index);
+            if let Some(location) = step
+                .span
+                .as_ref()
+                .and_then(|span| pipeline_definition.locate(span.start))
+            {
+                let _ = write!(
+                    rendered,
+                    " at line {}, column {}",
+                    location.line, location.column
+                );
+            }
+
+            match &step.outcome {
+                None => {
+                    let _ = writeln!(rendered, ": {} → failed", nodes(step.input_count));
+                }
+                Some(outcome) => {
+                    let _ = writeln!(
+                        rendered,
+                        ": {} → {}",
+                        step.input_count,
+                        nodes(outcome.result_count)
+                    );
+                    if !outcome.selected.is_empty() {
+                        let _ = writeln!(
+                            rendered,
+                            "{}    selected: {}",
+                            indent,
+                            list(&outcome.selected, outcome.result_count)
+                        );
+                    }
+                    if !outcome.changed.is_empty() {
+                        let _ = writeln!(
+                            rendered,
+                            "{}    changed: {}",
+                            indent,
+                            list(&outcome.changed, outcome.changed_count)
+                        );
+                    }
+                }
+            }
+        }
+
+        rendered
+    }
+}
+
+fn nodes(count: usize) -> String {
+    if count == 1 {
+        String::from("1 node")
+    } else {
+        format!("{} nodes", count)
+    }
+}
+
+/// the first paths, followed by the number of not listed ones
+fn list(paths: &[String], total: usize) -> String {
+    let listed = paths
+        .iter()
+        .take(LISTED_NODES)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if total > LISTED_NODES {
+        format!("{}, … ({} more)", listed, total - LISTED_NODES)
+    } else {
+        listed
+    }
+}
+
+impl Snapshot {
+    fn take(input: &[rctree::Node<HtmlContent>]) -> Self {
+        Snapshot {
+            nodes: input
+                .iter()
+                .map(|node| NodeSnapshot {
+                    node: node.clone(),
+                    parent: node.parent(),
+                    content: content_marker(node),
+                    surrounding: surrounding_marker(node),
+                })
+                .collect(),
+        }
+    }
+
+    /// the input nodes that changed, or if they were removed or only their surrounding changed
+    /// (e.g. by inserting siblings), their parents
+    fn changed_nodes(&self) -> Vec<rctree::Node<HtmlContent>> {
+        let mut changed = vec![];
+        let mut seen = HashSet::new();
+
+        for snapshot in &self.nodes {
+            let removed = snapshot.parent.is_some() && snapshot.node.parent().is_none();
+            let node = if !removed && content_marker(&snapshot.node) != snapshot.content {
+                Some(snapshot.node.clone())
+            } else if surrounding_marker(&snapshot.node) != snapshot.surrounding {
+                snapshot.parent.clone()
+            } else {
+                None
+            };
+
+            if let Some(node) = node {
+                if seen.insert(node_pointer(&node)) {
+                    changed.push(node);
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+/// changes with the content of the node and all its descendants
+fn content_marker(node: &rctree::Node<HtmlContent>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for edge in node.traverse() {
+        match edge {
+            rctree::NodeEdge::Start(node) => hash_content(&node, &mut hasher),
+            rctree::NodeEdge::End(_) => hasher.write_u8(0),
+        }
+    }
+
+    hasher.finish()
+}
+
+/// changes if the node is moved or gets other direct siblings
+fn surrounding_marker(node: &rctree::Node<HtmlContent>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for other in [node.parent(), node.previous_sibling(), node.next_sibling()] {
+        match other {
+            Some(other) => {
+                node_pointer(&other).hash(&mut hasher);
+                hash_content(&other, &mut hasher);
+            }
+            None => hasher.write_u8(0),
+        }
+    }
+
+    hasher.finish()
+}
+
+fn hash_content(node: &rctree::Node<HtmlContent>, hasher: &mut DefaultHasher) {
+    match &*node.borrow() {
+        HtmlContent::Document(_) => hasher.write_u8(1),
+        HtmlContent::Tag(tag) => {
+            hasher.write_u8(2);
+            tag.name.hash(hasher);
+            tag.attributes.hash(hasher);
+        }
+        HtmlContent::Text(text) => {
+            hasher.write_u8(3);
+            text.hash(hasher);
+        }
+        HtmlContent::Comment(comment) => {
+            hasher.write_u8(4);
+            comment.hash(hasher);
+        }
+    }
+}
+
+/// CSS-like path of the node, starting at the closest ancestor with an `id`,
+/// using `:nth-child` only where siblings have the same tag name
+pub(crate) fn short_css_path(node: &rctree::Node<HtmlContent>) -> String {
+    let mut segments = vec![];
+    let mut current = Some(node.clone());
+
+    while let Some(node) = current {
+        let (segment, done) = match &*node.borrow() {
+            HtmlContent::Document(_) => {
+                if segments.is_empty() {
+                    segments.push(String::from(":root"));
+                }
+                break;
+            }
+            HtmlContent::Text(_) => (String::from("#text"), false),
+            HtmlContent::Comment(_) => (String::from("#comment"), false),
+            HtmlContent::Tag(tag) => match tag.attributes.get("id") {
+                Some(id) => (format!("{}#{}", tag.name, id), true),
+                None => (
+                    format!("{}{}", tag.name, nth_child(&node, &tag.name)),
+                    false,
+                ),
+            },
+        };
+        segments.push(segment);
+        if done {
+            break;
+        }
+
+        current = node.parent();
+    }
+
+    segments.reverse();
+    segments.join(" > ")
+}
+
+/// `:nth-child(n)` if an element sibling has the same tag name, otherwise empty
+fn nth_child(node: &rctree::Node<HtmlContent>, name: &str) -> String {
+    let parent = match node.parent() {
+        Some(parent) => parent,
+        None => return String::new(),
+    };
+
+    let mut position = 0;
+    let mut same_name = 0;
+    for (index, sibling) in parent
+        .children()
+        .filter(|s| s.borrow().is_tag())
+        .enumerate()
+    {
+        if sibling == *node {
+            position = index + 1;
+        }
+        if matches!(&*sibling.borrow(), HtmlContent::Tag(tag) if tag.name == name) {
+            same_name += 1;
+        }
+    }
+
+    if same_name > 1 {
+        format!(":nth-child({})", position)
+    } else {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::explain::short_css_path;
+    use crate::html::HtmlContent;
+    use crate::{CssSelector, CssSelectorList, CssSelectorPath};
+
+    const TEST_HTML_DOCUMENT: &str = r#"<html>
+    <body>
+        <div id="main"><p>First</p><p class="x">Second <em>text</em></p></div>
+        <p>Outside</p>
+    </body>
+</html>"#;
+
+    fn path_of(element_name: &str, index: usize) -> String {
+        let dom = tl::parse(TEST_HTML_DOCUMENT, tl::ParserOptions::default()).unwrap();
+        let root = HtmlContent::import(dom).unwrap();
+        let selector = CssSelectorList::new(vec![CssSelectorPath::single(
+            CssSelector::for_element(element_name),
+        )]);
+
+        short_css_path(&selector.query(std::slice::from_ref(&root))[index])
+    }
+
+    #[test]
+    fn path_starts_at_closest_id() {
+        assert_eq!(path_of("em", 0), "div#main > p:nth-child(2) > em");
+        assert_eq!(path_of("div", 0), "div#main");
+    }
+
+    #[test]
+    fn path_without_id_starts_at_html() {
+        assert_eq!(path_of("p", 2), "html > body > p");
+    }
+}
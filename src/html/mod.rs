@@ -104,12 +104,74 @@ impl HtmlTag {
     }
 
     fn is_class_member(&self, class: &str) -> bool {
-        if let Some(classes) = self.attributes.get(&String::from("class")) {
-            classes.split(' ').any(|c| c == class)
+        self.attributes
+            .get("class")
+            .is_some_and(|classes| classes.split_ascii_whitespace().any(|c| c == class))
+    }
+
+    /// the tokens of the `class` attribute, in their original order
+    fn classes(&self) -> Vec<String> {
+        self.attributes
+            .get(&String::from("class"))
+            .map(|classes| {
+                classes
+                    .split_ascii_whitespace()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    }
+
+    /// writes the tokens back as `class` attribute, removing it if no token is left
+    fn set_classes(&mut self, classes: Vec<String>) {
+        if classes.is_empty() {
+            self.attributes.remove(&String::from("class"));
+        } else {
+            self.attributes
+                .insert(String::from("class"), classes.join(" "));
+        }
+    }
+
+    fn add_class(&mut self, class: &str) {
+        let mut classes = self.classes();
+        if !classes.iter().any(|c| c == class) {
+            classes.push(String::from(class));
+        }
+        self.set_classes(classes);
+    }
+
+    fn remove_class(&mut self, class: &str) {
+        let mut classes = self.classes();
+        classes.retain(|c| c != class);
+        self.set_classes(classes);
+    }
+
+    fn toggle_class(&mut self, class: &str) {
+        if self.is_class_member(class) {
+            self.remove_class(class);
         } else {
-            false
+            self.add_class(class);
         }
     }
+
+    fn replace_class(&mut self, old_class: &str, new_class: &str) {
+        if !self.is_class_member(old_class) {
+            return;
+        }
+
+        let mut classes = Vec::<String>::new();
+        for class in self.classes() {
+            let class = if class == old_class {
+                String::from(new_class)
+            } else {
+                class
+            };
+            if !classes.contains(&class) {
+                classes.push(class);
+            }
+        }
+        self.set_classes(classes);
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -140,6 +202,16 @@ impl HtmlContent {
         }
     }
 
+    /// converts all top-level nodes of the DOM, including text and comments
+    pub(crate) fn import_fragment(dom: VDom) -> Result<Vec<Node<HtmlContent>>, HtmlDomError> {
+        let parser = dom.parser();
+
+        dom.children()
+            .iter()
+            .map(|child| Self::convert_node(child, parser))
+            .collect()
+    }
+
     fn find_root_tag(dom: &VDom) -> Result<(Node<HtmlContent>, String), HtmlDomError> {
         let parser = dom.parser();
 
@@ -294,6 +366,63 @@ impl HtmlContent {
         }
     }
 
+    pub(crate) fn has_class(&self, class: &str) -> bool {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => false,
+            HtmlContent::Tag(tag) => tag.is_class_member(class),
+        }
+    }
+
+    pub(crate) fn add_class(&mut self, class: &str) {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => (),
+            HtmlContent::Tag(tag) => tag.add_class(class),
+        }
+    }
+
+    pub(crate) fn remove_class(&mut self, class: &str) {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => (),
+            HtmlContent::Tag(tag) => tag.remove_class(class),
+        }
+    }
+
+    pub(crate) fn toggle_class(&mut self, class: &str) {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => (),
+            HtmlContent::Tag(tag) => tag.toggle_class(class),
+        }
+    }
+
+    pub(crate) fn replace_class(&mut self, old_class: &str, new_class: &str) {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => (),
+            HtmlContent::Tag(tag) => tag.replace_class(old_class, new_class),
+        }
+    }
+
+    pub(crate) fn set_tag_name(&mut self, name: impl Into<String>) {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => (),
+            HtmlContent::Tag(tag) => tag.name = name.into(),
+        }
+    }
+
+    pub(crate) fn is_void_element(&self) -> bool {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => false,
+            HtmlContent::Tag(tag) => HTML_VOID_ELEMENTS.contains(&tag.name.as_ref()),
+        }
+    }
+
+    /// the names of all attributes, in alphabetical order
+    pub(crate) fn get_attribute_names(&self) -> Vec<String> {
+        match self {
+            HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => vec![],
+            HtmlContent::Tag(tag) => tag.attributes.keys().cloned().collect::<Vec<_>>(),
+        }
+    }
+
     pub(crate) fn get_attribute(&self, attribute: &String) -> Option<String> {
         match self {
             HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => None,
@@ -386,3 +515,8 @@ impl HtmlQueryable for Node<HtmlContent> {
         inner.matches_selector(selector)
     }
 }
+
+/// Identifies the node as long as it exists, e.g. to collect nodes in a `HashSet`
+pub(crate) fn node_pointer(node: &Node<HtmlContent>) -> *const HtmlContent {
+    &*node.borrow()
+}
@@ -407,3 +407,47 @@ fn convert_vdom_other_tag_builds_tag() {
     let content = converted.borrow();
     assert!(matches!(*content, HtmlContent::Tag(_)));
 }
+
+#[test]
+fn add_class_appends_missing_class_only() {
+    let node = build_tag_with_attr();
+
+    node.borrow_mut().add_class("bar");
+    node.borrow_mut().add_class("foo");
+
+    assert_eq!(
+        node.outer_html(),
+        r#"<div class="foo bar" data-bar="value"></div>"#
+    );
+}
+
+#[test]
+fn remove_class_drops_empty_class_attribute() {
+    let node = build_tag_with_attr();
+
+    node.borrow_mut().remove_class("foo");
+
+    assert_eq!(node.outer_html(), r#"<div data-bar="value"></div>"#);
+}
+
+#[test]
+fn toggle_class_adds_and_removes() {
+    let node = build_tag();
+
+    node.borrow_mut().toggle_class("open");
+    assert_eq!(node.outer_html(), r#"<div class="open"></div>"#);
+
+    node.borrow_mut().toggle_class("open");
+    assert_eq!(node.outer_html(), r#"<div></div>"#);
+}
+
+#[test]
+fn replace_class_keeps_position_and_normalizes_whitespace() {
+    let node = build_tag();
+    node.borrow_mut().set_attribute("class", " a  old\tb new ");
+
+    node.borrow_mut().replace_class("old", "new");
+    node.borrow_mut().replace_class("missing", "other");
+
+    assert_eq!(node.outer_html(), r#"<div class="a new b"></div>"#);
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+/// Minimal writer for a single-line JSON object, enough for reporting errors without a serialization framework
+pub(crate) struct JsonObject {
+    content: String,
+}
+
+impl JsonObject {
+    pub(crate) fn new() -> Self {
+        JsonObject {
+            content: String::new(),
+        }
+    }
+
+    pub(crate) fn string(mut self, key: &str, value: &str) -> Self {
+        self.key(key);
+        self.content.push_str(&quote(value));
+        self
+    }
+
+    pub(crate) fn number(mut self, key: &str, value: usize) -> Self {
+        self.key(key);
+        let _ = write!(self.content, "{}", value);
+        self
+    }
+
+    pub(crate) fn strings<S: AsRef<str>>(mut self, key: &str, values: &[S]) -> Self {
+        self.key(key);
+        self.content.push('[');
+        self.content.push_str(
+            &values
+                .iter()
+                .map(|v| quote(v.as_ref()))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        self.content.push(']');
+        self
+    }
+
+    fn key(&mut self, key: &str) {
+        if !self.content.is_empty() {
+            self.content.push(',');
+        }
+        self.content.push_str(&quote(key));
+        self.content.push(':');
+    }
+
+    pub(crate) fn finish(self) -> String {
+        format!("{{{}}}", self.content)
+    }
+}
+
+/// the value as JSON string literal, including the quotes
+pub(crate) fn quote(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{quote, JsonObject};
+
+    #[test]
+    fn quote_escapes_special_characters() {
+        assert_eq!(quote(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(quote("line\nnext\u{1}"), r#""line\nnext\u0001""#);
+        assert_eq!(quote("↦"), "\"↦\"");
+    }
+
+    #[test]
+    fn object_joins_fields() {
+        assert_eq!(
+            JsonObject::new()
+                .string("kind", "x")
+                .number("line", 3)
+                .strings("causes", &["a", "b"])
+                .strings("empty", &[] as &[&str])
+                .finish(),
+            r#"{"kind":"x","line":3,"causes":["a","b"],"empty":[]}"#
+        );
+    }
+}
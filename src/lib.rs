@@ -1,24 +1,42 @@
-use log::debug;
+use log::{debug, warn};
 use peg::str::LineCol;
-use snafu::{ResultExt, Snafu};
+use snafu::{ensure, IntoError, ResultExt, Snafu};
 use std::backtrace::Backtrace;
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
+pub(crate) use crate::compiled_regex::CompiledRegex;
 pub(crate) use crate::css::{
     CssAttributeComparison, CssAttributeSelector, CssPseudoClass, CssSelector, CssSelectorList,
     CssSelectorPath, CssSelectorStep,
 };
+use crate::element_processing::ElementProcessingPipeline;
+use crate::explain::Explanation;
 use crate::html::HtmlContent;
+use crate::json::JsonObject;
+use crate::parsing::ExpandedDefinition;
 use crate::string_creating::StringValueCreatingPipeline;
 
+pub use crate::diff::DryRun;
+pub use crate::emit::{format_records, ExtractedValue, RecordFormat};
 pub use crate::html::HtmlRenderable;
+pub use crate::parsing::{DiagnosticCode, PipelineDiagnostic};
+pub use crate::session::{EditingSession, QueryMatch};
 
+mod compiled_regex;
 mod css;
+mod diff;
 mod element_creating;
 mod element_processing;
+mod emit;
+mod explain;
 mod html;
+mod json;
 mod parsing;
+mod session;
 mod string_creating;
 
 #[derive(Debug, Snafu)]
@@ -40,29 +58,209 @@ pub enum StreamingEditorError {
         #[snafu(backtrace)]
         source: html::HtmlDomError,
     },
-    #[snafu(display("Failed to parse pipeline"))]
+    #[snafu(display("Failed to load pipeline definition"))]
+    LoadingPipelineFailed {
+        #[snafu(backtrace)]
+        source: PipelineSourceError,
+    },
+    #[snafu(display("Failed to parse pipeline: {diagnostic}"))]
     ParsingPipelineFailed {
-        source: peg::error::ParseError<LineCol>,
+        diagnostic: Box<PipelineDiagnostic>,
         backtrace: Backtrace,
     },
-    #[snafu(display("Failed to run pipeline"))]
+    #[snafu(display(
+        "Failed to run pipeline: {command_path} failed{}",
+        describe_location(file.as_deref(), *line, *column)
+    ))]
     RunningPipelineFailed {
+        /// like `PipelineError::command_path`, but numbering the commands as they were written,
+        /// before resolving `INCLUDE` and `CALL`
+        command_path: String,
+        /// file the failing command was read from, `None` if the pipeline definition was given directly
+        file: Option<PathBuf>,
+        /// `None` if the position of the failing command is unknown
+        line: Option<usize>,
+        column: Option<usize>,
         #[snafu(backtrace)]
-        source: PipelineError,
+        #[snafu(source(from(PipelineError, Box::new)))]
+        source: Box<PipelineError>,
+    },
+    #[snafu(display(
+        "Value pipeline {value} returned {count} values for record {record}, \
+         CSV and lines can only hold one value per field, combine them first (e.g. by JOIN)"
+    ))]
+    MultipleValuesInField {
+        /// starting at 1
+        record: usize,
+        /// number of the value pipeline, starting at 1
+        value: usize,
+        count: usize,
+        backtrace: Backtrace,
+    },
+}
+
+impl StreamingEditorError {
+    /// describes the error as single line JSON object, for machine consumption.
+    /// Besides `kind`, `message` and `causes` it contains the position (`file` if read from one, `line`, `column`) for pipeline errors,
+    /// `code`, `expected` and `suggestion` for parsing errors and the `command_path` for running errors
+    pub fn to_json(&self) -> String {
+        let json = match self {
+            StreamingEditorError::ReadingInputFailed { .. } => {
+                JsonObject::new().string("kind", "reading_input_failed")
+            }
+            StreamingEditorError::WritingOutputFailed { .. } => {
+                JsonObject::new().string("kind", "writing_output_failed")
+            }
+            StreamingEditorError::ParsingInputFailed { .. } => {
+                JsonObject::new().string("kind", "parsing_input_failed")
+            }
+            StreamingEditorError::LoadingParsedHtmlFailed { .. } => {
+                JsonObject::new().string("kind", "loading_parsed_html_failed")
+            }
+            StreamingEditorError::LoadingPipelineFailed { .. } => {
+                JsonObject::new().string("kind", "loading_pipeline_failed")
+            }
+            StreamingEditorError::ParsingPipelineFailed { diagnostic, .. } => {
+                let json = JsonObject::new()
+                    .string("kind", "parsing_pipeline_failed")
+                    .string("code", diagnostic.code().code());
+                let json = match diagnostic.file() {
+                    Some(file) => json.string("file", &file.to_string_lossy()),
+                    None => json,
+                };
+                let json = json
+                    .number("line", diagnostic.line())
+                    .number("column", diagnostic.column())
+                    .strings("expected", diagnostic.expected());
+                match diagnostic.suggestion() {
+                    Some(suggestion) => json.string("suggestion", suggestion),
+                    None => json,
+                }
+            }
+            StreamingEditorError::MultipleValuesInField { record, value, .. } => JsonObject::new()
+                .string("kind", "multiple_values_in_field")
+                .number("record", *record)
+                .number("value", *value),
+            StreamingEditorError::RunningPipelineFailed {
+                command_path,
+                file,
+                line,
+                column,
+                ..
+            } => {
+                let json = JsonObject::new()
+                    .string("kind", "running_pipeline_failed")
+                    .string("command_path", command_path);
+                let json = match file {
+                    Some(file) => json.string("file", &file.to_string_lossy()),
+                    None => json,
+                };
+                match (line, column) {
+                    (Some(line), Some(column)) => {
+                        json.number("line", *line).number("column", *column)
+                    }
+                    _ => json,
+                }
+            }
+        };
+
+        // the diagnostic spans multiple lines, its details are separate fields
+        let message = match self {
+            StreamingEditorError::ParsingPipelineFailed { diagnostic, .. } => {
+                format!("Failed to parse pipeline: {}", diagnostic.message())
+            }
+            _ => self.to_string(),
+        };
+        let causes = std::iter::successors(std::error::Error::source(self), |e| e.source())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+
+        json.string("message", &message)
+            .strings("causes", &causes)
+            .finish()
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum PipelineSourceError {
+    #[snafu(display("Failed to parse comments, includes and definitions"))]
+    ParsingSourceFailed {
+        source: peg::error::ParseError<LineCol>,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Failed to read included file {}", path.display()))]
+    ReadingIncludeFailed {
+        path: PathBuf,
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("File {} includes itself", path.display()))]
+    IncludeCycle { path: PathBuf, backtrace: Backtrace },
+    #[snafu(display("No definition named {name:?}, it has to be defined before being called"))]
+    UnknownDefinition { name: String, backtrace: Backtrace },
+    #[snafu(display("Definition {name:?} calls itself"))]
+    RecursiveDefinition { name: String, backtrace: Backtrace },
+    #[snafu(display(
+        "{directive} can't be used in an editing session, only single pipelines are applied"
+    ))]
+    DirectiveInSession {
+        directive: &'static str,
+        backtrace: Backtrace,
     },
 }
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(crate)))]
 pub enum PipelineError {
-    #[snafu(display("Command at index {index} failed"))]
+    #[snafu(display("Command {command} at index {index} failed"))]
     CommandFailed {
+        /// canonical name of the command, aliases are reported by the name they stand for
+        command: &'static str,
+        /// position of the command within its (sub-)pipeline
         index: usize,
+        /// byte range of the command within the pipeline definition, after resolving `INCLUDE` and `CALL`
+        span: Option<Range<usize>>,
+        /// byte ranges of all commands of the (sub-)pipeline, to number the commands as they were written
+        spans: Vec<Range<usize>>,
         #[snafu(backtrace)]
-        source: CommandError,
+        #[snafu(source(from(CommandError, Box::new)))]
+        source: Box<CommandError>,
     },
 }
 
+impl PipelineError {
+    /// the failing command and all commands containing it, starting with the outermost one
+    fn nested_errors(&self) -> impl Iterator<Item = &PipelineError> {
+        std::iter::successors(Some(self), |error| match error {
+            PipelineError::CommandFailed { source, .. } => match source.as_ref() {
+                CommandError::SubpipelineFailed { source, .. } => Some(source.as_ref()),
+                _ => None,
+            },
+        })
+    }
+
+    /// breadcrumb path of the failing command, e.g. `FOR-EACH[0] > SET-ATTR[1] > REGEX-REPLACE[2]`,
+    /// where the numbers are the positions within the respective (sub-)pipeline, after resolving `INCLUDE` and `CALL`
+    pub fn command_path(&self) -> String {
+        self.nested_errors()
+            .map(|error| match error {
+                PipelineError::CommandFailed { command, index, .. } => {
+                    format!("{}[{}]", command, index)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+
+    /// byte range of the innermost failing command within the pipeline definition
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.nested_errors().last().and_then(|error| match error {
+            PipelineError::CommandFailed { span, .. } => span.clone(),
+        })
+    }
+}
+
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(crate)))]
 pub enum CommandError {
@@ -87,28 +285,235 @@ pub enum CommandError {
         #[snafu(backtrace)]
         source: crate::html::HtmlDomError,
     },
-    #[snafu(display("Failed to parse regular expression"))]
-    ParsingRegexFailed {
-        source: regex::Error,
+    #[snafu(display("Invalid tag name {name:?}"))]
+    InvalidTagName { name: String, backtrace: Backtrace },
+    #[snafu(display(
+        "Invalid FORMAT template {template:?}, placeholders are {{}} or positions like {{0}}, \
+        literal braces are doubled"
+    ))]
+    InvalidFormatTemplate {
+        template: String,
         backtrace: Backtrace,
     },
+    #[snafu(display(
+        "Invalid value {value:?} of style property {property}, it must not end the declaration"
+    ))]
+    InvalidStyleValue {
+        property: String,
+        value: String,
+        backtrace: Backtrace,
+    },
+    #[snafu(display(
+        "Sub-Pipeline returned {count} values where one is expected, combine them with JOIN, FIRST, LAST or COUNT"
+    ))]
+    MultipleValues { count: usize, backtrace: Backtrace },
+    #[snafu(display("Selector matched no elements"))]
+    SelectorMatchedNothing { backtrace: Backtrace },
+    #[snafu(display("Command resulted in an empty result set"))]
+    EmptyResultSet { backtrace: Backtrace },
+    #[snafu(display("Found {count} elements, expected {}", describe_count(*min, *max)))]
+    UnexpectedMatchCount {
+        count: usize,
+        min: usize,
+        max: Option<usize>,
+        backtrace: Backtrace,
+    },
+}
+
+/// ` at line …, column …` (`of` the file), or nothing if the position is unknown
+fn describe_location(file: Option<&Path>, line: Option<usize>, column: Option<usize>) -> String {
+    match (file, line, column) {
+        (Some(file), Some(line), Some(column)) => {
+            format!(" at line {}, column {} of {}", line, column, file.display())
+        }
+        (None, Some(line), Some(column)) => format!(" at line {}, column {}", line, column),
+        _ => String::new(),
+    }
+}
+
+fn describe_count(min: usize, max: Option<usize>) -> String {
+    match max {
+        None => format!("at least {}", min),
+        Some(max) if max == min => format!("exactly {}", min),
+        Some(max) => format!("{} to {}", min, max),
+    }
 }
 
 pub struct HtmlStreamingEditor<'a> {
     input: &'a mut dyn BufRead,
+    strict: bool,
+    explanation: Option<&'a mut String>,
 }
 
 impl<'a> HtmlStreamingEditor<'a> {
     pub fn new(input: &'a mut dyn BufRead) -> Self {
-        HtmlStreamingEditor { input }
+        HtmlStreamingEditor {
+            input,
+            strict: false,
+            explanation: None,
+        }
     }
 
+    /// in strict mode running the pipeline fails if a selector matches no elements, a command results
+    /// in an empty result set or multiple values are used where one is expected, instead of only logging a warning
+    pub fn with_strict(self, strict: bool) -> Self {
+        HtmlStreamingEditor { strict, ..self }
+    }
+
+    /// describes what each command (including those of sub-pipelines) did while running the pipeline:
+    /// how many nodes it got and returned, the paths of newly selected and of changed nodes.
+    /// The description is written even if running the pipeline fails
+    pub fn with_explanation(self, explanation: &'a mut String) -> Self {
+        HtmlStreamingEditor {
+            explanation: Some(explanation),
+            ..self
+        }
+    }
+
+    /// runs the pipeline definition on the input.
+    /// `INCLUDE`s within the definition are resolved against the current working directory
     pub fn run(
         self,
         pipeline_definition: &str,
     ) -> Result<Vec<Box<dyn HtmlRenderable>>, StreamingEditorError> {
-        let pipeline =
-            parsing::grammar::pipeline(pipeline_definition).context(ParsingPipelineFailedSnafu)?;
+        let pipeline_definition = parsing::expand_pipeline_definition(pipeline_definition)
+            .context(LoadingPipelineFailedSnafu)?;
+
+        self.run_expanded(&pipeline_definition, None).map(boxed)
+    }
+
+    /// runs the pipeline defined in the file (e.g. `recipe.hsp`) on the input.
+    /// `INCLUDE`s within the definition are resolved against the directory of that file
+    pub fn run_file(
+        self,
+        pipeline_file: &Path,
+    ) -> Result<Vec<Box<dyn HtmlRenderable>>, StreamingEditorError> {
+        let pipeline_definition =
+            parsing::expand_pipeline_file(pipeline_file).context(LoadingPipelineFailedSnafu)?;
+
+        self.run_expanded(&pipeline_definition, None).map(boxed)
+    }
+
+    /// like `run`, but returns the rendered input before and after running the pipeline,
+    /// e.g. to show what would change
+    pub fn dry_run(self, pipeline_definition: &str) -> Result<DryRun, StreamingEditorError> {
+        let pipeline_definition = parsing::expand_pipeline_definition(pipeline_definition)
+            .context(LoadingPipelineFailedSnafu)?;
+
+        self.dry_run_expanded(&pipeline_definition)
+    }
+
+    /// like `run_file`, but returns the rendered input before and after running the pipeline
+    pub fn dry_run_file(self, pipeline_file: &Path) -> Result<DryRun, StreamingEditorError> {
+        let pipeline_definition =
+            parsing::expand_pipeline_file(pipeline_file).context(LoadingPipelineFailedSnafu)?;
+
+        self.dry_run_expanded(&pipeline_definition)
+    }
+
+    /// runs the pipeline, then each value pipeline (e.g. `THIS | GET-ATTR{href}`) on every resulting node.
+    /// Returns one record per node with the values returned by each value pipeline,
+    /// without value pipelines the record only contains the node's HTML
+    pub fn extract(
+        self,
+        pipeline_definition: &str,
+        value_definitions: &[&str],
+    ) -> Result<Vec<Vec<ExtractedValue>>, StreamingEditorError> {
+        let pipeline_definition = parsing::expand_pipeline_definition(pipeline_definition)
+            .context(LoadingPipelineFailedSnafu)?;
+
+        self.extract_expanded(&pipeline_definition, value_definitions)
+    }
+
+    /// like `extract`, with the pipeline defined in the file
+    pub fn extract_file(
+        self,
+        pipeline_file: &Path,
+        value_definitions: &[&str],
+    ) -> Result<Vec<Vec<ExtractedValue>>, StreamingEditorError> {
+        let pipeline_definition =
+            parsing::expand_pipeline_file(pipeline_file).context(LoadingPipelineFailedSnafu)?;
+
+        self.extract_expanded(&pipeline_definition, value_definitions)
+    }
+
+    fn extract_expanded(
+        self,
+        pipeline_definition: &ExpandedDefinition,
+        value_definitions: &[&str],
+    ) -> Result<Vec<Vec<ExtractedValue>>, StreamingEditorError> {
+        // parsed first, so mistakes are reported before the input is read
+        let value_pipelines = value_definitions
+            .iter()
+            .map(|definition| {
+                parsing::grammar::string_creating_pipeline(definition)
+                    .map(|pipeline| (*definition, pipeline))
+                    .map_err(|error| {
+                        ParsingPipelineFailedSnafu {
+                            diagnostic: Box::new(PipelineDiagnostic::new(
+                                &ExpandedDefinition::unexpanded(definition),
+                                &error,
+                            )),
+                        }
+                        .build()
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let result = self.run_expanded(pipeline_definition, None)?;
+        if value_pipelines.is_empty() {
+            return Ok(result
+                .iter()
+                .map(|node| vec![ExtractedValue::Single(node.outer_html())])
+                .collect());
+        }
+
+        result
+            .iter()
+            .map(|node| {
+                value_pipelines
+                    .iter()
+                    .map(|(definition, pipeline)| {
+                        pipeline
+                            .run_on(node)
+                            .map(ExtractedValue::from)
+                            .map_err(|error| {
+                                running_pipeline_failed(
+                                    &ExpandedDefinition::unexpanded(definition),
+                                    error,
+                                )
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect()
+    }
+
+    fn dry_run_expanded(
+        self,
+        pipeline_definition: &ExpandedDefinition,
+    ) -> Result<DryRun, StreamingEditorError> {
+        let mut original = String::new();
+        let result = self.run_expanded(pipeline_definition, Some(&mut original))?;
+
+        Ok(DryRun::new(original, render_lines(&result)))
+    }
+
+    /// if `original` is given, the input is rendered into it before the pipeline changes anything
+    fn run_expanded(
+        self,
+        pipeline_definition: &ExpandedDefinition,
+        original: Option<&mut String>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, StreamingEditorError> {
+        let mut pipeline = parse_pipeline(pipeline_definition)?;
+        if self.strict {
+            pipeline.make_strict();
+        }
+        let explanation = self.explanation.map(|target| {
+            let explanation = Explanation::default();
+            pipeline.explain_into(&explanation);
+            (explanation, target)
+        });
         debug!("Parsed Pipeline: {:#?}", &pipeline);
 
         let mut string_content = String::new();
@@ -116,19 +521,100 @@ impl<'a> HtmlStreamingEditor<'a> {
             .read_to_string(&mut string_content)
             .context(ReadingInputFailedSnafu)?;
 
-        let dom = tl::parse(&string_content, tl::ParserOptions::default())
-            .context(ParsingInputFailedSnafu)?;
-        let root_element = HtmlContent::import(dom).context(LoadingParsedHtmlFailedSnafu)?;
-        let result = pipeline
-            .run_on(vec![root_element])
-            .context(RunningPipelineFailedSnafu)?;
+        let root_element = parse_input(&string_content)?;
+        if let Some(original) = original {
+            *original = format!("{}\n", root_element.outer_html());
+        }
+        let result = pipeline.run_on(vec![root_element]);
+        if let Some((explanation, target)) = explanation {
+            *target = explanation.render(pipeline_definition);
+        }
+        let result = result.map_err(|error| running_pipeline_failed(pipeline_definition, error))?;
 
         debug!("Final Result: {:#?}", &result);
-        Ok(result
-            .iter()
-            .map(|n| Box::new(n.clone()) as Box<dyn HtmlRenderable>)
-            .collect::<Vec<_>>())
+        Ok(result)
+    }
+}
+
+/// parses the expanded pipeline definition, describing errors with a diagnostic
+fn parse_pipeline(
+    pipeline_definition: &ExpandedDefinition,
+) -> Result<ElementProcessingPipeline<'_>, StreamingEditorError> {
+    parsing::grammar::pipeline(pipeline_definition.text()).map_err(|error| {
+        ParsingPipelineFailedSnafu {
+            diagnostic: Box::new(PipelineDiagnostic::new(pipeline_definition, &error)),
+        }
+        .build()
+    })
+}
+
+/// parses the HTML input into the memory model
+fn parse_input(content: &str) -> Result<rctree::Node<HtmlContent>, StreamingEditorError> {
+    let dom = tl::parse(content, tl::ParserOptions::default()).context(ParsingInputFailedSnafu)?;
+
+    HtmlContent::import(dom).context(LoadingParsedHtmlFailedSnafu)
+}
+
+/// adds the position of the failing command within the pipeline definition
+fn running_pipeline_failed(
+    pipeline_definition: &ExpandedDefinition,
+    error: PipelineError,
+) -> StreamingEditorError {
+    let command_path = error
+        .nested_errors()
+        .map(|nested| match nested {
+            PipelineError::CommandFailed {
+                command,
+                index,
+                spans,
+                ..
+            } => format!(
+                "{}[{}]",
+                command,
+                pipeline_definition.command_index(spans, *index)
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join(" > ");
+    let location = error
+        .span()
+        .and_then(|span| pipeline_definition.locate(span.start));
+
+    RunningPipelineFailedSnafu {
+        command_path,
+        file: location
+            .as_ref()
+            .and_then(|location| location.path.map(Path::to_path_buf)),
+        line: location.as_ref().map(|location| location.line),
+        column: location.as_ref().map(|location| location.column),
     }
+    .into_error(error)
+}
+
+fn boxed(nodes: Vec<rctree::Node<HtmlContent>>) -> Vec<Box<dyn HtmlRenderable>> {
+    nodes
+        .into_iter()
+        .map(|n| Box::new(n) as Box<dyn HtmlRenderable>)
+        .collect()
+}
+
+/// each node's HTML on its own line
+fn render_lines(nodes: &[rctree::Node<HtmlContent>]) -> String {
+    nodes
+        .iter()
+        .map(|node| format!("{}\n", node.outer_html()))
+        .collect()
+}
+
+/// 1-based line and column (counting characters) of a byte offset within the text
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
 }
 
 pub fn report<E>(err: &E)
@@ -153,10 +639,24 @@ where
     }
 }
 
+/// Single line JSON object for errors happening outside the editor, e.g. while opening files,
+/// with the same `kind`, `message` and `causes` fields as `StreamingEditorError::to_json`
+pub fn error_json(kind: &str, message: &str, cause: Option<&dyn std::error::Error>) -> String {
+    let causes = std::iter::successors(cause, |e| e.source())
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>();
+
+    JsonObject::new()
+        .string("kind", kind)
+        .string("message", message)
+        .strings("causes", &causes)
+        .finish()
+}
+
 /// Is the value directly defined or is it a sub-pipeline?
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum ValueSource<'a> {
-    StringValue(&'a str),
+    StringValue(Cow<'a, str>),
     SubPipeline(StringValueCreatingPipeline<'a>),
 }
 
@@ -166,10 +666,56 @@ impl<'a> ValueSource<'a> {
         element: &rctree::Node<HtmlContent>,
     ) -> Result<Vec<String>, PipelineError> {
         match self {
-            ValueSource::StringValue(value) => Ok(vec![String::from(*value)]),
+            ValueSource::StringValue(value) => Ok(vec![value.to_string()]),
             ValueSource::SubPipeline(pipeline) => pipeline.run_on(element),
         }
     }
+
+    /// renders the value source into a single string.
+    /// A sub-pipeline returning multiple values has to define how they are combined, by ending with `JOIN{…}`
+    /// (or `FIRST`, `LAST`, `COUNT`). Otherwise they are concatenated without separator and a warning is logged,
+    /// in strict mode it fails
+    pub fn render_joined(
+        &self,
+        element: &rctree::Node<HtmlContent>,
+    ) -> Result<String, CommandError> {
+        let values = self.render(element).context(SubpipelineFailedSnafu)?;
+        if values.len() > 1 {
+            let strict = matches!(self, ValueSource::SubPipeline(pipeline) if pipeline.is_strict());
+            ensure!(
+                !strict,
+                MultipleValuesSnafu {
+                    count: values.len()
+                }
+            );
+            warn!(
+                "Concatenating {} values without separator, combine them with JOIN, FIRST, LAST or COUNT",
+                values.len()
+            );
+        }
+
+        Ok(values.join(""))
+    }
+
+    /// lets rendering multiple values into a single string fail
+    pub(crate) fn make_strict(&mut self) {
+        if let ValueSource::SubPipeline(pipeline) = self {
+            pipeline.make_strict();
+        }
+    }
+}
+
+/// warns about an empty result set, or fails in strict mode
+pub(crate) fn check_result_set(
+    result: Vec<rctree::Node<HtmlContent>>,
+    strict: bool,
+) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+    if result.is_empty() {
+        ensure!(!strict, EmptyResultSetSnafu);
+        warn!("Command resulted in an empty result set");
+    }
+
+    Ok(result)
 }
 
 pub(crate) fn load_html_file(file_path: &str) -> Result<rctree::Node<HtmlContent>, CommandError> {
@@ -187,6 +733,15 @@ pub(crate) fn load_html_file(file_path: &str) -> Result<rctree::Node<HtmlContent
     HtmlContent::import(dom).context(LoadingParsedCommandHtmlFailedSnafu)
 }
 
+pub(crate) fn parse_html_fragment(
+    html: &str,
+) -> Result<Vec<rctree::Node<HtmlContent>>, CommandError> {
+    let dom =
+        tl::parse(html, tl::ParserOptions::default()).context(ParsingCommandInputFailedSnafu)?;
+
+    HtmlContent::import_fragment(dom).context(LoadingParsedCommandHtmlFailedSnafu)
+}
+
 #[cfg(test)]
 pub(crate) fn load_inline_html(html: &str) -> rctree::Node<HtmlContent> {
     let dom = tl::parse(html, tl::ParserOptions::default()).unwrap();
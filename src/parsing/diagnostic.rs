@@ -0,0 +1,705 @@
+use peg::error::ParseError;
+use peg::str::LineCol;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::parsing::{take_regex_error, ExpandedDefinition};
+
+/// Known commands by category, used to summarize what the parser expected instead of listing every command.
+/// Some names (e.g. `FIRST`) belong to multiple categories.
+const COMMAND_CATEGORIES: [(&str, &[&str]); 5] = [
+    (
+        "an element processing command",
+        &[
+            "ADD-CLASS",
+            "APPEND-COMMENT",
+            "APPEND-ELEMENT",
+            "APPEND-HTML",
+            "APPEND-TEXT-CONTENT",
+            "CHILDREN",
+            "CLEAR-ALL-ATTRS-EXCEPT",
+            "CLEAR-ATTR",
+            "CLEAR-CONTENT",
+            "CLOSEST",
+            "COPY",
+            "EXPECT-COUNT",
+            "EXTRACT-ELEMENT",
+            "FILTER",
+            "FIRST",
+            "FOR-EACH",
+            "IF",
+            "INSERT-AFTER",
+            "INSERT-BEFORE",
+            "LAST",
+            "MAP",
+            "MOVE",
+            "NEXT-SIBLING",
+            "NOT",
+            "NTH",
+            "ONLY",
+            "PARENT",
+            "PREPEND-COMMENT",
+            "PREPEND-ELEMENT",
+            "PREPEND-HTML",
+            "PREPEND-TEXT-CONTENT",
+            "PREV-SIBLING",
+            "REMOVE-CLASS",
+            "REMOVE-ELEMENT",
+            "REMOVE-STYLE",
+            "RENAME-ATTR",
+            "RENAME-ELEMENT",
+            "REPLACE-CLASS",
+            "REPLACE-ELEMENT",
+            "REQUIRE",
+            "ROOT",
+            "SET-ATTR",
+            "SET-INNER-HTML",
+            "SET-STYLE",
+            "SET-TAG-NAME",
+            "SET-TEXT-CONTENT",
+            "SLICE",
+            "TOGGLE-CLASS",
+            "UNLESS",
+            "UNWRAP",
+            "WITH",
+            "WITHOUT",
+            "WRAP",
+            "WRAP-INNER",
+        ],
+    ),
+    (
+        "an element creating command",
+        &[
+            "CREATE-ELEMENT",
+            "KEEP",
+            "LOAD-FILE",
+            "NEW",
+            "PARSE-HTML",
+            "QUERY-REPLACED",
+            "SOURCE",
+        ],
+    ),
+    (
+        "an element selecting command",
+        &[
+            "PARENT",
+            "QUERY-ELEMENT",
+            "QUERY-PARENT",
+            "QUERY-ROOT",
+            "THIS",
+            "USE-ELEMENT",
+            "USE-PARENT",
+        ],
+    ),
+    (
+        "a value extracting command",
+        &[
+            "GET-ATTR",
+            "GET-ATTR-NAMES",
+            "GET-COMMENTS",
+            "GET-INNER-HTML",
+            "GET-OUTER-HTML",
+            "GET-RAW-TEXT",
+            "GET-STYLE",
+            "GET-TAG-NAME",
+            "GET-TEXT-CONTENT",
+        ],
+    ),
+    (
+        "a value processing command",
+        &[
+            "ADD-PREFIX",
+            "ADD-SUFFIX",
+            "COLLAPSE-WHITESPACE",
+            "COUNT",
+            "DEFAULT",
+            "FIRST",
+            "FORMAT",
+            "HTML-ESCAPE",
+            "HTML-UNESCAPE",
+            "JOIN",
+            "LAST",
+            "REGEX-EXTRACT",
+            "REGEX-REPLACE",
+            "SLUGIFY",
+            "SORT",
+            "SPLIT",
+            "SUBSTRING",
+            "TO-LOWER",
+            "TO-UPPER",
+            "TRIM",
+            "TRUNCATE",
+            "UNIQUE",
+            "URL-DECODE",
+            "URL-ENCODE",
+        ],
+    ),
+];
+
+/// messages of the grammar's validating actions,
+/// see `regex_value`, `string_value`, `format_template` and `count_range`
+const INVALID_REGEX: &str = "valid regular expression";
+const INVALID_ESCAPE_SEQUENCE: &str = "valid escape sequence";
+const INVALID_FORMAT_TEMPLATE: &str = "valid FORMAT template";
+const INVALID_COUNT_RANGE: &str = "maximum not below minimum count";
+
+/// Kind of a pipeline parsing error. The codes are stable, so they can be looked up or matched by tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    /// `P001`: a command name which doesn't exist (at this place)
+    UnknownCommand,
+    /// `P002`: anything else the parser could not make sense of
+    UnexpectedInput,
+    /// `P003`: the pipeline ended while a command was still incomplete
+    UnexpectedEnd,
+    /// `P004`: a regular expression which can't be compiled
+    InvalidRegex,
+    /// `P005`: a string value with an unknown or malformed escape sequence
+    InvalidEscapeSequence,
+    /// `P006`: a count range whose maximum is below its minimum, e.g. `EXPECT-COUNT{5, 2}`
+    InvalidCountRange,
+    /// `P007`: a `FORMAT` template with a malformed placeholder or a single brace
+    InvalidFormatTemplate,
+}
+
+impl DiagnosticCode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagnosticCode::UnknownCommand => "P001",
+            DiagnosticCode::UnexpectedInput => "P002",
+            DiagnosticCode::UnexpectedEnd => "P003",
+            DiagnosticCode::InvalidRegex => "P004",
+            DiagnosticCode::InvalidEscapeSequence => "P005",
+            DiagnosticCode::InvalidCountRange => "P006",
+            DiagnosticCode::InvalidFormatTemplate => "P007",
+        }
+    }
+}
+
+/// Human-readable description of a pipeline parsing error,
+/// showing the offending line with a caret, what was expected and a suggestion for misspelled commands.
+/// The position refers to where the offending part was written, before resolving `INCLUDE` and `CALL`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineDiagnostic {
+    code: DiagnosticCode,
+    message: String,
+    file: Option<PathBuf>,
+    line: usize,
+    column: usize,
+    source_line: String,
+    marker_length: usize,
+    expected: Vec<String>,
+    suggestion: Option<String>,
+}
+
+impl PipelineDiagnostic {
+    pub(crate) fn new(definition: &ExpandedDefinition, error: &ParseError<LineCol>) -> Self {
+        let offset = error.location.offset;
+        let (file, line, column, source_line) = match definition.locate(offset) {
+            Some(location) => (
+                location.path.map(Path::to_path_buf),
+                location.line,
+                location.column,
+                location.source_line.to_owned(),
+            ),
+            None => (
+                None,
+                error.location.line,
+                error.location.column,
+                String::new(),
+            ),
+        };
+        let tokens = error
+            .expected
+            .tokens()
+            .map(|t| t.trim_matches('"'))
+            .collect::<Vec<_>>();
+
+        let mut diagnostic = PipelineDiagnostic {
+            code: DiagnosticCode::UnexpectedInput,
+            message: String::new(),
+            file,
+            line,
+            column,
+            source_line,
+            marker_length: 1,
+            expected: vec![],
+            suggestion: None,
+        };
+
+        let remaining = &definition.text()[offset..];
+        let word = remaining
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .next()
+            .unwrap_or_default();
+
+        let string_error = if tokens.contains(&INVALID_REGEX) {
+            Some((DiagnosticCode::InvalidRegex, "invalid regular expression"))
+        } else if tokens.contains(&INVALID_ESCAPE_SEQUENCE) {
+            Some((
+                DiagnosticCode::InvalidEscapeSequence,
+                "invalid escape sequence in string",
+            ))
+        } else if tokens.contains(&INVALID_FORMAT_TEMPLATE) {
+            Some((
+                DiagnosticCode::InvalidFormatTemplate,
+                "invalid FORMAT template, placeholders are `{}` or positions like `{0}`, literal braces are doubled",
+            ))
+        } else {
+            None
+        };
+
+        if let Some((code, message)) = string_error {
+            // the grammar only validates after reading the whole string, so mark all of it
+            diagnostic.code = code;
+            diagnostic.message = String::from(message);
+            diagnostic.mark_preceding_string();
+            if diagnostic.code == DiagnosticCode::InvalidRegex {
+                if let Some(error) = take_regex_error(offset) {
+                    diagnostic.message = format!("{}: {}", message, Self::regex_reason(&error));
+                }
+            }
+        } else if tokens.contains(&INVALID_COUNT_RANGE) {
+            diagnostic.code = DiagnosticCode::InvalidCountRange;
+            diagnostic.message = String::from("maximum count below minimum");
+            diagnostic.mark_preceding_count_range();
+        } else if remaining.trim().is_empty() {
+            diagnostic.code = DiagnosticCode::UnexpectedEnd;
+            diagnostic.message = String::from("unexpected end of pipeline");
+            diagnostic.expected = Self::summarize(&tokens);
+        } else if word.starts_with(|c: char| c.is_ascii_uppercase())
+            && tokens.iter().any(|t| Self::is_command(t))
+        {
+            diagnostic.code = DiagnosticCode::UnknownCommand;
+            diagnostic.message = format!("unknown command `{}`", word);
+            diagnostic.marker_length = word.chars().count();
+            diagnostic.expected = Self::summarize(&tokens);
+            diagnostic.suggestion = Self::closest_command(word, &tokens);
+        } else {
+            diagnostic.message = format!(
+                "unexpected `{}`",
+                remaining.chars().next().unwrap_or_default()
+            );
+            diagnostic.expected = Self::summarize(&tokens);
+        }
+
+        diagnostic
+    }
+
+    pub fn code(&self) -> DiagnosticCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// file the offending line was read from, `None` if the pipeline definition was given directly
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// line of the error, starting at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// column (in characters) of the error, starting at 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// summary of what the parser would have accepted instead
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// name of a known command close to the misspelled one
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    /// the syntax errors show the pattern with their own marker, the reason is in the last line
+    fn regex_reason(error: &regex::Error) -> String {
+        let error = error.to_string();
+        let reason = error.lines().last().unwrap_or_default();
+
+        String::from(reason.strip_prefix("error: ").unwrap_or(reason))
+    }
+
+    /// like `mark_preceding_string`, for the `min, max` numbers before the position
+    fn mark_preceding_count_range(&mut self) {
+        let prefix = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .collect::<String>();
+        let range = prefix.trim_end();
+        let numbers = range
+            .rfind(|c: char| !(c.is_ascii_digit() || c.is_whitespace() || c == ','))
+            .map_or(0, |index| index + 1);
+        let start = range[numbers..]
+            .find(|c: char| c.is_ascii_digit())
+            .map_or(numbers, |index| numbers + index);
+
+        let start_column = prefix[..start].chars().count() + 1;
+        self.marker_length = range[start..].chars().count();
+        self.column = start_column;
+    }
+
+    /// moves the marker from the end of a string value to cover all of it
+    fn mark_preceding_string(&mut self) {
+        let prefix = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .collect::<String>();
+
+        let start = if prefix.ends_with("\"#") {
+            prefix.rfind("r#\"")
+        } else {
+            prefix.chars().last().and_then(|delimiter| {
+                let opening = &prefix[..prefix.len() - delimiter.len_utf8()];
+                opening
+                    .rmatch_indices(delimiter)
+                    .map(|(index, _)| index)
+                    .find(|index| !opening[..*index].ends_with('\\'))
+                    .map(|index| {
+                        if delimiter == '"' && opening[..index].ends_with('r') {
+                            index - 1
+                        } else {
+                            index
+                        }
+                    })
+            })
+        };
+
+        if let Some(start) = start {
+            let start_column = prefix[..start].chars().count() + 1;
+            self.marker_length = self.column - start_column;
+            self.column = start_column;
+        }
+    }
+
+    fn is_command(token: &str) -> bool {
+        let name = token.trim_end_matches('{');
+        COMMAND_CATEGORIES
+            .iter()
+            .any(|(_, commands)| commands.contains(&name))
+    }
+
+    /// replaces all commands by their categories, keeps all other tokens
+    fn summarize(tokens: &[&str]) -> Vec<String> {
+        let names = tokens
+            .iter()
+            .map(|t| t.trim_end_matches('{'))
+            .collect::<Vec<_>>();
+
+        let mut summary = vec![];
+        for (category, commands) in COMMAND_CATEGORIES {
+            // names shared with other categories don't prove this category is expected
+            let is_expected = commands.iter().any(|command| {
+                names.contains(command)
+                    && COMMAND_CATEGORIES
+                        .iter()
+                        .filter(|(_, other)| other.contains(command))
+                        .count()
+                        == 1
+            });
+            if is_expected {
+                summary.push(String::from(category));
+            }
+        }
+
+        for token in tokens {
+            if Self::is_command(token) {
+                continue;
+            }
+
+            let description = match *token {
+                "EOF" => String::from("end of pipeline"),
+                t if t.starts_with('[') && t.contains("'a'..='z'") => String::from("a name"),
+                t if t.starts_with('[') && t.contains("'0'..='9'") => String::from("a number"),
+                t => format!("`{}`", t),
+            };
+            if !summary.contains(&description) {
+                summary.push(description);
+            }
+        }
+
+        summary
+    }
+
+    fn closest_command(word: &str, tokens: &[&str]) -> Option<String> {
+        let maximum_distance = (word.len() / 3).max(1);
+
+        tokens
+            .iter()
+            .filter(|t| Self::is_command(t))
+            .map(|t| t.trim_end_matches('{'))
+            .map(|command| (levenshtein_distance(word, command), command))
+            .filter(|(distance, _)| *distance <= maximum_distance)
+            .min()
+            .map(|(_, command)| String::from(command))
+    }
+}
+
+impl Display for PipelineDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let gutter = " ".repeat(self.line.to_string().len());
+        // keep tabs, so the marker lines up with the source line
+        let indentation = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        writeln!(f, "{} [{}]", self.message, self.code.code())?;
+        write!(
+            f,
+            "{}--> line {}, column {}",
+            gutter, self.line, self.column
+        )?;
+        match &self.file {
+            Some(file) => writeln!(f, " of {}", file.display())?,
+            None => writeln!(f)?,
+        }
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", self.line, self.source_line)?;
+        write!(
+            f,
+            "{} | {}{}",
+            gutter,
+            indentation,
+            "^".repeat(self.marker_length.max(1))
+        )?;
+        match self.expected.as_slice() {
+            [] => (),
+            [expected] => write!(f, "\n{} = expected {}", gutter, expected)?,
+            expected => write!(f, "\n{} = expected one of: {}", gutter, expected.join(", "))?,
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n{} = help: did you mean `{}`?", gutter, suggestion)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// number of single character insertions, deletions or substitutions to turn one word into the other
+fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::diagnostic::{levenshtein_distance, COMMAND_CATEGORIES};
+    use crate::parsing::{expand_pipeline_definition, grammar, ExpandedDefinition};
+    use crate::{DiagnosticCode, PipelineDiagnostic};
+
+    fn diagnose(definition: &str) -> PipelineDiagnostic {
+        PipelineDiagnostic::new(
+            &ExpandedDefinition::unexpanded(definition),
+            &grammar::pipeline(definition).unwrap_err(),
+        )
+    }
+
+    #[test]
+    fn levenshtein_distance_counts_edits() {
+        assert_eq!(levenshtein_distance("SET-ATR", "SET-ATTR"), 1);
+        assert_eq!(levenshtein_distance("WITHOUT", "WITH"), 3);
+        assert_eq!(levenshtein_distance("", "ONLY"), 4);
+        assert_eq!(levenshtein_distance("ONLY", "ONLY"), 0);
+    }
+
+    #[test]
+    fn unknown_command_suggests_closest_one() {
+        let diagnostic = diagnose("ONLY{body} | SET-ATR{title ↤ 'x'}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::UnknownCommand);
+        assert_eq!(diagnostic.suggestion(), Some("SET-ATTR"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "unknown command `SET-ATR` [P001]
+ --> line 1, column 14
+  |
+1 | ONLY{body} | SET-ATR{title ↤ 'x'}
+  |              ^^^^^^^
+  = expected an element processing command
+  = help: did you mean `SET-ATTR`?"
+        );
+    }
+
+    #[test]
+    fn unknown_command_in_value_pipeline() {
+        let diagnostic = diagnose("WITH{p ↦ SET-ATTR{title ↤ THIS | GET-TEXT-CONTENT | TO-LOWRE}}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::UnknownCommand);
+        assert_eq!(diagnostic.suggestion(), Some("TO-LOWER"));
+        assert!(diagnostic
+            .expected()
+            .contains(&String::from("a value processing command")));
+    }
+
+    #[test]
+    fn unrelated_word_gets_no_suggestion() {
+        let diagnostic = diagnose("ONLY{body} | FROBNICATE");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::UnknownCommand);
+        assert_eq!(diagnostic.suggestion(), None);
+    }
+
+    #[test]
+    fn unexpected_end_of_pipeline() {
+        let diagnostic = diagnose("ONLY{body");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::UnexpectedEnd);
+        assert!(diagnostic.expected().contains(&String::from("`}`")));
+    }
+
+    #[test]
+    fn unexpected_input_on_later_line() {
+        let diagnostic = diagnose("ONLY{body}\n  | WITHOUT{p}}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::UnexpectedInput);
+        assert_eq!(diagnostic.line(), 2);
+        assert_eq!(diagnostic.column(), 15);
+        assert_eq!(diagnostic.message(), "unexpected `}`");
+    }
+
+    #[test]
+    fn invalid_regex_marks_whole_string() {
+        let diagnostic =
+            diagnose(r"WITH{p ↦ SET-ATTR{x ↤ THIS | GET-ATTR{y} | REGEX-REPLACE{'a\'(' ↤ ''}}}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::InvalidRegex);
+        assert_eq!(
+            diagnostic.message(),
+            "invalid regular expression: unclosed group"
+        );
+        assert_eq!(diagnostic.column(), 58);
+        assert!(diagnostic
+            .to_string()
+            .ends_with(&format!("{}^^^^^^", " ".repeat(57))));
+    }
+
+    #[test]
+    fn invalid_regex_reason_is_that_of_the_failing_one() {
+        let diagnostic = diagnose(
+            "ONLY{p} | SET-ATTR{x ↤ THIS | GET-ATTR{y} | REGEX-REPLACE{'(a)' ↤ ''} | REGEX-REPLACE{'[b' ↤ ''}}",
+        );
+
+        assert_eq!(
+            diagnostic.message(),
+            "invalid regular expression: unclosed character class"
+        );
+
+        let diagnostic =
+            diagnose("ONLY{p} | SET-ATTR{x ↤ THIS | GET-ATTR{y} | REGEX-REPLACE{r\"a\n(b\" ↤ ''}}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::InvalidRegex);
+        assert_eq!(
+            diagnostic.message(),
+            "invalid regular expression: unclosed group"
+        );
+    }
+
+    #[test]
+    fn invalid_format_template_marks_whole_string() {
+        let diagnostic = diagnose("ONLY{p} | SET-ATTR{x ↤ THIS | GET-ATTR{y} | FORMAT{'{0'}}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::InvalidFormatTemplate);
+        assert_eq!(diagnostic.column(), 52);
+        assert!(diagnostic
+            .to_string()
+            .ends_with(&format!("{}^^^^", " ".repeat(51))));
+    }
+
+    #[test]
+    fn invalid_escape_sequence_marks_whole_string() {
+        let diagnostic = diagnose(r#"ONLY{p} | SET-TEXT-CONTENT{"\q"}"#);
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::InvalidEscapeSequence);
+        assert_eq!(diagnostic.column(), 28);
+    }
+
+    #[test]
+    fn count_range_with_maximum_below_minimum_is_rejected() {
+        let diagnostic = diagnose("ONLY{ul} | REQUIRE{li, 5, 2}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::InvalidCountRange);
+        assert_eq!(diagnostic.column(), 24);
+        assert!(diagnostic
+            .to_string()
+            .ends_with(&format!("{}^^^^", " ".repeat(23))));
+
+        let diagnostic = diagnose("ONLY{li} | EXPECT-COUNT{5,2}");
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::InvalidCountRange);
+        assert_eq!(diagnostic.column(), 25);
+    }
+
+    #[test]
+    fn error_in_called_definition_shows_line_where_it_is_written() {
+        let expanded = expand_pipeline_definition(
+            "# tidy up\nDEFINE clean {\n  WITHOUT{script} | REMOVE-CLAS{x}\n}\nONLY{body}\n| CALL{clean}",
+        )
+        .unwrap();
+        let diagnostic =
+            PipelineDiagnostic::new(&expanded, &grammar::pipeline(expanded.text()).unwrap_err());
+
+        assert_eq!(diagnostic.code(), DiagnosticCode::UnknownCommand);
+        assert_eq!(diagnostic.file(), None);
+        assert_eq!(
+            diagnostic.to_string(),
+            "unknown command `REMOVE-CLAS` [P001]
+ --> line 3, column 21
+  |
+3 |   WITHOUT{script} | REMOVE-CLAS{x}
+  |                     ^^^^^^^^^^^
+  = expected an element processing command
+  = help: did you mean `REMOVE-CLASS`?"
+        );
+    }
+
+    #[test]
+    fn all_commands_of_the_grammar_are_categorized() {
+        for definition in [
+            "X",
+            "WITH{p ↦ SET-ATTR{x ↤ X",
+            "WITH{p ↦ SET-ATTR{x ↤ THIS | X",
+            "WITH{p ↦ SET-ATTR{x ↤ THIS | GET-ATTR{y} | X",
+            "APPEND-ELEMENT{X",
+        ] {
+            let error = grammar::pipeline(definition).unwrap_err();
+            for token in error.expected.tokens() {
+                let name = token.trim_matches('"').trim_end_matches('{');
+                if name != "EOF"
+                    && name.len() > 1
+                    && name.chars().all(|c| c.is_ascii_uppercase() || c == '-')
+                {
+                    assert!(
+                        COMMAND_CATEGORIES
+                            .iter()
+                            .any(|(_, commands)| commands.contains(&name)),
+                        "{} is not categorized",
+                        name
+                    );
+                }
+            }
+        }
+    }
+}
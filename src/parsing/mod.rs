@@ -1,19 +1,51 @@
 use peg::parser;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
 
 use crate::{
     element_creating::{ElementCreatingCommand, ElementCreatingPipeline},
-    element_processing::{ElementProcessingCommand, ElementProcessingPipeline},
+    element_processing::{
+        AttributePattern, ElementCondition, ElementProcessingCommand, ElementProcessingPipeline,
+        InsertPosition, ValueComparison,
+    },
     string_creating::{
-        ElementSelectingCommand, StringValueCreatingPipeline, ValueExtractingCommand,
-        ValueProcessingCommand,
+        format_template_parts, ElementSelectingCommand, StringValueCreatingPipeline,
+        ValueExtractingCommand, ValueProcessingCommand,
     },
-    CssAttributeComparison, CssAttributeSelector, CssPseudoClass, CssSelector, CssSelectorList,
-    CssSelectorPath, CssSelectorStep, ValueSource,
+    CompiledRegex, CssAttributeComparison, CssAttributeSelector, CssPseudoClass, CssSelector,
+    CssSelectorList, CssSelectorPath, CssSelectorStep, ValueSource,
 };
 
+mod diagnostic;
+mod source;
 #[cfg(test)]
 mod tests;
 
+pub use diagnostic::{DiagnosticCode, PipelineDiagnostic};
+use source::SourceChunk;
+pub(crate) use source::{
+    expand_pipeline_definition, expand_pipeline_file, expand_session_command, ExpandedDefinition,
+};
+
+thread_local! {
+    /// why regular expressions can't be compiled, by the offset after them,
+    /// as the grammar can only report a fixed message
+    static REGEX_ERRORS: RefCell<HashMap<usize, regex::Error>> = RefCell::new(HashMap::new());
+}
+
+fn record_regex_error(offset: usize, error: regex::Error) {
+    REGEX_ERRORS.with(|errors| errors.borrow_mut().insert(offset, error));
+}
+
+/// the error of the regular expression ending at the offset, forgetting all recorded ones
+fn take_regex_error(offset: usize) -> Option<regex::Error> {
+    REGEX_ERRORS
+        .with(|errors| errors.borrow_mut().drain().find(|(o, _)| *o == offset))
+        .map(|(_, error)| error)
+}
+
 /// utility method to "prepend" the first found CSS selector step before the following list,
 /// generated by the recursive PEG rule
 fn build_css_path<'a>(
@@ -29,6 +61,91 @@ fn build_css_path<'a>(
     result
 }
 
+/// resolves the escape sequences of a quoted string: `\n`, `\r`, `\t`, `\0`, `\\`, `\"`, `\'`, `\?`
+/// and `\u{…}` with the hexadecimal code point of a character
+fn unescape(value: &str) -> Result<Cow<'_, str>, UnescapeError> {
+    if !value.contains('\\') {
+        return Ok(Cow::Borrowed(value));
+    }
+
+    let mut result = String::with_capacity(value.len());
+    let mut characters = value.chars();
+
+    while let Some(c) = characters.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match characters.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(c @ ('\\' | '"' | '\'' | '?')) => result.push(c),
+            Some('u') => {
+                if characters.next() != Some('{') {
+                    return Err(UnescapeError);
+                }
+                let mut code_point = String::new();
+                loop {
+                    match characters.next() {
+                        Some('}') => break,
+                        Some(c) => code_point.push(c),
+                        None => return Err(UnescapeError),
+                    }
+                }
+                let character = u32::from_str_radix(&code_point, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(UnescapeError)?;
+                result.push(character);
+            }
+            _ => return Err(UnescapeError),
+        }
+    }
+
+    Ok(Cow::Owned(result))
+}
+
+/// a quoted string contains an unknown or malformed escape sequence
+#[derive(Debug, PartialEq, Eq)]
+struct UnescapeError;
+
+fn spanned_element_creating_pipeline<'a>(
+    creation: (ElementCreatingCommand<'a>, Range<usize>),
+    processing: Option<Vec<(ElementProcessingCommand<'a>, Range<usize>)>>,
+) -> ElementCreatingPipeline<'a> {
+    let (commands, spans): (Vec<_>, Vec<_>) = processing.unwrap_or_default().into_iter().unzip();
+
+    ElementCreatingPipeline::new(creation.0, Some(commands))
+        .with_spans([vec![creation.1], spans].concat())
+}
+
+fn spanned_string_creating_pipeline<'a>(
+    element_selector: (ElementSelectingCommand<'a>, Range<usize>),
+    value_extractor: (ValueExtractingCommand<'a>, Range<usize>),
+    value_processing: Option<Vec<(ValueProcessingCommand<'a>, Range<usize>)>>,
+) -> StringValueCreatingPipeline<'a> {
+    let spans = vec![element_selector.1, value_extractor.1];
+
+    match value_processing {
+        None => StringValueCreatingPipeline::new(element_selector.0, value_extractor.0)
+            .with_spans(spans),
+        Some(value_processing) => {
+            let (commands, processing_spans): (Vec<_>, Vec<_>) =
+                value_processing.into_iter().unzip();
+
+            StringValueCreatingPipeline::with_value_processing(
+                element_selector.0,
+                value_extractor.0,
+                commands,
+            )
+            .with_spans([spans, processing_spans].concat())
+        }
+    }
+}
+
 parser! {
   pub grammar grammar() for str {
         rule whitespace()
@@ -38,6 +155,9 @@ parser! {
         rule assign_marker()
             = "↤"
             / "<="
+        /// a command together with its byte range within the pipeline definition, to report errors when running it
+        rule spanned<T>(command: rule<T>) -> (T, Range<usize>)
+            = start:position!() c:command() end:position!() { (c, start..end) }
         rule iterate_marker()
             = "↦"
             / "=>"
@@ -88,10 +208,30 @@ parser! {
         pub(crate) rule css_selector_list() -> CssSelectorList<'input>
             = v:(css_selector_path() ++ ",") { CssSelectorList::new(v) }
 
-        pub(super) rule string_value() -> &'input str
-            = "\"" s:$([^'"']+) "\"" { s }
-            / "'" s:$([^'\'']+) "'" { s }
-            / "?" s:$([^'?']+) "?" { s }
+        rule escaped_character()
+            = "\\" [_]
+        /// source of a quoted string between its delimiters, escape sequences are not resolved yet
+        rule quoted_string() -> &'input str
+            = "\"" s:$((escaped_character() / [^'"' | '\\'])*) "\"" { s }
+            / "'" s:$((escaped_character() / [^'\'' | '\\'])*) "'" { s }
+            / "?" s:$((escaped_character() / [^'?' | '\\'])*) "?" { s }
+        /// string taken verbatim, may span multiple lines
+        rule raw_string() -> &'input str
+            = "r#\"" s:$((!"\"#" [_])*) "\"#" { s }
+            / "r\"" s:$([^'"']*) "\"" { s }
+        pub(super) rule string_value() -> Cow<'input, str>
+            = s:raw_string() { Cow::Borrowed(s) }
+            / s:quoted_string() {? unescape(s).or(Err("valid escape sequence")) }
+
+        /// regular expressions are passed to the RegEx engine verbatim, which resolves the escape sequences.
+        /// Why one can't be compiled is recorded for `PipelineDiagnostic`
+        rule regex_value() -> CompiledRegex<'input>
+            = r:(raw_string() / quoted_string()) end:position!() {?
+                CompiledRegex::new(r).map_err(|error| {
+                    record_regex_error(end, error);
+                    "valid regular expression"
+                })
+            }
 
         rule value_source() -> ValueSource<'input>
             = v:string_value() { ValueSource::StringValue(v) }
@@ -101,14 +241,96 @@ parser! {
             = ("EXTRACT-ELEMENT" / "ONLY") "{" whitespace()?  oc:css_selector_list() whitespace()? "}" { ElementProcessingCommand::ExtractElement(oc) }
         rule remove_element_command() -> ElementProcessingCommand<'input>
             = ("REMOVE-ELEMENT" / "WITHOUT") "{" whitespace()? oc:css_selector_list() whitespace()? "}" { ElementProcessingCommand::RemoveElement(oc) }
+        rule filter_command() -> ElementProcessingCommand<'input>
+            = "FILTER{" whitespace()? oc:css_selector_list() whitespace()? "}" { ElementProcessingCommand::Filter(oc) }
+        rule filter_not_command() -> ElementProcessingCommand<'input>
+            = "NOT{" whitespace()? oc:css_selector_list() whitespace()? "}" { ElementProcessingCommand::FilterNot(oc) }
+        rule first_command() -> ElementProcessingCommand<'input>
+            = "FIRST" { ElementProcessingCommand::First }
+        rule last_command() -> ElementProcessingCommand<'input>
+            = "LAST" { ElementProcessingCommand::Last }
+        rule nth_command() -> ElementProcessingCommand<'input>
+            = "NTH{" whitespace()? n:number() whitespace()? "}" { ElementProcessingCommand::Nth(n) }
+        rule slice_command() -> ElementProcessingCommand<'input>
+            = "SLICE{" whitespace()? s:number()? whitespace()? ".." whitespace()? e:number()? whitespace()? "}" { ElementProcessingCommand::Slice(s, e) }
+        rule parent_command() -> ElementProcessingCommand<'input>
+            = "PARENT" { ElementProcessingCommand::Parent }
+        rule closest_command() -> ElementProcessingCommand<'input>
+            = "CLOSEST{" whitespace()? oc:css_selector_list() whitespace()? "}" { ElementProcessingCommand::Closest(oc) }
+        rule children_command() -> ElementProcessingCommand<'input>
+            = "CHILDREN{" whitespace()? oc:css_selector_list() whitespace()? "}" { ElementProcessingCommand::Children(Some(oc)) }
+            / "CHILDREN" { ElementProcessingCommand::Children(None) }
+        rule next_sibling_command() -> ElementProcessingCommand<'input>
+            = "NEXT-SIBLING" { ElementProcessingCommand::NextSibling }
+        rule prev_sibling_command() -> ElementProcessingCommand<'input>
+            = "PREV-SIBLING" { ElementProcessingCommand::PreviousSibling }
+        rule root_command() -> ElementProcessingCommand<'input>
+            = "ROOT" { ElementProcessingCommand::Root }
         rule for_each_command() -> ElementProcessingCommand<'input>
             = ("FOR-EACH"/"WITH") "{" whitespace()? oc:css_selector_list() whitespace()? iterate_marker() whitespace()? sp:pipeline() whitespace()?  "}" { ElementProcessingCommand::ForEach(oc, sp) }
+        rule value_comparison() -> ValueComparison
+            = "!=" { ValueComparison::NotEquals }
+            / "^=" { ValueComparison::StartsWith }
+            / "$=" { ValueComparison::EndsWith }
+            / "*=" { ValueComparison::Contains }
+            / "<=" { ValueComparison::LessOrEqual }
+            / ">=" { ValueComparison::GreaterOrEqual }
+            / "<" { ValueComparison::LessThan }
+            / ">" { ValueComparison::GreaterThan }
+            / "=" { ValueComparison::Equals }
+        rule has_attr_condition() -> ElementCondition<'input>
+            = "HAS-ATTR{" whitespace()? a:identifier() whitespace()? "}" { ElementCondition::HasAttribute(a) }
+        pub(super) rule element_condition() -> ElementCondition<'input>
+            = has_attr_condition()
+            / p:string_creating_pipeline() whitespace()? c:value_comparison() whitespace()? v:string_value() { ElementCondition::Compares(p, c, v) }
+            / s:element_selecting_command() { ElementCondition::Selects(s) }
+        rule else_branch() -> ElementProcessingPipeline<'input>
+            = whitespace()? "ELSE{" whitespace()? sp:pipeline() whitespace()? "}" { sp }
+        rule if_command() -> ElementProcessingCommand<'input>
+            = "IF{" whitespace()? c:element_condition() whitespace()? iterate_marker() whitespace()? sp:pipeline() whitespace()? "}" e:else_branch()? { ElementProcessingCommand::If(c, sp, e) }
+        rule unless_command() -> ElementProcessingCommand<'input>
+            = "UNLESS{" whitespace()? c:element_condition() whitespace()? iterate_marker() whitespace()? sp:pipeline() whitespace()? "}" e:else_branch()? { ElementProcessingCommand::Unless(c, sp, e) }
         rule replace_element_command() -> ElementProcessingCommand<'input>
             = ("REPLACE-ELEMENT"/"MAP") "{" whitespace()? oc:css_selector_list() whitespace()? assign_marker() whitespace()? sp:element_subselect_or_creating_pipeline() whitespace()? "}" { ElementProcessingCommand::ReplaceElement(oc, sp)}
+        rule attribute_pattern() -> AttributePattern<'input>
+            = "~" r:regex_value() { AttributePattern::Regex(r) }
+            / g:$(['a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '*' | '?' ]+) {
+                if g.contains(['*', '?']) { AttributePattern::Glob(g) } else { AttributePattern::Name(g) }
+            }
+        rule attribute_pattern_list() -> Vec<AttributePattern<'input>>
+            = p:(attribute_pattern() ++ (whitespace()? "," whitespace()?)) { p }
         rule clear_attr_command() -> ElementProcessingCommand<'input>
             = "CLEAR-ATTR{" whitespace()? a:identifier() whitespace()? "}" { ElementProcessingCommand::ClearAttribute(a) }
+            / "CLEAR-ATTR{" whitespace()? p:attribute_pattern_list() whitespace()? "}" { ElementProcessingCommand::ClearAttributes(p) }
+        rule clear_all_attrs_except_command() -> ElementProcessingCommand<'input>
+            = "CLEAR-ALL-ATTRS-EXCEPT{" whitespace()? p:attribute_pattern_list() whitespace()? "}" { ElementProcessingCommand::ClearAllAttributesExcept(p) }
+        rule rename_attr_command() -> ElementProcessingCommand<'input>
+            = "RENAME-ATTR{" whitespace()? f:identifier() whitespace()? iterate_marker() whitespace()? t:identifier() whitespace()? "}" { ElementProcessingCommand::RenameAttribute(f, t) }
+        rule set_inner_html_command() -> ElementProcessingCommand<'input>
+            = "SET-INNER-HTML{" whitespace()? (assign_marker() whitespace()?)? v:value_source() whitespace()? "}" { ElementProcessingCommand::SetInnerHtml(v) }
+        rule append_html_command() -> ElementProcessingCommand<'input>
+            = "APPEND-HTML{" whitespace()? (assign_marker() whitespace()?)? v:value_source() whitespace()? "}" { ElementProcessingCommand::AppendHtml(v) }
+        rule prepend_html_command() -> ElementProcessingCommand<'input>
+            = "PREPEND-HTML{" whitespace()? (assign_marker() whitespace()?)? v:value_source() whitespace()? "}" { ElementProcessingCommand::PrependHtml(v) }
+        rule rename_element_command() -> ElementProcessingCommand<'input>
+            = ("RENAME-ELEMENT"/"SET-TAG-NAME") "{" whitespace()? (assign_marker() whitespace()?)? v:identifier_value_source() whitespace()? "}" { ElementProcessingCommand::RenameElement(v) }
         rule clear_content_command() -> ElementProcessingCommand<'input>
             = "CLEAR-CONTENT" { ElementProcessingCommand::ClearContent }
+        rule set_style_command() -> ElementProcessingCommand<'input>
+            = "SET-STYLE{" whitespace()? p:identifier() whitespace()? assign_marker() whitespace()? v:value_source() whitespace()? "}" { ElementProcessingCommand::SetStyle(p, v) }
+        rule remove_style_command() -> ElementProcessingCommand<'input>
+            = "REMOVE-STYLE{" whitespace()? p:identifier() whitespace()? "}" { ElementProcessingCommand::RemoveStyle(p) }
+        rule identifier_value_source() -> ValueSource<'input>
+            = value_source()
+            / i:identifier() { ValueSource::StringValue(Cow::Borrowed(i)) }
+        rule add_class_command() -> ElementProcessingCommand<'input>
+            = "ADD-CLASS{" whitespace()? v:identifier_value_source() whitespace()? "}" { ElementProcessingCommand::AddClass(v) }
+        rule remove_class_command() -> ElementProcessingCommand<'input>
+            = "REMOVE-CLASS{" whitespace()? v:identifier_value_source() whitespace()? "}" { ElementProcessingCommand::RemoveClass(v) }
+        rule toggle_class_command() -> ElementProcessingCommand<'input>
+            = "TOGGLE-CLASS{" whitespace()? v:identifier_value_source() whitespace()? "}" { ElementProcessingCommand::ToggleClass(v) }
+        rule replace_class_command() -> ElementProcessingCommand<'input>
+            = "REPLACE-CLASS{" whitespace()? o:identifier_value_source() whitespace()? assign_marker() whitespace()? n:identifier_value_source() whitespace()? "}" { ElementProcessingCommand::ReplaceClass(o, n) }
         rule set_attr_command() -> ElementProcessingCommand<'input>
             = "SET-ATTR{" whitespace()? a:identifier() whitespace()? assign_marker() whitespace()? v:value_source() whitespace()? "}" { ElementProcessingCommand::SetAttribute(a, v) }
         rule set_text_content_command() -> ElementProcessingCommand<'input>
@@ -118,18 +340,73 @@ parser! {
         rule append_comment_command() -> ElementProcessingCommand<'input>
             = "APPEND-COMMENT{" whitespace()? (assign_marker() whitespace()?)? v:value_source() whitespace()? "}" { ElementProcessingCommand::AppendComment(v) }
         rule append_element_command() -> ElementProcessingCommand<'input>
-            = "APPEND-ELEMENT{" whitespace()? (assign_marker() whitespace()?)? sp:element_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::AppendElement(sp) }
+            = "APPEND-ELEMENT{" whitespace()? (assign_marker() whitespace()?)? sp:element_subselect_or_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::AppendElement(sp) }
         rule prepend_text_content_command() -> ElementProcessingCommand<'input>
             = "PREPEND-TEXT-CONTENT{" whitespace()? (assign_marker() whitespace()?)? v:value_source() whitespace()? "}" { ElementProcessingCommand::PrependTextContent(v) }
         rule prepend_comment_command() -> ElementProcessingCommand<'input>
             = "PREPEND-COMMENT{" whitespace()? (assign_marker() whitespace()?)? v:value_source() whitespace()? "}" { ElementProcessingCommand::PrependComment(v) }
         rule prepend_element_command() -> ElementProcessingCommand<'input>
-            = "PREPEND-ELEMENT{" whitespace()? (assign_marker() whitespace()?)? sp:element_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::PrependElement(sp) }
+            = "PREPEND-ELEMENT{" whitespace()? (assign_marker() whitespace()?)? sp:element_subselect_or_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::PrependElement(sp) }
+        rule insert_before_command() -> ElementProcessingCommand<'input>
+            = "INSERT-BEFORE{" whitespace()? (assign_marker() whitespace()?)? sp:element_subselect_or_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::InsertBefore(sp) }
+        rule insert_after_command() -> ElementProcessingCommand<'input>
+            = "INSERT-AFTER{" whitespace()? (assign_marker() whitespace()?)? sp:element_subselect_or_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::InsertAfter(sp) }
+        rule wrap_command() -> ElementProcessingCommand<'input>
+            = "WRAP{" whitespace()? (assign_marker() whitespace()?)? sp:element_subselect_or_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::Wrap(sp) }
+        rule wrap_inner_command() -> ElementProcessingCommand<'input>
+            = "WRAP-INNER{" whitespace()? (assign_marker() whitespace()?)? sp:element_subselect_or_creating_pipeline() whitespace()?  "}" { ElementProcessingCommand::WrapInner(sp) }
+        rule unwrap_command() -> ElementProcessingCommand<'input>
+            = "UNWRAP" { ElementProcessingCommand::Unwrap }
+        rule insert_position() -> InsertPosition
+            = "APPEND" { InsertPosition::Append }
+            / "PREPEND" { InsertPosition::Prepend }
+            / "BEFORE" { InsertPosition::Before }
+            / "AFTER" { InsertPosition::After }
+        rule insert_target_path() -> CssSelectorPath<'input>
+            = !(whitespace()? insert_position() whitespace()? "}") p:css_selector_path() { p }
+        rule insert_target() -> (CssSelectorList<'input>, InsertPosition)
+            = t:(insert_target_path() ++ ",") p:("," whitespace()? p:insert_position() { p })? { (CssSelectorList::new(t), p.unwrap_or_default()) }
+        rule move_command() -> ElementProcessingCommand<'input>
+            = "MOVE{" whitespace()? s:css_selector_list() whitespace()? iterate_marker() whitespace()? t:insert_target() whitespace()? "}" { ElementProcessingCommand::Move(s, t.0, t.1) }
+        rule copy_command() -> ElementProcessingCommand<'input>
+            = "COPY{" whitespace()? s:css_selector_list() whitespace()? iterate_marker() whitespace()? t:insert_target() whitespace()? "}" { ElementProcessingCommand::Copy(s, t.0, t.1) }
+        /// like `css_selector_list()`, but stops before a `,` followed by the expected count
+        rule require_selector_list() -> CssSelectorList<'input>
+            = v:(css_selector_path() ++ ("," !(whitespace()? number() whitespace()? ("," / "}")))) { CssSelectorList::new(v) }
+        /// either exactly `n` or between `min` and `max`
+        rule count_range() -> (usize, Option<usize>)
+            = min:number() whitespace()? max:("," whitespace()? max:number() whitespace()? { max })? {?
+                match max {
+                    Some(max) if max < min => Err("maximum not below minimum count"),
+                    _ => Ok((min, Some(max.unwrap_or(min)))),
+                }
+            }
+        rule require_command() -> ElementProcessingCommand<'input>
+            = "REQUIRE{" whitespace()? s:require_selector_list() whitespace()? c:("," whitespace()? c:count_range() { c })? "}" {
+                let (min, max) = c.unwrap_or((1, None));
+                ElementProcessingCommand::Require(s, min, max)
+            }
+        rule expect_count_command() -> ElementProcessingCommand<'input>
+            = "EXPECT-COUNT{" whitespace()? c:count_range() "}" { ElementProcessingCommand::ExpectCount(c.0, c.1) }
         pub(super) rule element_processing_command() -> ElementProcessingCommand<'input>
             = for_each_command()
+            / if_command()
+            / unless_command()
             / replace_element_command()
             / extract_element_command()
             / remove_element_command()
+            / filter_command()
+            / filter_not_command()
+            / first_command()
+            / last_command()
+            / nth_command()
+            / slice_command()
+            / parent_command()
+            / closest_command()
+            / children_command()
+            / next_sibling_command()
+            / prev_sibling_command()
+            / root_command()
             / clear_attr_command()
             / set_attr_command()
             / clear_content_command()
@@ -140,18 +417,42 @@ parser! {
             / prepend_text_content_command()
             / prepend_comment_command()
             / prepend_element_command()
+            / insert_before_command()
+            / insert_after_command()
+            / wrap_command()
+            / wrap_inner_command()
+            / unwrap_command()
+            / move_command()
+            / copy_command()
+            / require_command()
+            / expect_count_command()
+            / add_class_command()
+            / remove_class_command()
+            / toggle_class_command()
+            / replace_class_command()
+            / set_style_command()
+            / clear_all_attrs_except_command()
+            / rename_attr_command()
+            / rename_element_command()
+            / set_inner_html_command()
+            / append_html_command()
+            / prepend_html_command()
+            / remove_style_command()
 
         rule create_element_command() -> ElementCreatingCommand<'input>
             = ("CREATE-ELEMENT"/"NEW") "{" whitespace()? n:identifier() whitespace()? "}" { ElementCreatingCommand::CreateElement(n)}
         rule load_file_command() -> ElementCreatingCommand<'input>
             = ("LOAD-FILE"/"SOURCE") "{" whitespace()? f:string_value() whitespace()? "}" { ElementCreatingCommand::FromFile(f) }
+        rule parse_html_command() -> ElementCreatingCommand<'input>
+            = "PARSE-HTML{" whitespace()? v:value_source() whitespace()? "}" { ElementCreatingCommand::ParseHtml(v) }
         rule element_creating_command() -> ElementCreatingCommand<'input>
             = create_element_command()
             / load_file_command()
+            / parse_html_command()
         rule element_creating_pipeline() -> ElementCreatingPipeline<'input>
-            = s:element_creating_command() p:element_manipulating_subpipeline()? { ElementCreatingPipeline::new(s, p) }
-        rule element_manipulating_subpipeline() -> Vec<ElementProcessingCommand<'input>>
-            = pipeline_marker() p:(element_processing_command() ** pipeline_marker()) { p }
+            = s:spanned(<element_creating_command()>) p:element_manipulating_subpipeline()? { spanned_element_creating_pipeline(s, p) }
+        rule element_manipulating_subpipeline() -> Vec<(ElementProcessingCommand<'input>, Range<usize>)>
+            = pipeline_marker() p:(spanned(<element_processing_command()>) ** pipeline_marker()) { p }
 
         rule query_replaced_command() -> ElementCreatingCommand<'input>
             = ("QUERY-REPLACED"/"KEEP") "{" whitespace()? oc:css_selector_list() whitespace()? "}" { ElementCreatingCommand::FromReplaced(oc) }
@@ -159,7 +460,7 @@ parser! {
             = query_replaced_command()
             / element_creating_command()
         rule element_subselect_or_creating_pipeline() -> ElementCreatingPipeline<'input>
-            = s:element_subselect_or_creating_category() p:element_manipulating_subpipeline()? { ElementCreatingPipeline::new(s, p) }
+            = s:spanned(<element_subselect_or_creating_category()>) p:element_manipulating_subpipeline()? { spanned_element_creating_pipeline(s, p) }
 
         rule use_element_command() -> ElementSelectingCommand<'input>
             = ("USE-ELEMENT"/"THIS") { ElementSelectingCommand::UseElement }
@@ -182,12 +483,33 @@ parser! {
             = "GET-ATTR{" whitespace()? a:identifier() whitespace()? "}" { ValueExtractingCommand::GetAttribute(a) }
         rule get_text_content_command() -> ValueExtractingCommand<'input>
             = "GET-TEXT-CONTENT" { ValueExtractingCommand::GetTextContent }
+        rule get_style_command() -> ValueExtractingCommand<'input>
+            = "GET-STYLE{" whitespace()? p:identifier() whitespace()? "}" { ValueExtractingCommand::GetStyle(p) }
+        rule get_inner_html_command() -> ValueExtractingCommand<'input>
+            = "GET-INNER-HTML" { ValueExtractingCommand::GetInnerHtml }
+        rule get_outer_html_command() -> ValueExtractingCommand<'input>
+            = "GET-OUTER-HTML" { ValueExtractingCommand::GetOuterHtml }
+        rule get_tag_name_command() -> ValueExtractingCommand<'input>
+            = "GET-TAG-NAME" { ValueExtractingCommand::GetTagName }
+        rule get_comments_command() -> ValueExtractingCommand<'input>
+            = "GET-COMMENTS" { ValueExtractingCommand::GetComments }
+        rule get_attr_names_command() -> ValueExtractingCommand<'input>
+            = "GET-ATTR-NAMES" { ValueExtractingCommand::GetAttributeNames }
+        rule get_raw_text_command() -> ValueExtractingCommand<'input>
+            = "GET-RAW-TEXT" { ValueExtractingCommand::GetRawText }
         pub(super) rule value_extracting_command() -> ValueExtractingCommand<'input>
             = get_attr_command()
             / get_text_content_command()
+            / get_style_command()
+            / get_inner_html_command()
+            / get_outer_html_command()
+            / get_tag_name_command()
+            / get_comments_command()
+            / get_attr_names_command()
+            / get_raw_text_command()
 
         rule regex_replace_command() -> ValueProcessingCommand<'input>
-            = "REGEX-REPLACE{" whitespace()? m:string_value() whitespace()? assign_marker() whitespace()? r:string_value() whitespace()? "}" { ValueProcessingCommand::RegexReplace(m,r) }
+            = "REGEX-REPLACE{" whitespace()? m:regex_value() whitespace()? assign_marker() whitespace()? r:string_value() whitespace()? "}" { ValueProcessingCommand::RegexReplace(m,r) }
         rule to_lower_command() -> ValueProcessingCommand<'input>
             = "TO-LOWER" { ValueProcessingCommand::ToLower }
         rule to_upper_command() -> ValueProcessingCommand<'input>
@@ -196,18 +518,113 @@ parser! {
             = "ADD-PREFIX{" whitespace()? v:string_value() whitespace()? "}" { ValueProcessingCommand::AddPrefix(v) }
         rule add_suffix_command() -> ValueProcessingCommand<'input>
             = "ADD-SUFFIX{" whitespace()? v:string_value() whitespace()? "}" { ValueProcessingCommand::AddSuffix(v) }
+        rule trim_command() -> ValueProcessingCommand<'input>
+            = "TRIM" { ValueProcessingCommand::Trim }
+        rule collapse_whitespace_command() -> ValueProcessingCommand<'input>
+            = "COLLAPSE-WHITESPACE" { ValueProcessingCommand::CollapseWhitespace }
+        rule substring_command() -> ValueProcessingCommand<'input>
+            = "SUBSTRING{" whitespace()? s:number() whitespace()? l:("," whitespace()? l:number() whitespace()? { l })? "}" { ValueProcessingCommand::Substring(s, l) }
+        rule regex_extract_command() -> ValueProcessingCommand<'input>
+            = "REGEX-EXTRACT{" whitespace()? r:regex_value() whitespace()? g:("," whitespace()? g:number() whitespace()? { g })? "}" { ValueProcessingCommand::RegexExtract(r, g) }
+        rule split_command() -> ValueProcessingCommand<'input>
+            = "SPLIT{" whitespace()? v:string_value() whitespace()? "}" { ValueProcessingCommand::Split(v) }
+        rule join_command() -> ValueProcessingCommand<'input>
+            = "JOIN{" whitespace()? v:string_value() whitespace()? "}" { ValueProcessingCommand::Join(v) }
+        rule slugify_command() -> ValueProcessingCommand<'input>
+            = "SLUGIFY" { ValueProcessingCommand::Slugify }
+        rule truncate_command() -> ValueProcessingCommand<'input>
+            = "TRUNCATE{" whitespace()? n:number() whitespace()? e:("," whitespace()? e:string_value() whitespace()? { e })? "}" { ValueProcessingCommand::Truncate(n, e) }
+        rule url_encode_command() -> ValueProcessingCommand<'input>
+            = "URL-ENCODE" { ValueProcessingCommand::UrlEncode }
+        rule url_decode_command() -> ValueProcessingCommand<'input>
+            = "URL-DECODE" { ValueProcessingCommand::UrlDecode }
+        rule html_escape_command() -> ValueProcessingCommand<'input>
+            = "HTML-ESCAPE" { ValueProcessingCommand::HtmlEscape }
+        rule html_unescape_command() -> ValueProcessingCommand<'input>
+            = "HTML-UNESCAPE" { ValueProcessingCommand::HtmlUnescape }
+        rule default_command() -> ValueProcessingCommand<'input>
+            = "DEFAULT{" whitespace()? v:string_value() whitespace()? "}" { ValueProcessingCommand::Default(v) }
+        rule format_command() -> ValueProcessingCommand<'input>
+            = "FORMAT{" whitespace()? v:format_template() whitespace()? "}" { ValueProcessingCommand::Format(v) }
+        rule format_template() -> Cow<'input, str>
+            = v:string_value() {?
+                match format_template_parts(&v) {
+                    Some(_) => Ok(v),
+                    None => Err("valid FORMAT template"),
+                }
+            }
+        rule first_value_command() -> ValueProcessingCommand<'input>
+            = "FIRST" { ValueProcessingCommand::First }
+        rule last_value_command() -> ValueProcessingCommand<'input>
+            = "LAST" { ValueProcessingCommand::Last }
+        rule count_command() -> ValueProcessingCommand<'input>
+            = "COUNT" { ValueProcessingCommand::Count }
+        rule unique_command() -> ValueProcessingCommand<'input>
+            = "UNIQUE" { ValueProcessingCommand::Unique }
+        rule sort_command() -> ValueProcessingCommand<'input>
+            = "SORT" { ValueProcessingCommand::Sort }
         pub(super) rule value_processing_command() -> ValueProcessingCommand<'input>
             = regex_replace_command()
             / to_lower_command()
             / to_upper_command()
             / add_prefix_command()
             / add_suffix_command()
+            / trim_command()
+            / collapse_whitespace_command()
+            / substring_command()
+            / regex_extract_command()
+            / split_command()
+            / join_command()
+            / slugify_command()
+            / truncate_command()
+            / url_encode_command()
+            / url_decode_command()
+            / html_escape_command()
+            / html_unescape_command()
+            / default_command()
+            / format_command()
+            / first_value_command()
+            / last_value_command()
+            / count_command()
+            / unique_command()
+            / sort_command()
 
-        pub(super) rule string_creating_pipeline() -> StringValueCreatingPipeline<'input>
-            = s:element_selecting_command() pipeline_marker() e:value_extracting_command() pipeline_marker() p:(value_processing_command() ** pipeline_marker()) { StringValueCreatingPipeline::with_value_processing(s, e, p) }
-            / s:element_selecting_command() pipeline_marker() e:value_extracting_command() { StringValueCreatingPipeline::new(s, e) }
+        pub(crate) rule string_creating_pipeline() -> StringValueCreatingPipeline<'input>
+            = s:spanned(<element_selecting_command()>) pipeline_marker() e:spanned(<value_extracting_command()>) pipeline_marker() p:(spanned(<value_processing_command()>) ** pipeline_marker()) { spanned_string_creating_pipeline(s, e, Some(p)) }
+            / s:spanned(<element_selecting_command()>) pipeline_marker() e:spanned(<value_extracting_command()>) { spanned_string_creating_pipeline(s, e, None) }
 
         pub(crate) rule pipeline() -> ElementProcessingPipeline<'input>
-            = p:(element_processing_command() ** pipeline_marker()) { ElementProcessingPipeline::new(p) }
+            = whitespace()? p:(spanned(<element_processing_command()>) ** pipeline_marker()) whitespace()? {
+                let (commands, spans) = p.into_iter().unzip();
+                ElementProcessingPipeline::new(commands).with_spans(spans)
+            }
+
+        /// `#` only starts a comment if followed by whitespace, so CSS ids stay intact
+        rule source_comment()
+            = "#" &([' ' | '\t' | '\r' | '\n'] / ![_]) [^'\n']*
+        /// string values, except for `?…?` strings
+        rule source_string() -> &'input str
+            = $(raw_string())
+            / $("\"" (escaped_character() / [^'"' | '\\'])* "\"")
+            / $("'" (escaped_character() / [^'\'' | '\\'])* "'")
+        /// `?…?` strings don't span lines and can't be part of a word, as `?` is used in attribute globs as well
+        rule source_question_mark_string() -> &'input str
+            = $("?" (escaped_character() / [^'?' | '\\' | '\n'])* "?")
+        /// names and attribute globs as a whole, so a `?` within them doesn't start a string
+        rule source_word() -> &'input str
+            = $(['a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '*'] ['a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '*' | '?']*)
+        rule source_block()
+            = "{" (source_comment() / source_string() / source_word() / source_question_mark_string() / source_block() / [^'}'])* "}"
+        rule source_chunk() -> SourceChunk<'input>
+            = source_comment() { SourceChunk::Comment }
+            / s:source_string() { SourceChunk::Text(s) }
+            / "INCLUDE{" whitespace()? p:string_value() whitespace()? "}" { SourceChunk::Include(p) }
+            / "DEFINE" whitespace() n:identifier() whitespace()? b:$(source_block()) { SourceChunk::Define(n, &b[1..b.len() - 1]) }
+            / "CALL{" whitespace()? n:identifier() whitespace()? "}" { SourceChunk::Call(n) }
+            / w:source_word() { SourceChunk::Text(w) }
+            / s:source_question_mark_string() { SourceChunk::Text(s) }
+            / t:$([_]) { SourceChunk::Text(t) }
+        pub(super) rule source_chunks() -> Vec<SourceChunk<'input>>
+            = source_chunk()*
   }
 }
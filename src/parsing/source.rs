@@ -0,0 +1,482 @@
+use snafu::{ensure, OptionExt, ResultExt};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    line_and_column, DirectiveInSessionSnafu, IncludeCycleSnafu, ParsingSourceFailedSnafu,
+    PipelineSourceError, ReadingIncludeFailedSnafu, RecursiveDefinitionSnafu,
+    UnknownDefinitionSnafu,
+};
+
+/// Part of a pipeline definition as seen before the pipeline itself gets parsed
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SourceChunk<'a> {
+    /// anything that is passed on to the pipeline parser unchanged, including string values
+    Text(&'a str),
+    /// `# …` until the end of the line
+    Comment,
+    /// `INCLUDE{"file.hsp"}`: the content of that file, relative to the including one
+    Include(Cow<'a, str>),
+    /// `DEFINE name { … }`: a named fragment of the pipeline, not inserted where it is defined
+    Define(&'a str, &'a str),
+    /// `CALL{name}`: the previously defined fragment of that name
+    Call(&'a str),
+}
+
+/// A pipeline definition with comments removed and `INCLUDE`s and `CALL`s resolved, ready to be parsed,
+/// together with where each part of it was originally written
+#[derive(Debug)]
+pub(crate) struct ExpandedDefinition {
+    text: String,
+    sources: Vec<Source>,
+    segments: Vec<Segment>,
+}
+
+/// A pipeline definition as written: given directly or read from a file
+#[derive(Debug)]
+struct Source {
+    path: Option<PathBuf>,
+    text: Rc<str>,
+}
+
+/// A part of the expanded definition copied unchanged from a source
+#[derive(Debug)]
+struct Segment {
+    /// byte offset within the expanded definition
+    start: usize,
+    source: usize,
+    /// byte offset within the source
+    offset: usize,
+    /// the `INCLUDE`s and `CALL`s that inserted this part, outermost first, numbered in order of expansion
+    expansions: Rc<[usize]>,
+}
+
+/// Where a part of the expanded definition was written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceLocation<'a> {
+    /// `None` if the definition was given directly
+    pub(crate) path: Option<&'a Path>,
+    /// starting at 1
+    pub(crate) line: usize,
+    /// in characters, starting at 1
+    pub(crate) column: usize,
+    /// the whole line of the source the location is in
+    pub(crate) source_line: &'a str,
+}
+
+impl ExpandedDefinition {
+    /// a definition without comments, includes or definitions, e.g. of a value pipeline
+    pub(crate) fn unexpanded(definition: &str) -> Self {
+        let mut expander = Expander::new();
+        expander.push_source(None, definition);
+        expander.push_text(0, 0, definition, &Rc::from([]));
+
+        expander.finish()
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// where the byte offset within the expanded definition was written.
+    /// The end of the expanded definition is the end of the definition as written
+    pub(crate) fn locate(&self, offset: usize) -> Option<SourceLocation<'_>> {
+        let (source, offset) = match self.segment_at(offset) {
+            Some(segment) if offset < self.text.len() => (
+                &self.sources[segment.source],
+                segment.offset + offset - segment.start,
+            ),
+            _ => {
+                let source = self.sources.first()?;
+                (source, source.text.len())
+            }
+        };
+        let (line, column) = line_and_column(&source.text, offset);
+
+        Some(SourceLocation {
+            path: source.path.as_deref(),
+            line,
+            column,
+            source_line: source.text.lines().nth(line - 1).unwrap_or_default(),
+        })
+    }
+
+    /// position of a command within its (sub-)pipeline, given the spans of all its commands,
+    /// as written: counting only the commands written in the same source as this one,
+    /// with all commands inserted by the same `INCLUDE` or `CALL` counting as one
+    pub(crate) fn command_index(&self, spans: &[Range<usize>], index: usize) -> usize {
+        if index >= spans.len() {
+            return index;
+        }
+
+        let expansions = spans
+            .iter()
+            .map(|span| self.expansions_at(span.start))
+            .collect::<Vec<_>>();
+        let own = expansions[index];
+
+        let mut position = 0;
+        let mut previous_insertion = None;
+        for other in &expansions[..index] {
+            if *other == own {
+                position += 1;
+                previous_insertion = None;
+            } else if other.len() > own.len() && other.starts_with(own) {
+                let insertion = other[own.len()];
+                if previous_insertion != Some(insertion) {
+                    position += 1;
+                }
+                previous_insertion = Some(insertion);
+            }
+        }
+
+        position
+    }
+
+    fn expansions_at(&self, offset: usize) -> &[usize] {
+        self.segment_at(offset)
+            .map(|segment| segment.expansions.as_ref())
+            .unwrap_or_default()
+    }
+
+    fn segment_at(&self, offset: usize) -> Option<&Segment> {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.start <= offset)
+            .checked_sub(1)?;
+
+        self.segments.get(index)
+    }
+}
+
+/// A fragment defined by `DEFINE`, with the directory `INCLUDE`s within it are resolved against
+struct Definition {
+    source: usize,
+    body: Range<usize>,
+    base_directory: PathBuf,
+}
+
+/// Resolves comments, includes and named fragments of a pipeline definition,
+/// building the plain definition to be parsed
+struct Expander {
+    definitions: HashMap<String, Definition>,
+    /// canonical paths of all files currently being included, to detect cycles
+    include_stack: Vec<PathBuf>,
+    /// names of all fragments currently being called, to detect recursion
+    call_stack: Vec<String>,
+    sources: Vec<Source>,
+    segments: Vec<Segment>,
+    text: String,
+    expansion_count: usize,
+    /// `INCLUDE` and `DEFINE` aren't allowed in a command of an editing session,
+    /// which only keeps the commands it applied
+    in_session: bool,
+}
+
+/// expands the pipeline definition given on the command line (or as string),
+/// `INCLUDE`s are resolved against the current working directory
+pub(crate) fn expand_pipeline_definition(
+    definition: &str,
+) -> Result<ExpandedDefinition, PipelineSourceError> {
+    let mut expander = Expander::new();
+    let source = expander.push_source(None, definition);
+    expander.expand(source, 0..definition.len(), Path::new(""), Rc::from([]))?;
+
+    Ok(expander.finish())
+}
+
+/// expands a command applied in an editing session, which must not contain `INCLUDE`s or `DEFINE`s
+pub(crate) fn expand_session_command(
+    definition: &str,
+) -> Result<ExpandedDefinition, PipelineSourceError> {
+    let mut expander = Expander::new();
+    expander.in_session = true;
+    let source = expander.push_source(None, definition);
+    expander.expand(source, 0..definition.len(), Path::new(""), Rc::from([]))?;
+
+    Ok(expander.finish())
+}
+
+/// reads and expands the pipeline definition from a file,
+/// `INCLUDE`s are resolved against the directory of that file
+pub(crate) fn expand_pipeline_file(path: &Path) -> Result<ExpandedDefinition, PipelineSourceError> {
+    let mut expander = Expander::new();
+    expander.include(path, Rc::from([]))?;
+
+    Ok(expander.finish())
+}
+
+impl Expander {
+    fn new() -> Self {
+        Expander {
+            definitions: HashMap::new(),
+            include_stack: vec![],
+            call_stack: vec![],
+            sources: vec![],
+            segments: vec![],
+            text: String::new(),
+            expansion_count: 0,
+            in_session: false,
+        }
+    }
+
+    fn finish(self) -> ExpandedDefinition {
+        ExpandedDefinition {
+            text: self.text,
+            sources: self.sources,
+            segments: self.segments,
+        }
+    }
+
+    /// expands the part `range` of the source
+    fn expand(
+        &mut self,
+        source: usize,
+        range: Range<usize>,
+        base_directory: &Path,
+        expansions: Rc<[usize]>,
+    ) -> Result<(), PipelineSourceError> {
+        let text = Rc::clone(&self.sources[source].text);
+        let definition = &text[range.clone()];
+        let chunks = super::grammar::source_chunks(definition).context(ParsingSourceFailedSnafu)?;
+
+        // the chunks are slices of the definition
+        let offset_of =
+            |part: &str| range.start + (part.as_ptr() as usize - definition.as_ptr() as usize);
+        for chunk in chunks {
+            match chunk {
+                SourceChunk::Text(text) => {
+                    self.push_text(source, offset_of(text), text, &expansions)
+                }
+                SourceChunk::Comment => (),
+                SourceChunk::Include(path) => {
+                    ensure!(
+                        !self.in_session,
+                        DirectiveInSessionSnafu {
+                            directive: "INCLUDE"
+                        }
+                    );
+                    let nested = self.nested(&expansions);
+                    self.include(&base_directory.join(path.as_ref()), nested)?
+                }
+                SourceChunk::Define(name, body) => {
+                    ensure!(
+                        !self.in_session,
+                        DirectiveInSessionSnafu {
+                            directive: "DEFINE"
+                        }
+                    );
+                    self.definitions.insert(
+                        String::from(name),
+                        Definition {
+                            source,
+                            body: offset_of(body)..offset_of(body) + body.len(),
+                            base_directory: base_directory.to_path_buf(),
+                        },
+                    );
+                }
+                SourceChunk::Call(name) => {
+                    let nested = self.nested(&expansions);
+                    self.call(name, nested)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn include(&mut self, path: &Path, expansions: Rc<[usize]>) -> Result<(), PipelineSourceError> {
+        let canonical_path = fs::canonicalize(path).context(ReadingIncludeFailedSnafu { path })?;
+        ensure!(
+            !self.include_stack.contains(&canonical_path),
+            IncludeCycleSnafu { path }
+        );
+        let definition =
+            fs::read_to_string(&canonical_path).context(ReadingIncludeFailedSnafu { path })?;
+
+        let base_directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let source = self.push_source(Some(path.to_path_buf()), &definition);
+
+        self.include_stack.push(canonical_path);
+        let result = self.expand(source, 0..definition.len(), &base_directory, expansions);
+        self.include_stack.pop();
+
+        result
+    }
+
+    fn call(&mut self, name: &str, expansions: Rc<[usize]>) -> Result<(), PipelineSourceError> {
+        ensure!(
+            !self.call_stack.iter().any(|n| n == name),
+            RecursiveDefinitionSnafu { name }
+        );
+        let definition = self
+            .definitions
+            .get(name)
+            .context(UnknownDefinitionSnafu { name })?;
+        let source = definition.source;
+        let body = definition.body.clone();
+        let base_directory = definition.base_directory.clone();
+
+        self.call_stack.push(String::from(name));
+        let result = self.expand(source, body, &base_directory, expansions);
+        self.call_stack.pop();
+
+        result
+    }
+
+    fn push_source(&mut self, path: Option<PathBuf>, text: &str) -> usize {
+        self.sources.push(Source {
+            path,
+            text: Rc::from(text),
+        });
+
+        self.sources.len() - 1
+    }
+
+    /// appends the text found at the offset of the source to the expanded definition
+    fn push_text(&mut self, source: usize, offset: usize, text: &str, expansions: &Rc<[usize]>) {
+        let continues_last = self.segments.last().is_some_and(|last| {
+            last.source == source
+                && Rc::ptr_eq(&last.expansions, expansions)
+                && last.offset + (self.text.len() - last.start) == offset
+        });
+        if !continues_last {
+            self.segments.push(Segment {
+                start: self.text.len(),
+                source,
+                offset,
+                expansions: Rc::clone(expansions),
+            });
+        }
+
+        self.text.push_str(text);
+    }
+
+    /// the expansions of an `INCLUDE` or `CALL` within a part inserted by the given ones
+    fn nested(&mut self, expansions: &[usize]) -> Rc<[usize]> {
+        self.expansion_count += 1;
+
+        [expansions, &[self.expansion_count]].concat().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsing::source::{expand_pipeline_definition, SourceChunk};
+    use crate::PipelineSourceError;
+
+    #[test]
+    fn chunks_keep_strings_and_selectors_intact() {
+        let chunks = crate::parsing::grammar::source_chunks(
+            "WITH{#id ↦ SET-ATTR{x ↤ '# CALL{y}'}} # comment\n",
+        )
+        .unwrap();
+
+        assert!(chunks.contains(&SourceChunk::Text("'# CALL{y}'")));
+        assert!(chunks.contains(&SourceChunk::Comment));
+        assert!(!chunks.iter().any(|c| matches!(c, SourceChunk::Call(_))));
+    }
+
+    #[test]
+    fn comments_are_removed() {
+        assert_eq!(
+            expand_pipeline_definition("# leading\nONLY{#main} # trailing\n#\n| WITHOUT{p}")
+                .unwrap()
+                .text(),
+            "\nONLY{#main} \n\n| WITHOUT{p}"
+        );
+    }
+
+    #[test]
+    fn definitions_are_inserted_where_called() {
+        assert_eq!(
+            expand_pipeline_definition(
+                "DEFINE clean {\n  WITHOUT{script} # no JS\n}\nONLY{body} | CALL{clean}"
+            )
+            .unwrap()
+            .text(),
+            "\nONLY{body} | \n  WITHOUT{script} \n"
+        );
+    }
+
+    #[test]
+    fn definitions_may_contain_braces_and_strings() {
+        assert_eq!(
+            expand_pipeline_definition("DEFINE mark { WITH{p ↦ ADD-CLASS{'}'}} } CALL{ mark }")
+                .unwrap()
+                .text(),
+            "  WITH{p ↦ ADD-CLASS{'}'}} "
+        );
+    }
+
+    #[test]
+    fn expanded_offsets_are_located_where_written() {
+        let expanded = expand_pipeline_definition(
+            "DEFINE clean {\n  WITHOUT{script}\n}\nONLY{body}\n| CALL{clean}\n| CALL{clean}",
+        )
+        .unwrap();
+        let located = |part: &str, occurrence: usize| {
+            let offset = expanded
+                .text()
+                .match_indices(part)
+                .nth(occurrence)
+                .unwrap()
+                .0;
+            let location = expanded.locate(offset).unwrap();
+            (location.line, location.column)
+        };
+
+        assert_eq!(located("ONLY", 0), (4, 1));
+        assert_eq!(located("WITHOUT", 0), (2, 3));
+        assert_eq!(located("WITHOUT", 1), (2, 3));
+        assert_eq!(located("| ", 1), (6, 1));
+    }
+
+    #[test]
+    fn question_marks_of_attribute_globs_dont_start_strings() {
+        let definition = "WITH{div ↦ CLEAR-ATTR{data-?}} # drop data-? attrs";
+        let expanded = expand_pipeline_definition(definition).unwrap();
+
+        assert_eq!(expanded.text(), "WITH{div ↦ CLEAR-ATTR{data-?}} ");
+        assert!(crate::parsing::grammar::pipeline(expanded.text()).is_ok());
+        assert_eq!(
+            expand_pipeline_definition(&format!(
+                "DEFINE strip {{ {} \n}} CALL{{strip}}",
+                definition
+            ))
+            .unwrap()
+            .text(),
+            "  WITH{div ↦ CLEAR-ATTR{data-?}} \n"
+        );
+    }
+
+    #[test]
+    fn question_mark_strings_keep_comments_and_calls() {
+        assert_eq!(
+            expand_pipeline_definition("ONLY{p} | SET-TEXT-CONTENT{?# CALL{x}?} # comment")
+                .unwrap()
+                .text(),
+            "ONLY{p} | SET-TEXT-CONTENT{?# CALL{x}?} "
+        );
+    }
+
+    #[test]
+    fn unknown_definition_fails() {
+        assert!(matches!(
+            expand_pipeline_definition("CALL{missing}"),
+            Err(PipelineSourceError::UnknownDefinition { name, .. }) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn recursive_definition_fails() {
+        assert!(matches!(
+            expand_pipeline_definition("DEFINE a { CALL{b} } DEFINE b { CALL{a} } CALL{a}"),
+            Err(PipelineSourceError::RecursiveDefinition { name, .. }) if name == "a"
+        ));
+    }
+}
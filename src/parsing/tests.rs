@@ -1,10 +1,17 @@
+use std::borrow::Cow;
+
 use crate::element_creating::{ElementCreatingCommand, ElementCreatingPipeline};
-use crate::element_processing::{ElementProcessingCommand, ElementProcessingPipeline};
+use crate::element_processing::{
+    AttributePattern, ElementCondition, ElementProcessingCommand, ElementProcessingPipeline,
+    InsertPosition, ValueComparison,
+};
 use crate::string_creating::{
     ElementSelectingCommand, StringValueCreatingPipeline, ValueExtractingCommand,
     ValueProcessingCommand,
 };
-use crate::{CssSelector, CssSelectorList, CssSelectorPath, ValueSource};
+use crate::{
+    CompiledRegex, CssSelector, CssSelectorList, CssSelectorPath, CssSelectorStep, ValueSource,
+};
 
 const EXEMPLARY_SUB_PIPELINE_DEFINITION: &str = "USE-ELEMENT | GET-ATTR{data-test}";
 const EXEMPLARY_SUB_PIPELINE_MODEL: StringValueCreatingPipeline = StringValueCreatingPipeline::new(
@@ -15,43 +22,137 @@ const EXEMPLARY_SUB_PIPELINE_MODEL: StringValueCreatingPipeline = StringValueCre
 #[test]
 fn parse_value_simple_doublequotes() {
     let parsed = super::grammar::string_value("\"a\"");
-    assert_eq!(parsed, Ok("a"))
+    assert_eq!(parsed, Ok("a".into()))
 }
 
 #[test]
 fn parse_value_simple_singlequotes() {
     let parsed = super::grammar::string_value("'a'");
-    assert_eq!(parsed, Ok("a"))
+    assert_eq!(parsed, Ok("a".into()))
 }
 
 #[test]
 fn parse_value_simple_questionsmarks() {
     let parsed = super::grammar::string_value("?a?");
-    assert_eq!(parsed, Ok("a"))
+    assert_eq!(parsed, Ok("a".into()))
+}
+
+#[test]
+fn parse_value_empty_strings() {
+    for definition in ["\"\"", "''", "??", "r\"\"", "r#\"\"#"] {
+        assert_eq!(super::grammar::string_value(definition), Ok("".into()));
+    }
+}
+
+#[test]
+fn parse_value_resolves_escape_sequences() {
+    let parsed = super::grammar::string_value(r#"'it\'s \"quoted\"\?\n\t\\ \u{1F600}\u{e4}'"#);
+    assert_eq!(parsed, Ok("it's \"quoted\"?\n\t\\ 😀ä".into()))
+}
+
+#[test]
+fn parse_value_escaped_delimiters() {
+    assert_eq!(super::grammar::string_value(r#""a\"b""#), Ok("a\"b".into()));
+    assert_eq!(
+        super::grammar::string_value(r#"?really\??"#),
+        Ok("really?".into())
+    );
+}
+
+#[test]
+fn parse_value_borrows_without_escape_sequences() {
+    assert!(matches!(
+        super::grammar::string_value("'plain'"),
+        Ok(Cow::Borrowed("plain"))
+    ));
+    assert!(matches!(
+        super::grammar::string_value(r"'\n'"),
+        Ok(Cow::Owned(_))
+    ));
+}
+
+#[test]
+fn parse_value_invalid_escape_sequences_fail() {
+    for definition in [r"'\x'", r"'\u{110000}'", r"'\u{zz}'", r"'\u12'", r"'a\'"] {
+        assert!(
+            super::grammar::string_value(definition).is_err(),
+            "{definition} should fail"
+        );
+    }
+}
+
+#[test]
+fn parse_value_unterminated_code_point_fails() {
+    for definition in [r"'\u{41'", r"'\u{41 more'", r"'\u{'"] {
+        assert!(
+            super::grammar::string_value(definition).is_err(),
+            "{definition} should fail"
+        );
+    }
+    assert_eq!(super::grammar::string_value(r"'\u{41}'"), Ok("A".into()));
+}
+
+#[test]
+fn parse_value_raw_strings_are_taken_verbatim() {
+    assert_eq!(
+        super::grammar::string_value(r#"r"C:\new\table""#),
+        Ok(r"C:\new\table".into())
+    );
+
+    let parsed = super::grammar::string_value(
+        r##"r#"<div class="note">
+    {"json": 'yes'}
+</div>"#"##,
+    );
+    assert_eq!(
+        parsed,
+        Ok(r#"<div class="note">
+    {"json": 'yes'}
+</div>"#
+            .into())
+    );
+}
+
+#[test]
+fn parse_regex_keeps_escape_sequences() {
+    assert_eq!(
+        super::grammar::value_processing_command(r"REGEX-REPLACE{'\s+\'' ↤ '\''}"),
+        Ok(ValueProcessingCommand::RegexReplace(
+            CompiledRegex::new(r"\s+\'").unwrap(),
+            "'".into()
+        ))
+    );
+    assert_eq!(
+        super::grammar::value_processing_command(r#"REGEX-EXTRACT{r"(\d+)"}"#),
+        Ok(ValueProcessingCommand::RegexExtract(
+            CompiledRegex::new(r"(\d+)").unwrap(),
+            None
+        ))
+    );
 }
 
 #[test]
 fn parse_value_loreipsum_doublequotes() {
     let parsed = super::grammar::string_value("\"Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet.\"");
-    assert_eq!(parsed, Ok("Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet."))
+    assert_eq!(parsed, Ok("Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet.".into()))
 }
 
 #[test]
 fn parse_value_loreipsum_singlequotes() {
     let parsed = super::grammar::string_value("'Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet.'");
-    assert_eq!(parsed, Ok("Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet."))
+    assert_eq!(parsed, Ok("Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet.".into()))
 }
 
 #[test]
 fn parse_value_loreipsum_questionsmarks() {
     let parsed = super::grammar::string_value("?Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet.?");
-    assert_eq!(parsed, Ok("Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet."))
+    assert_eq!(parsed, Ok("Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet. Lorem ipsum dolor sit amet, consetetur sadipscing elitr, sed diam nonumy eirmod tempor invidunt ut labore et dolore magna aliquyam erat, sed diam voluptua. At vero eos et accusam et justo duo dolores et ea rebum. Stet clita kasd gubergren, no sea takimata sanctus est Lorem ipsum dolor sit amet.".into()))
 }
 
 #[test]
 fn parse_value_germanumlauts() {
     let parsed = super::grammar::string_value("'Hä?'");
-    assert_eq!(parsed, Ok("Hä?"))
+    assert_eq!(parsed, Ok("Hä?".into()))
 }
 
 #[test]
@@ -169,6 +270,113 @@ fn parse_remove_element_alias_without() {
     );
 }
 
+#[test]
+fn parse_filter() {
+    let parsed = super::grammar::element_processing_command("FILTER{.a, li}");
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::Filter(CssSelectorList::new(
+            vec![
+                CssSelectorPath::single(CssSelector::for_class("a")),
+                CssSelectorPath::single(CssSelector::for_element("li"))
+            ]
+        )))
+    );
+}
+
+#[test]
+fn parse_not() {
+    let parsed = super::grammar::element_processing_command("NOT{ .a }");
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::FilterNot(CssSelectorList::new(
+            vec![CssSelectorPath::single(CssSelector::for_class("a"))]
+        )))
+    );
+}
+
+#[test]
+fn parse_first_and_last() {
+    assert_eq!(
+        super::grammar::element_processing_command("FIRST"),
+        Ok(ElementProcessingCommand::First)
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("LAST"),
+        Ok(ElementProcessingCommand::Last)
+    );
+}
+
+#[test]
+fn parse_nth() {
+    let parsed = super::grammar::element_processing_command("NTH{3}");
+    assert_eq!(parsed, Ok(ElementProcessingCommand::Nth(3)));
+}
+
+#[test]
+fn parse_slice() {
+    assert_eq!(
+        super::grammar::element_processing_command("SLICE{2..4}"),
+        Ok(ElementProcessingCommand::Slice(Some(2), Some(4)))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("SLICE{ 2.. }"),
+        Ok(ElementProcessingCommand::Slice(Some(2), None))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("SLICE{..4}"),
+        Ok(ElementProcessingCommand::Slice(None, Some(4)))
+    );
+}
+
+#[test]
+fn parse_navigation_commands() {
+    assert_eq!(
+        super::grammar::element_processing_command("PARENT"),
+        Ok(ElementProcessingCommand::Parent)
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("CHILDREN"),
+        Ok(ElementProcessingCommand::Children(None))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("NEXT-SIBLING"),
+        Ok(ElementProcessingCommand::NextSibling)
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("PREV-SIBLING"),
+        Ok(ElementProcessingCommand::PreviousSibling)
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("ROOT"),
+        Ok(ElementProcessingCommand::Root)
+    );
+}
+
+#[test]
+fn parse_closest() {
+    let parsed = super::grammar::element_processing_command("CLOSEST{figure}");
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::Closest(CssSelectorList::new(
+            vec![CssSelectorPath::single(CssSelector::for_element("figure"))]
+        )))
+    );
+}
+
+#[test]
+fn parse_children_with_selector() {
+    let parsed = super::grammar::element_processing_command("CHILDREN{ li }");
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::Children(Some(
+            CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+                "li"
+            ))])
+        )))
+    );
+}
+
 #[test]
 fn parse_clear_attr() {
     let parsed = super::grammar::element_processing_command("CLEAR-ATTR{a}");
@@ -188,7 +396,7 @@ fn parse_set_attr_by_string() {
         parsed,
         Ok(ElementProcessingCommand::SetAttribute(
             "data-test",
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -200,7 +408,7 @@ fn parse_set_attr_by_string_with_ascii_arrow() {
         parsed,
         Ok(ElementProcessingCommand::SetAttribute(
             "data-test",
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -228,7 +436,7 @@ fn parse_set_text_content_by_string() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::SetTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -239,7 +447,7 @@ fn parse_set_text_content_by_string_with_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::SetTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -266,7 +474,7 @@ fn parse_set_text_content_by_string_with_ascii_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::SetTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -277,7 +485,7 @@ fn parse_append_text_content_by_string() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::AppendTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -288,7 +496,7 @@ fn parse_append_text_content_by_string_with_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::AppendTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -315,7 +523,7 @@ fn parse_append_text_content_by_string_with_ascii_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::AppendTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -326,7 +534,7 @@ fn parse_append_comment_by_string() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::AppendComment(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -337,7 +545,7 @@ fn parse_append_comment_by_string_with_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::AppendComment(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -348,7 +556,7 @@ fn parse_append_comment_by_string_with_ascii_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::AppendComment(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -366,7 +574,7 @@ fn parse_for_each_alias_with_using_set_attr() {
             ))]),
             ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
                 "data-test",
-                ValueSource::StringValue("some text")
+                ValueSource::StringValue("some text".into())
             )]),
         ))
     );
@@ -386,7 +594,7 @@ fn parse_for_each_using_set_attr() {
             ))]),
             ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
                 "data-test",
-                ValueSource::StringValue("some text")
+                ValueSource::StringValue("some text".into())
             )]),
         ))
     );
@@ -406,8 +614,136 @@ fn parse_for_each_with_ascii_arrow_using_set_attr() {
             ))]),
             ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
                 "data-test",
-                ValueSource::StringValue("some text")
+                ValueSource::StringValue("some text".into())
+            )]),
+        ))
+    );
+}
+
+#[test]
+fn parse_if_query_element_without_else() {
+    let parsed = super::grammar::element_processing_command(
+        "IF{QUERY-ELEMENT{meta} ↦ SET-ATTR{data-test ↤ 'some text'}}",
+    );
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::If(
+            ElementCondition::Selects(ElementSelectingCommand::QueryElement(CssSelectorList::new(
+                vec![CssSelectorPath::single(CssSelector::for_element("meta"))]
+            ))),
+            ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
+                "data-test",
+                ValueSource::StringValue("some text".into())
+            )]),
+            None,
+        ))
+    );
+}
+
+#[test]
+fn parse_if_has_attr_with_else() {
+    let parsed = super::grammar::element_processing_command(
+        "IF{HAS-ATTR{alt} => CLEAR-ATTR{title}} ELSE{SET-ATTR{alt ↤ 'x'}}",
+    );
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::If(
+            ElementCondition::HasAttribute("alt"),
+            ElementProcessingPipeline::new(vec![ElementProcessingCommand::ClearAttribute("title")]),
+            Some(ElementProcessingPipeline::new(vec![
+                ElementProcessingCommand::SetAttribute("alt", ValueSource::StringValue("x".into()))
+            ])),
+        ))
+    );
+}
+
+#[test]
+fn parse_if_value_comparison() {
+    let parsed = super::grammar::element_processing_command(
+        "IF{THIS | GET-ATTR{width} > '400' ↦ SET-ATTR{loading ↤ 'lazy'}}",
+    );
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::If(
+            ElementCondition::Compares(
+                StringValueCreatingPipeline::new(
+                    ElementSelectingCommand::UseElement,
+                    ValueExtractingCommand::GetAttribute("width"),
+                ),
+                ValueComparison::GreaterThan,
+                "400".into()
+            ),
+            ElementProcessingPipeline::new(vec![ElementProcessingCommand::SetAttribute(
+                "loading",
+                ValueSource::StringValue("lazy".into())
             )]),
+            None,
+        ))
+    );
+}
+
+#[test]
+fn parse_element_condition_comparisons() {
+    let pipeline = StringValueCreatingPipeline::new(
+        ElementSelectingCommand::UseElement,
+        ValueExtractingCommand::GetAttribute("width"),
+    );
+
+    for (definition, comparison) in [
+        ("=", ValueComparison::Equals),
+        ("!=", ValueComparison::NotEquals),
+        ("^=", ValueComparison::StartsWith),
+        ("$=", ValueComparison::EndsWith),
+        ("*=", ValueComparison::Contains),
+        ("<", ValueComparison::LessThan),
+        ("<=", ValueComparison::LessOrEqual),
+        (">", ValueComparison::GreaterThan),
+        (">=", ValueComparison::GreaterOrEqual),
+    ] {
+        let definition = format!("USE-ELEMENT | GET-ATTR{{width}} {} '400'", definition);
+        let parsed = super::grammar::element_condition(&definition);
+        assert_eq!(
+            parsed,
+            Ok(ElementCondition::Compares(
+                pipeline.clone(),
+                comparison,
+                "400".into()
+            ))
+        );
+    }
+}
+
+#[test]
+fn parse_unless_query_element_with_else() {
+    let parsed = super::grammar::element_processing_command(
+        "UNLESS{QUERY-ELEMENT{meta} ↦ CLEAR-CONTENT}\nELSE{CLEAR-ATTR{lang}}",
+    );
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::Unless(
+            ElementCondition::Selects(ElementSelectingCommand::QueryElement(CssSelectorList::new(
+                vec![CssSelectorPath::single(CssSelector::for_element("meta"))]
+            ))),
+            ElementProcessingPipeline::new(vec![ElementProcessingCommand::ClearContent]),
+            Some(ElementProcessingPipeline::new(vec![
+                ElementProcessingCommand::ClearAttribute("lang")
+            ])),
+        ))
+    );
+}
+
+#[test]
+fn parse_append_element_using_keep() {
+    let parsed = super::grammar::element_processing_command("APPEND-ELEMENT{KEEP{li}}");
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::AppendElement(
+            ElementCreatingPipeline::new(
+                ElementCreatingCommand::FromReplaced(CssSelectorList::new(vec![
+                    CssSelectorPath::single(CssSelector::for_element("li"))
+                ])),
+                None
+            )
         ))
     );
 }
@@ -443,7 +779,7 @@ fn parse_append_element_using_load_file() {
         parsed,
         Ok(ElementProcessingCommand::AppendElement(
             ElementCreatingPipeline::new(
-                ElementCreatingCommand::FromFile("tests/source.html"),
+                ElementCreatingCommand::FromFile("tests/source.html".into()),
                 None
             )
         ))
@@ -458,7 +794,7 @@ fn parse_append_element_using_source() {
         parsed,
         Ok(ElementProcessingCommand::AppendElement(
             ElementCreatingPipeline::new(
-                ElementCreatingCommand::FromFile("tests/source.html"),
+                ElementCreatingCommand::FromFile("tests/source.html".into()),
                 None
             )
         ))
@@ -572,7 +908,7 @@ fn parse_prepend_text_content_by_string() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::PrependTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -583,7 +919,7 @@ fn parse_prepend_text_content_by_string_with_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::PrependTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -611,7 +947,7 @@ fn parse_prepend_text_content_by_string_with_ascii_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::PrependTextContent(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -622,7 +958,7 @@ fn parse_prepend_comment_by_string() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::PrependComment(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -633,7 +969,7 @@ fn parse_prepend_comment_by_string_with_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::PrependComment(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -644,7 +980,7 @@ fn parse_prepend_comment_by_string_with_ascii_arrow() {
     assert_eq!(
         parsed,
         Ok(ElementProcessingCommand::PrependComment(
-            ValueSource::StringValue("some text")
+            ValueSource::StringValue("some text".into())
         ))
     );
 }
@@ -680,7 +1016,7 @@ fn parse_prepend_element_using_load_file() {
         parsed,
         Ok(ElementProcessingCommand::PrependElement(
             ElementCreatingPipeline::new(
-                ElementCreatingCommand::FromFile("tests/source.html"),
+                ElementCreatingCommand::FromFile("tests/source.html".into()),
                 None
             )
         ))
@@ -695,7 +1031,7 @@ fn parse_prepend_element_using_source() {
         parsed,
         Ok(ElementProcessingCommand::PrependElement(
             ElementCreatingPipeline::new(
-                ElementCreatingCommand::FromFile("tests/source.html"),
+                ElementCreatingCommand::FromFile("tests/source.html".into()),
                 None
             )
         ))
@@ -726,6 +1062,393 @@ fn parse_prepend_element_with_ascii_arrow_using_create() {
     );
 }
 
+#[test]
+fn parse_insert_before_and_after() {
+    assert_eq!(
+        super::grammar::element_processing_command("INSERT-BEFORE{NEW{hr}}"),
+        Ok(ElementProcessingCommand::InsertBefore(
+            ElementCreatingPipeline::new(ElementCreatingCommand::CreateElement("hr"), None)
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("INSERT-AFTER{ ↤ NEW{hr}}"),
+        Ok(ElementProcessingCommand::InsertAfter(
+            ElementCreatingPipeline::new(ElementCreatingCommand::CreateElement("hr"), None)
+        ))
+    );
+}
+
+#[test]
+fn parse_wrap_with_sub_pipeline() {
+    let parsed = super::grammar::element_processing_command(
+        "WRAP{NEW{div} | SET-ATTR{class ↤ 'table-scroll'}}",
+    );
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::Wrap(
+            ElementCreatingPipeline::new(
+                ElementCreatingCommand::CreateElement("div"),
+                Some(vec![ElementProcessingCommand::SetAttribute(
+                    "class",
+                    ValueSource::StringValue("table-scroll".into())
+                )])
+            )
+        ))
+    );
+}
+
+#[test]
+fn parse_wrap_inner_and_unwrap() {
+    assert_eq!(
+        super::grammar::element_processing_command("WRAP-INNER{NEW{span}}"),
+        Ok(ElementProcessingCommand::WrapInner(
+            ElementCreatingPipeline::new(ElementCreatingCommand::CreateElement("span"), None)
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("UNWRAP"),
+        Ok(ElementProcessingCommand::Unwrap)
+    );
+}
+
+#[test]
+fn parse_move_defaults_to_append() {
+    assert_eq!(
+        super::grammar::element_processing_command("MOVE{script ↦ body}"),
+        Ok(ElementProcessingCommand::Move(
+            CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+                "script"
+            ))]),
+            CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+                "body"
+            ))]),
+            InsertPosition::Append
+        ))
+    );
+}
+
+#[test]
+fn parse_move_with_position() {
+    assert_eq!(
+        super::grammar::element_processing_command("MOVE{body style => head title, AFTER}"),
+        Ok(ElementProcessingCommand::Move(
+            CssSelectorList::new(vec![CssSelectorPath::new(
+                CssSelector::for_element("body"),
+                vec![CssSelectorStep::descendent(CssSelector::for_element(
+                    "style"
+                ))]
+            )]),
+            CssSelectorList::new(vec![CssSelectorPath::new(
+                CssSelector::for_element("head"),
+                vec![CssSelectorStep::descendent(CssSelector::for_element(
+                    "title"
+                ))]
+            )]),
+            InsertPosition::After
+        ))
+    );
+}
+
+#[test]
+fn parse_copy_with_target_list_and_position() {
+    assert_eq!(
+        super::grammar::element_processing_command("COPY{.logo ↦ header, footer, PREPEND}"),
+        Ok(ElementProcessingCommand::Copy(
+            CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_class(
+                "logo"
+            ))]),
+            CssSelectorList::new(vec![
+                CssSelectorPath::single(CssSelector::for_element("header")),
+                CssSelectorPath::single(CssSelector::for_element("footer"))
+            ]),
+            InsertPosition::Prepend
+        ))
+    );
+}
+
+#[test]
+fn parse_class_commands_with_bare_identifier() {
+    assert_eq!(
+        super::grammar::element_processing_command("ADD-CLASS{active}"),
+        Ok(ElementProcessingCommand::AddClass(
+            ValueSource::StringValue("active".into())
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("REMOVE-CLASS{ 'old-style' }"),
+        Ok(ElementProcessingCommand::RemoveClass(
+            ValueSource::StringValue("old-style".into())
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("TOGGLE-CLASS{open}"),
+        Ok(ElementProcessingCommand::ToggleClass(
+            ValueSource::StringValue("open".into())
+        ))
+    );
+}
+
+#[test]
+fn parse_replace_class() {
+    assert_eq!(
+        super::grammar::element_processing_command("REPLACE-CLASS{btn-default ↤ 'btn-primary'}"),
+        Ok(ElementProcessingCommand::ReplaceClass(
+            ValueSource::StringValue("btn-default".into()),
+            ValueSource::StringValue("btn-primary".into())
+        ))
+    );
+}
+
+#[test]
+fn parse_add_class_with_sub_pipeline() {
+    let definition = format!("ADD-CLASS{{{}}}", EXEMPLARY_SUB_PIPELINE_DEFINITION);
+    let parsed = super::grammar::element_processing_command(&definition);
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::AddClass(
+            ValueSource::SubPipeline(EXEMPLARY_SUB_PIPELINE_MODEL)
+        ))
+    );
+}
+
+#[test]
+fn parse_set_and_remove_style() {
+    assert_eq!(
+        super::grammar::element_processing_command("SET-STYLE{max-width ↤ '100%'}"),
+        Ok(ElementProcessingCommand::SetStyle(
+            "max-width",
+            ValueSource::StringValue("100%".into())
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("REMOVE-STYLE{ display }"),
+        Ok(ElementProcessingCommand::RemoveStyle("display"))
+    );
+}
+
+#[test]
+fn parse_string_creating_pipeline_use_element_get_style() {
+    let parsed = super::grammar::string_creating_pipeline("THIS | GET-STYLE{--main-color}");
+    assert_eq!(
+        parsed,
+        Ok(StringValueCreatingPipeline::new(
+            ElementSelectingCommand::UseElement,
+            ValueExtractingCommand::GetStyle("--main-color"),
+        )),
+    );
+}
+
+#[test]
+fn parse_clear_attr_with_patterns() {
+    assert_eq!(
+        super::grammar::element_processing_command("CLEAR-ATTR{on*, data-v-*,aria-?? , ~'^x-'}"),
+        Ok(ElementProcessingCommand::ClearAttributes(vec![
+            AttributePattern::Glob("on*"),
+            AttributePattern::Glob("data-v-*"),
+            AttributePattern::Glob("aria-??"),
+            AttributePattern::Regex(CompiledRegex::new("^x-").unwrap()),
+        ]))
+    );
+}
+
+#[test]
+fn parse_clear_attr_with_name_list() {
+    assert_eq!(
+        super::grammar::element_processing_command("CLEAR-ATTR{width, height}"),
+        Ok(ElementProcessingCommand::ClearAttributes(vec![
+            AttributePattern::Name("width"),
+            AttributePattern::Name("height"),
+        ]))
+    );
+}
+
+#[test]
+fn parse_clear_all_attrs_except() {
+    assert_eq!(
+        super::grammar::element_processing_command("CLEAR-ALL-ATTRS-EXCEPT{href, data-*}"),
+        Ok(ElementProcessingCommand::ClearAllAttributesExcept(vec![
+            AttributePattern::Name("href"),
+            AttributePattern::Glob("data-*"),
+        ]))
+    );
+}
+
+#[test]
+fn parse_rename_attr() {
+    assert_eq!(
+        super::grammar::element_processing_command("RENAME-ATTR{data-src ↦ src}"),
+        Ok(ElementProcessingCommand::RenameAttribute("data-src", "src"))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("RENAME-ATTR{ data-src => src }"),
+        Ok(ElementProcessingCommand::RenameAttribute("data-src", "src"))
+    );
+}
+
+#[test]
+fn parse_rename_element() {
+    assert_eq!(
+        super::grammar::element_processing_command("RENAME-ELEMENT{strong}"),
+        Ok(ElementProcessingCommand::RenameElement(
+            ValueSource::StringValue("strong".into())
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("SET-TAG-NAME{ ↤ 'em' }"),
+        Ok(ElementProcessingCommand::RenameElement(
+            ValueSource::StringValue("em".into())
+        ))
+    );
+}
+
+#[test]
+fn parse_rename_element_with_sub_pipeline() {
+    let definition = format!("RENAME-ELEMENT{{{}}}", EXEMPLARY_SUB_PIPELINE_DEFINITION);
+    let parsed = super::grammar::element_processing_command(&definition);
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::RenameElement(
+            ValueSource::SubPipeline(EXEMPLARY_SUB_PIPELINE_MODEL)
+        ))
+    );
+}
+
+#[test]
+fn parse_html_insertion_commands() {
+    assert_eq!(
+        super::grammar::element_processing_command("SET-INNER-HTML{'<b>bold</b>'}"),
+        Ok(ElementProcessingCommand::SetInnerHtml(
+            ValueSource::StringValue("<b>bold</b>".into())
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("APPEND-HTML{ ↤ '<hr>'}"),
+        Ok(ElementProcessingCommand::AppendHtml(
+            ValueSource::StringValue("<hr>".into())
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("PREPEND-HTML{'<!-- start -->'}"),
+        Ok(ElementProcessingCommand::PrependHtml(
+            ValueSource::StringValue("<!-- start -->".into())
+        ))
+    );
+}
+
+#[test]
+fn parse_insert_before_with_parse_html() {
+    let definition = format!(
+        "INSERT-BEFORE{{PARSE-HTML{{{}}}}}",
+        EXEMPLARY_SUB_PIPELINE_DEFINITION
+    );
+    let parsed = super::grammar::element_processing_command(&definition);
+    assert_eq!(
+        parsed,
+        Ok(ElementProcessingCommand::InsertBefore(
+            ElementCreatingPipeline::new(
+                ElementCreatingCommand::ParseHtml(ValueSource::SubPipeline(
+                    EXEMPLARY_SUB_PIPELINE_MODEL
+                )),
+                None
+            )
+        ))
+    );
+}
+
+#[test]
+fn parse_markup_and_node_extractors() {
+    for (definition, extractor) in [
+        ("GET-INNER-HTML", ValueExtractingCommand::GetInnerHtml),
+        ("GET-OUTER-HTML", ValueExtractingCommand::GetOuterHtml),
+        ("GET-TAG-NAME", ValueExtractingCommand::GetTagName),
+        ("GET-COMMENTS", ValueExtractingCommand::GetComments),
+        ("GET-ATTR-NAMES", ValueExtractingCommand::GetAttributeNames),
+        ("GET-RAW-TEXT", ValueExtractingCommand::GetRawText),
+    ] {
+        assert_eq!(
+            super::grammar::value_extracting_command(definition),
+            Ok(extractor)
+        );
+    }
+}
+
+#[test]
+fn parse_string_processing_commands() {
+    for (definition, command) in [
+        ("TRIM", ValueProcessingCommand::Trim),
+        (
+            "COLLAPSE-WHITESPACE",
+            ValueProcessingCommand::CollapseWhitespace,
+        ),
+        ("SUBSTRING{3}", ValueProcessingCommand::Substring(3, None)),
+        (
+            "SUBSTRING{ 3, 5 }",
+            ValueProcessingCommand::Substring(3, Some(5)),
+        ),
+        (
+            "REGEX-EXTRACT{'v(\\d+)'}",
+            ValueProcessingCommand::RegexExtract(CompiledRegex::new("v(\\d+)").unwrap(), None),
+        ),
+        (
+            "REGEX-EXTRACT{'(\\w+)@(\\w+)', 2}",
+            ValueProcessingCommand::RegexExtract(
+                CompiledRegex::new("(\\w+)@(\\w+)").unwrap(),
+                Some(2),
+            ),
+        ),
+        ("SPLIT{','}", ValueProcessingCommand::Split(",".into())),
+        ("JOIN{', '}", ValueProcessingCommand::Join(", ".into())),
+        ("SLUGIFY", ValueProcessingCommand::Slugify),
+        ("TRUNCATE{20}", ValueProcessingCommand::Truncate(20, None)),
+        (
+            "TRUNCATE{20, \"…\"}",
+            ValueProcessingCommand::Truncate(20, Some("…".into())),
+        ),
+        ("URL-ENCODE", ValueProcessingCommand::UrlEncode),
+        ("URL-DECODE", ValueProcessingCommand::UrlDecode),
+        ("HTML-ESCAPE", ValueProcessingCommand::HtmlEscape),
+        ("HTML-UNESCAPE", ValueProcessingCommand::HtmlUnescape),
+        (
+            "DEFAULT{'n/a'}",
+            ValueProcessingCommand::Default("n/a".into()),
+        ),
+        (
+            "FORMAT{\"{0}-{1}\"}",
+            ValueProcessingCommand::Format("{0}-{1}".into()),
+        ),
+        ("FIRST", ValueProcessingCommand::First),
+        ("LAST", ValueProcessingCommand::Last),
+        ("COUNT", ValueProcessingCommand::Count),
+        ("UNIQUE", ValueProcessingCommand::Unique),
+        ("SORT", ValueProcessingCommand::Sort),
+    ] {
+        assert_eq!(
+            super::grammar::value_processing_command(definition),
+            Ok(command)
+        );
+    }
+}
+
+#[test]
+fn parse_string_creating_pipeline_with_aggregation() {
+    assert_eq!(
+        super::grammar::string_creating_pipeline(
+            "QUERY-ELEMENT{li} | GET-TEXT-CONTENT | SORT | UNIQUE | JOIN{', '}"
+        ),
+        Ok(StringValueCreatingPipeline::with_value_processing(
+            ElementSelectingCommand::QueryElement(CssSelectorList::new(vec![
+                CssSelectorPath::single(CssSelector::for_element("li"))
+            ])),
+            ValueExtractingCommand::GetTextContent,
+            vec![
+                ValueProcessingCommand::Sort,
+                ValueProcessingCommand::Unique,
+                ValueProcessingCommand::Join(", ".into()),
+            ]
+        ))
+    );
+}
+
 #[test]
 fn parse_string_creating_pipeline_use_element_get_attr() {
     let parsed = super::grammar::string_creating_pipeline("USE-ELEMENT | GET-ATTR{data-test}");
@@ -748,7 +1471,10 @@ fn parse_string_creating_pipeline_use_element_get_attr_regex_replace() {
         Ok(StringValueCreatingPipeline::with_value_processing(
             ElementSelectingCommand::UseElement,
             ValueExtractingCommand::GetAttribute("data-test"),
-            vec![ValueProcessingCommand::RegexReplace("a", "b")]
+            vec![ValueProcessingCommand::RegexReplace(
+                CompiledRegex::new("a").unwrap(),
+                "b".into()
+            )]
         )),
     );
 }
@@ -764,8 +1490,8 @@ fn parse_string_creating_pipeline_use_element_get_attr_2_regex_replaces() {
             ElementSelectingCommand::UseElement,
             ValueExtractingCommand::GetAttribute("data-test"),
             vec![
-                ValueProcessingCommand::RegexReplace("a", "b"),
-                ValueProcessingCommand::RegexReplace("a", "b")
+                ValueProcessingCommand::RegexReplace(CompiledRegex::new("a").unwrap(), "b".into()),
+                ValueProcessingCommand::RegexReplace(CompiledRegex::new("a").unwrap(), "b".into())
             ]
         )),
     );
@@ -846,13 +1572,25 @@ fn parse_get_text_content() {
 #[test]
 fn parse_regex_replace_simple() {
     let parsed = super::grammar::value_processing_command("REGEX-REPLACE{'a' ↤ 'b'}");
-    assert_eq!(parsed, Ok(ValueProcessingCommand::RegexReplace("a", "b")));
+    assert_eq!(
+        parsed,
+        Ok(ValueProcessingCommand::RegexReplace(
+            CompiledRegex::new("a").unwrap(),
+            "b".into()
+        ))
+    );
 }
 
 #[test]
 fn parse_regex_replace_with_ascii_arrow() {
     let parsed = super::grammar::value_processing_command("REGEX-REPLACE{'a' <= 'b'}");
-    assert_eq!(parsed, Ok(ValueProcessingCommand::RegexReplace("a", "b")));
+    assert_eq!(
+        parsed,
+        Ok(ValueProcessingCommand::RegexReplace(
+            CompiledRegex::new("a").unwrap(),
+            "b".into()
+        ))
+    );
 }
 
 #[test]
@@ -863,8 +1601,8 @@ fn parse_regex_replace_complex() {
     assert_eq!(
         parsed,
         Ok(ValueProcessingCommand::RegexReplace(
-            "^(\\w+)[a-zA-Z]{0,3}\\s$",
-            "Some $1 stuff"
+            CompiledRegex::new("^(\\w+)[a-zA-Z]{0,3}\\s$").unwrap(),
+            "Some $1 stuff".into()
         ))
     );
 }
@@ -884,11 +1622,86 @@ fn parse_to_upper() {
 #[test]
 fn parse_add_prefix() {
     let parsed = super::grammar::value_processing_command("ADD-PREFIX{'a'}");
-    assert_eq!(parsed, Ok(ValueProcessingCommand::AddPrefix("a")));
+    assert_eq!(parsed, Ok(ValueProcessingCommand::AddPrefix("a".into())));
 }
 
 #[test]
 fn parse_add_suffix() {
     let parsed = super::grammar::value_processing_command("ADD-SUFFIX{'a'}");
-    assert_eq!(parsed, Ok(ValueProcessingCommand::AddSuffix("a")));
+    assert_eq!(parsed, Ok(ValueProcessingCommand::AddSuffix("a".into())));
+}
+
+#[test]
+fn parse_invalid_regex_fails_with_position() {
+    let error = super::grammar::pipeline(
+        "WITH{a => SET-ATTR{title <= THIS | GET-ATTR{href} | REGEX-REPLACE{'(' <= 'x'}}}",
+    )
+    .unwrap_err();
+
+    assert_eq!(error.location.line, 1);
+    assert_eq!(error.location.column, 70);
+    assert_eq!(error.expected.to_string(), "valid regular expression");
+}
+
+#[test]
+fn parse_invalid_regex_fails_in_all_commands() {
+    assert!(super::grammar::value_processing_command("REGEX-EXTRACT{'[a-'}").is_err());
+    assert!(super::grammar::element_processing_command("CLEAR-ATTR{href, ~'*x'}").is_err());
+}
+
+#[test]
+fn parse_require_with_default_count() {
+    assert_eq!(
+        super::grammar::element_processing_command("REQUIRE{#cookie-banner}"),
+        Ok(ElementProcessingCommand::Require(
+            CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_id(
+                "cookie-banner"
+            ))]),
+            1,
+            None
+        ))
+    );
+}
+
+#[test]
+fn parse_require_with_selector_list_and_counts() {
+    assert_eq!(
+        super::grammar::element_processing_command("REQUIRE{h1,h2, 2, 5}"),
+        Ok(ElementProcessingCommand::Require(
+            CssSelectorList::new(vec![
+                CssSelectorPath::single(CssSelector::for_element("h1")),
+                CssSelectorPath::single(CssSelector::for_element("h2"))
+            ]),
+            2,
+            Some(5)
+        ))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("REQUIRE{li,3}"),
+        Ok(ElementProcessingCommand::Require(
+            CssSelectorList::new(vec![CssSelectorPath::single(CssSelector::for_element(
+                "li"
+            ))]),
+            3,
+            Some(3)
+        ))
+    );
+}
+
+#[test]
+fn parse_count_range_with_maximum_below_minimum_fails() {
+    assert!(super::grammar::element_processing_command("REQUIRE{li, 5, 2}").is_err());
+    assert!(super::grammar::element_processing_command("EXPECT-COUNT{5, 2}").is_err());
+}
+
+#[test]
+fn parse_expect_count() {
+    assert_eq!(
+        super::grammar::element_processing_command("EXPECT-COUNT{1}"),
+        Ok(ElementProcessingCommand::ExpectCount(1, Some(1)))
+    );
+    assert_eq!(
+        super::grammar::element_processing_command("EXPECT-COUNT{ 2, 4 }"),
+        Ok(ElementProcessingCommand::ExpectCount(2, Some(4)))
+    );
 }
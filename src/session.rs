@@ -0,0 +1,155 @@
+use snafu::ResultExt;
+use std::io::BufRead;
+
+use crate::explain::short_css_path;
+use crate::html::{HtmlContent, HtmlRenderable};
+use crate::parsing::ExpandedDefinition;
+use crate::{
+    boxed, parse_input, parse_pipeline, parsing, running_pipeline_failed,
+    LoadingPipelineFailedSnafu, ParsingPipelineFailedSnafu, PipelineDiagnostic,
+    ReadingInputFailedSnafu, StreamingEditorError,
+};
+
+/// A document loaded once, to which pipeline commands are applied one after another,
+/// each on the result of the previous ones, as `hse repl` does.
+/// The applied commands can be undone and together form a pipeline definition.
+/// As only the commands are kept, they can't contain `INCLUDE` or `DEFINE`
+pub struct EditingSession {
+    input: String,
+    commands: Vec<String>,
+    current: Vec<rctree::Node<HtmlContent>>,
+}
+
+/// A node found by `EditingSession::query`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryMatch {
+    path: String,
+    html: String,
+}
+
+impl QueryMatch {
+    /// short CSS-like path of the node, starting at the closest ancestor with an `id`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+}
+
+impl EditingSession {
+    /// reads and parses the whole input, which then is the current (single) node
+    pub fn load(input: &mut dyn BufRead) -> Result<Self, StreamingEditorError> {
+        let mut content = String::new();
+        input
+            .read_to_string(&mut content)
+            .context(ReadingInputFailedSnafu)?;
+
+        let root_element = parse_input(&content)?;
+        Ok(EditingSession {
+            input: content,
+            commands: vec![],
+            current: vec![root_element],
+        })
+    }
+
+    /// finds all nodes matching the CSS selector, beginning at the current nodes, without changing anything
+    pub fn query(&self, selector: &str) -> Result<Vec<QueryMatch>, StreamingEditorError> {
+        let selector = parsing::grammar::css_selector_list(selector).map_err(|error| {
+            ParsingPipelineFailedSnafu {
+                diagnostic: Box::new(PipelineDiagnostic::new(
+                    &ExpandedDefinition::unexpanded(selector),
+                    &error,
+                )),
+            }
+            .build()
+        })?;
+
+        Ok(selector
+            .query(&self.current)
+            .iter()
+            .map(|node| QueryMatch {
+                path: short_css_path(node),
+                html: node.outer_html(),
+            })
+            .collect())
+    }
+
+    /// runs the pipeline on the current nodes, its result becomes the new current nodes.
+    /// If it fails, the current nodes stay as they were.
+    /// Definitions without commands, e.g. only a comment, change nothing and aren't recorded
+    pub fn apply(&mut self, pipeline_definition: &str) -> Result<usize, StreamingEditorError> {
+        let expanded_definition = parsing::expand_session_command(pipeline_definition)
+            .context(LoadingPipelineFailedSnafu)?;
+        let pipeline = parse_pipeline(&expanded_definition)?;
+        // without comments, so joining the commands with `|` can't end up in one
+        let commands = expanded_definition.text().trim();
+        if commands.is_empty() {
+            return Ok(self.current.len());
+        }
+
+        match pipeline.run_on(self.current.clone()) {
+            Ok(result) => {
+                self.commands.push(commands.to_string());
+                self.current = result;
+                Ok(self.current.len())
+            }
+            Err(error) => {
+                // the failing command may have changed the nodes already
+                self.replay()?;
+                Err(running_pipeline_failed(&expanded_definition, error))
+            }
+        }
+    }
+
+    /// reverts the last applied pipeline, returning its definition.
+    /// Returns `None` if nothing was applied yet
+    pub fn undo(&mut self) -> Result<Option<String>, StreamingEditorError> {
+        let command = self.commands.pop();
+        if command.is_some() {
+            self.replay()?;
+        }
+
+        Ok(command)
+    }
+
+    /// the definitions of all applied pipelines, in order, without comments
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    /// the applied pipelines combined into one pipeline definition, e.g. to save as `.hsp` file
+    pub fn pipeline_definition(&self) -> String {
+        self.commands.join("\n| ")
+    }
+
+    /// the current nodes, as they would be written by `hse`
+    pub fn current(&self) -> Vec<Box<dyn HtmlRenderable>> {
+        boxed(self.current.clone())
+    }
+
+    fn run(
+        pipeline_definition: &str,
+        nodes: Vec<rctree::Node<HtmlContent>>,
+    ) -> Result<Vec<rctree::Node<HtmlContent>>, StreamingEditorError> {
+        let pipeline_definition = parsing::expand_session_command(pipeline_definition)
+            .context(LoadingPipelineFailedSnafu)?;
+        let pipeline = parse_pipeline(&pipeline_definition)?;
+
+        pipeline
+            .run_on(nodes)
+            .map_err(|error| running_pipeline_failed(&pipeline_definition, error))
+    }
+
+    /// starts again with the input and applies all remaining commands
+    fn replay(&mut self) -> Result<(), StreamingEditorError> {
+        let mut current = vec![parse_input(&self.input)?];
+        for command in &self.commands {
+            current = Self::run(command, current)?;
+        }
+        self.current = current;
+
+        Ok(())
+    }
+}
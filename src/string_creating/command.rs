@@ -1,7 +1,15 @@
-use crate::{CommandError, CssSelectorList, HtmlContent, HtmlRenderable, ParsingRegexFailedSnafu};
+use crate::css::StyleDeclarations;
+use crate::{
+    CommandError, CompiledRegex, CssSelectorList, HtmlContent, HtmlRenderable,
+    InvalidFormatTemplateSnafu,
+};
+use html_escape::{decode_html_entities, encode_safe};
+use log::warn;
 use rctree::Node;
 use regex::Regex;
-use snafu::ResultExt;
+use snafu::OptionExt;
+use std::borrow::Cow;
+use std::collections::HashSet;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ElementSelectingCommand<'a> {
@@ -19,6 +27,17 @@ pub(crate) enum ElementSelectingCommand<'a> {
 }
 
 impl<'a> ElementSelectingCommand<'a> {
+    /// canonical name of the command, as used in error messages
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ElementSelectingCommand::UseElement => "USE-ELEMENT",
+            ElementSelectingCommand::UseParent => "USE-PARENT",
+            ElementSelectingCommand::QueryElement(..) => "QUERY-ELEMENT",
+            ElementSelectingCommand::QueryParent(..) => "QUERY-PARENT",
+            ElementSelectingCommand::QueryRoot(..) => "QUERY-ROOT",
+        }
+    }
+
     /// perform the action defined by the command on the set of nodes
     /// and return the calculated results.
     /// For some command the output can be equal to the input,
@@ -81,14 +100,44 @@ impl<'a> ElementSelectingCommand<'a> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)] // named after the commands, which all start with GET-
 pub(crate) enum ValueExtractingCommand<'a> {
     /// returns the content of a named attribute
     GetAttribute(&'a str),
-    /// return the text content of that element
+    /// return the text content of that element, one (possibly empty) value per element
     GetTextContent,
+    /// returns the value of a single declaration of the `style` attribute, without `!important`
+    GetStyle(&'a str),
+    /// returns the markup of all children of that element
+    GetInnerHtml,
+    /// returns the markup of that element, including itself
+    GetOuterHtml,
+    /// returns the tag name of that element
+    GetTagName,
+    /// returns the content of each comment within that element, including nested ones
+    GetComments,
+    /// returns the name of each attribute of that element, in alphabetical order
+    GetAttributeNames,
+    /// returns all text within that element, concatenated without separators
+    GetRawText,
 }
 
 impl<'a> ValueExtractingCommand<'a> {
+    /// canonical name of the command, as used in error messages
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ValueExtractingCommand::GetAttribute(..) => "GET-ATTR",
+            ValueExtractingCommand::GetTextContent => "GET-TEXT-CONTENT",
+            ValueExtractingCommand::GetStyle(..) => "GET-STYLE",
+            ValueExtractingCommand::GetInnerHtml => "GET-INNER-HTML",
+            ValueExtractingCommand::GetOuterHtml => "GET-OUTER-HTML",
+            ValueExtractingCommand::GetTagName => "GET-TAG-NAME",
+            ValueExtractingCommand::GetComments => "GET-COMMENTS",
+            ValueExtractingCommand::GetAttributeNames => "GET-ATTR-NAMES",
+            ValueExtractingCommand::GetRawText => "GET-RAW-TEXT",
+        }
+    }
+
     /// perform the action defined by the command on the set of nodes
     /// and return the calculated results.
     pub(crate) fn execute(&self, input: &[Node<HtmlContent>]) -> Result<Vec<String>, CommandError> {
@@ -97,6 +146,20 @@ impl<'a> ValueExtractingCommand<'a> {
                 Self::get_attribute(input, attr_name)
             }
             ValueExtractingCommand::GetTextContent => Self::get_text_content(input),
+            ValueExtractingCommand::GetStyle(property) => Self::get_style(input, property),
+            ValueExtractingCommand::GetInnerHtml => {
+                Ok(input.iter().map(|n| n.inner_html()).collect::<Vec<_>>())
+            }
+            ValueExtractingCommand::GetOuterHtml => {
+                Ok(input.iter().map(|n| n.outer_html()).collect::<Vec<_>>())
+            }
+            ValueExtractingCommand::GetTagName => Self::get_tag_name(input),
+            ValueExtractingCommand::GetComments => Self::get_comments(input),
+            ValueExtractingCommand::GetAttributeNames => Ok(input
+                .iter()
+                .flat_map(|n| n.borrow().get_attribute_names())
+                .collect::<Vec<_>>()),
+            ValueExtractingCommand::GetRawText => Self::get_raw_text(input),
         }
     }
 
@@ -115,35 +178,145 @@ impl<'a> ValueExtractingCommand<'a> {
             .collect::<Vec<_>>())
     }
 
-    fn get_text_content(input: &[Node<HtmlContent>]) -> Result<Vec<String>, CommandError> {
+    fn get_style(input: &[Node<HtmlContent>], property: &str) -> Result<Vec<String>, CommandError> {
+        let attribute = String::from("style");
         Ok(input
             .iter()
             .filter_map(|n| {
-                let content = n.text_content();
-                if content.is_empty() {
-                    None
-                } else {
-                    Some(content)
-                }
+                let style = StyleDeclarations::parse(&n.borrow().get_attribute(&attribute)?);
+
+                style.get(property).map(String::from)
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn get_tag_name(input: &[Node<HtmlContent>]) -> Result<Vec<String>, CommandError> {
+        Ok(input
+            .iter()
+            .filter_map(|n| match &*n.borrow() {
+                HtmlContent::Tag(tag) => Some(tag.name.clone()),
+                HtmlContent::Comment(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => None,
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn get_comments(input: &[Node<HtmlContent>]) -> Result<Vec<String>, CommandError> {
+        Ok(input
+            .iter()
+            .flat_map(|n| n.descendants())
+            .filter_map(|n| match &*n.borrow() {
+                HtmlContent::Comment(comment) => Some(comment.clone()),
+                HtmlContent::Tag(_) | HtmlContent::Text(_) | HtmlContent::Document(_) => None,
             })
             .collect::<Vec<_>>())
     }
+
+    fn get_raw_text(input: &[Node<HtmlContent>]) -> Result<Vec<String>, CommandError> {
+        Ok(input
+            .iter()
+            .map(|n| {
+                n.descendants()
+                    .filter_map(|d| match &*d.borrow() {
+                        HtmlContent::Text(text) => Some(text.clone()),
+                        HtmlContent::Tag(_)
+                        | HtmlContent::Comment(_)
+                        | HtmlContent::Document(_) => None,
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn get_text_content(input: &[Node<HtmlContent>]) -> Result<Vec<String>, CommandError> {
+        Ok(input.iter().map(|n| n.text_content()).collect::<Vec<_>>())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ValueProcessingCommand<'a> {
-    RegexReplace(&'a str, &'a str),
+    RegexReplace(CompiledRegex<'a>, Cow<'a, str>),
     /// returns an all-lower-case version of the input
     ToLower,
     /// returns an all-upper-case version of the input
     ToUpper,
     /// returns the input prefixed with given string
-    AddPrefix(&'a str),
+    AddPrefix(Cow<'a, str>),
     /// returns the input suffixed with given string
-    AddSuffix(&'a str),
+    AddSuffix(Cow<'a, str>),
+    /// returns the input without leading and trailing whitespace
+    Trim,
+    /// returns the input with each run of whitespace replaced by a single space, trimmed
+    CollapseWhitespace,
+    /// returns the part of the input starting at the given character (counting from 0),
+    /// optionally limited to the given number of characters
+    Substring(usize, Option<usize>),
+    /// returns the given capture group (default: the first one, or the whole match if there are no groups)
+    /// of the first match of the RegEx. Values without match are dropped
+    RegexExtract(CompiledRegex<'a>, Option<usize>),
+    /// splits each value at the given separator, returning all parts as separate values
+    Split(Cow<'a, str>),
+    /// joins all values into one, separated by the given string
+    Join(Cow<'a, str>),
+    /// returns a lower-case version of the input with each run of non-alphanumeric characters replaced by `-`
+    Slugify,
+    /// returns the input cut to the given number of characters, followed by the (optional) ellipsis if it was longer
+    Truncate(usize, Option<Cow<'a, str>>),
+    /// returns the input percent-encoded, keeping only unreserved characters (`A-Z`, `a-z`, `0-9`, `-`, `_`, `.`, `~`)
+    UrlEncode,
+    /// returns the input with all percent-encoded characters decoded
+    UrlDecode,
+    /// returns the input with `&`, `<`, `>`, `"` and `'` escaped as HTML entities
+    HtmlEscape,
+    /// returns the input with all HTML entities decoded
+    HtmlUnescape,
+    /// returns the given value instead, if there are no values or all of them are empty
+    Default(Cow<'a, str>),
+    /// returns one value built from the template, replacing `{0}`, `{1}`, … by the value at that position
+    /// and `{}` by the next one. `{{` and `}}` produce literal braces, any other braces are invalid
+    Format(Cow<'a, str>),
+    /// returns only the first value
+    First,
+    /// returns only the last value
+    Last,
+    /// returns the number of values as single value
+    Count,
+    /// returns the values without duplicates, keeping the first occurrence of each
+    Unique,
+    /// returns the values sorted, numerically if all of them are numbers, lexicographically otherwise
+    Sort,
 }
 
 impl<'a> ValueProcessingCommand<'a> {
+    /// canonical name of the command, as used in error messages
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ValueProcessingCommand::RegexReplace(..) => "REGEX-REPLACE",
+            ValueProcessingCommand::ToLower => "TO-LOWER",
+            ValueProcessingCommand::ToUpper => "TO-UPPER",
+            ValueProcessingCommand::AddPrefix(..) => "ADD-PREFIX",
+            ValueProcessingCommand::AddSuffix(..) => "ADD-SUFFIX",
+            ValueProcessingCommand::Trim => "TRIM",
+            ValueProcessingCommand::CollapseWhitespace => "COLLAPSE-WHITESPACE",
+            ValueProcessingCommand::Substring(..) => "SUBSTRING",
+            ValueProcessingCommand::RegexExtract(..) => "REGEX-EXTRACT",
+            ValueProcessingCommand::Split(..) => "SPLIT",
+            ValueProcessingCommand::Join(..) => "JOIN",
+            ValueProcessingCommand::Slugify => "SLUGIFY",
+            ValueProcessingCommand::Truncate(..) => "TRUNCATE",
+            ValueProcessingCommand::UrlEncode => "URL-ENCODE",
+            ValueProcessingCommand::UrlDecode => "URL-DECODE",
+            ValueProcessingCommand::HtmlEscape => "HTML-ESCAPE",
+            ValueProcessingCommand::HtmlUnescape => "HTML-UNESCAPE",
+            ValueProcessingCommand::Default(..) => "DEFAULT",
+            ValueProcessingCommand::Format(..) => "FORMAT",
+            ValueProcessingCommand::First => "FIRST",
+            ValueProcessingCommand::Last => "LAST",
+            ValueProcessingCommand::Count => "COUNT",
+            ValueProcessingCommand::Unique => "UNIQUE",
+            ValueProcessingCommand::Sort => "SORT",
+        }
+    }
+
     /// perform the action defined by the command on the set of nodes
     /// and return the calculated results.
     pub(crate) fn execute(&self, input: &[String]) -> Result<Vec<String>, CommandError> {
@@ -155,19 +328,59 @@ impl<'a> ValueProcessingCommand<'a> {
             ValueProcessingCommand::ToUpper => Self::convert_to_upper(input),
             ValueProcessingCommand::AddPrefix(prefix) => Self::add_prefix(input, prefix),
             ValueProcessingCommand::AddSuffix(suffix) => Self::add_suffix(input, suffix),
+            ValueProcessingCommand::Trim => {
+                Ok(input.iter().map(|v| String::from(v.trim())).collect())
+            }
+            ValueProcessingCommand::CollapseWhitespace => Ok(input
+                .iter()
+                .map(|v| v.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect()),
+            ValueProcessingCommand::Substring(start, length) => {
+                Self::substring(input, *start, *length)
+            }
+            ValueProcessingCommand::RegexExtract(regex, group) => {
+                Self::regex_extract(input, regex, *group)
+            }
+            ValueProcessingCommand::Split(separator) => Ok(input
+                .iter()
+                .flat_map(|v| v.split(separator.as_ref()).map(String::from))
+                .collect()),
+            ValueProcessingCommand::Join(separator) => Self::join(input, separator),
+            ValueProcessingCommand::Slugify => Ok(input.iter().map(|v| Self::slugify(v)).collect()),
+            ValueProcessingCommand::Truncate(length, ellipsis) => {
+                Self::truncate(input, *length, ellipsis.as_deref().unwrap_or_default())
+            }
+            ValueProcessingCommand::UrlEncode => {
+                Ok(input.iter().map(|v| Self::url_encode(v)).collect())
+            }
+            ValueProcessingCommand::UrlDecode => {
+                Ok(input.iter().map(|v| Self::url_decode(v)).collect())
+            }
+            ValueProcessingCommand::HtmlEscape => {
+                Ok(input.iter().map(|v| String::from(encode_safe(v))).collect())
+            }
+            ValueProcessingCommand::HtmlUnescape => Ok(input
+                .iter()
+                .map(|v| String::from(decode_html_entities(v)))
+                .collect()),
+            ValueProcessingCommand::Default(default) => Self::default(input, default),
+            ValueProcessingCommand::Format(template) => Self::format(input, template),
+            ValueProcessingCommand::First => Ok(input.iter().take(1).cloned().collect()),
+            ValueProcessingCommand::Last => Ok(input.iter().last().cloned().into_iter().collect()),
+            ValueProcessingCommand::Count => Ok(vec![input.len().to_string()]),
+            ValueProcessingCommand::Unique => Self::unique(input),
+            ValueProcessingCommand::Sort => Self::sort(input),
         }
     }
 
     fn regex_replace(
         input: &[String],
-        regex: &str,
+        regex: &Regex,
         replace: &str,
     ) -> Result<Vec<String>, CommandError> {
-        let re = Regex::new(regex).context(ParsingRegexFailedSnafu)?;
-
         Ok(input
             .iter()
-            .map(|v| re.replace_all(v, replace))
+            .map(|v| regex.replace_all(v, replace))
             .map(String::from)
             .collect::<Vec<_>>())
     }
@@ -180,26 +393,245 @@ impl<'a> ValueProcessingCommand<'a> {
         Ok(input.iter().map(|v| v.to_uppercase()).collect::<Vec<_>>())
     }
 
-    fn add_prefix(input: &[String], prefix: &'a str) -> Result<Vec<String>, CommandError> {
+    fn add_prefix(input: &[String], prefix: &str) -> Result<Vec<String>, CommandError> {
         Ok(input
             .iter()
             .map(|v| format!("{}{}", prefix, v))
             .collect::<Vec<_>>())
     }
 
-    fn add_suffix(input: &[String], suffix: &'a str) -> Result<Vec<String>, CommandError> {
+    fn add_suffix(input: &[String], suffix: &str) -> Result<Vec<String>, CommandError> {
         Ok(input
             .iter()
             .map(|v| format!("{}{}", v, suffix))
             .collect::<Vec<_>>())
     }
+
+    fn substring(
+        input: &[String],
+        start: usize,
+        length: Option<usize>,
+    ) -> Result<Vec<String>, CommandError> {
+        Ok(input
+            .iter()
+            .map(|v| {
+                let remaining = v.chars().skip(start);
+                match length {
+                    Some(length) => remaining.take(length).collect::<String>(),
+                    None => remaining.collect::<String>(),
+                }
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn regex_extract(
+        input: &[String],
+        regex: &Regex,
+        group: Option<usize>,
+    ) -> Result<Vec<String>, CommandError> {
+        let group = group.unwrap_or(if regex.captures_len() > 1 { 1 } else { 0 });
+
+        Ok(input
+            .iter()
+            .filter_map(|v| regex.captures(v))
+            .filter_map(|c| c.get(group))
+            .map(|m| String::from(m.as_str()))
+            .collect::<Vec<_>>())
+    }
+
+    fn join(input: &[String], separator: &str) -> Result<Vec<String>, CommandError> {
+        if input.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(vec![input.join(separator)])
+    }
+
+    fn unique(input: &[String]) -> Result<Vec<String>, CommandError> {
+        let mut seen = HashSet::new();
+
+        Ok(input
+            .iter()
+            .filter(|v| seen.insert(v.as_str()))
+            .cloned()
+            .collect::<Vec<_>>())
+    }
+
+    fn sort(input: &[String]) -> Result<Vec<String>, CommandError> {
+        let numbers = input
+            .iter()
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>();
+
+        let mut sorted = input.iter().enumerate().collect::<Vec<_>>();
+        match numbers {
+            Ok(numbers) => sorted.sort_by(|(a, _), (b, _)| numbers[*a].total_cmp(&numbers[*b])),
+            Err(_) => sorted.sort_by_key(|(_, a)| *a),
+        }
+
+        Ok(sorted.into_iter().map(|(_, v)| v.clone()).collect())
+    }
+
+    fn slugify(value: &str) -> String {
+        value
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    fn truncate(
+        input: &[String],
+        length: usize,
+        ellipsis: &str,
+    ) -> Result<Vec<String>, CommandError> {
+        Ok(input
+            .iter()
+            .map(|v| {
+                if v.chars().count() <= length {
+                    v.clone()
+                } else {
+                    format!("{}{}", v.chars().take(length).collect::<String>(), ellipsis)
+                }
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn url_encode(value: &str) -> String {
+        value
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    String::from(b as char)
+                }
+                _ => format!("%{:02X}", b),
+            })
+            .collect::<String>()
+    }
+
+    fn url_decode(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+
+        while index < bytes.len() {
+            let hex = bytes
+                .get(index + 1..index + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+            match (bytes[index], hex) {
+                (b'%', Some(byte)) => {
+                    decoded.push(byte);
+                    index += 3;
+                }
+                (byte, _) => {
+                    decoded.push(byte);
+                    index += 1;
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    fn default(input: &[String], default: &str) -> Result<Vec<String>, CommandError> {
+        if input.iter().all(|v| v.is_empty()) {
+            return Ok(vec![String::from(default)]);
+        }
+
+        Ok(input.to_vec())
+    }
+
+    fn format(input: &[String], template: &str) -> Result<Vec<String>, CommandError> {
+        let parts = format_template_parts(template).context(InvalidFormatTemplateSnafu {
+            template: String::from(template),
+        })?;
+
+        let mut result = String::new();
+        for part in parts {
+            match part {
+                FormatPart::Text(text) => result.push_str(&text),
+                FormatPart::Value(position) => match input.get(position) {
+                    Some(value) => result.push_str(value),
+                    None => warn!("FORMAT references missing value {}", position),
+                },
+            }
+        }
+
+        Ok(vec![result])
+    }
+}
+
+/// A part of a `FORMAT` template
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum FormatPart {
+    Text(String),
+    /// a placeholder, with the position of the value it stands for
+    Value(usize),
+}
+
+/// splits the template into text and placeholders: `{0}`, `{1}`, … or `{}` for the next value,
+/// `{{` and `}}` being literal braces.
+/// `None` if a placeholder is neither empty nor a position, isn't closed or a `}` isn't doubled
+pub(crate) fn format_template_parts(template: &str) -> Option<Vec<FormatPart>> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut next_position = 0;
+    let mut characters = template.chars().peekable();
+
+    while let Some(c) = characters.next() {
+        match c {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                text.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                text.push('}');
+            }
+            '}' => return None,
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match characters.next()? {
+                        '}' => break,
+                        c => placeholder.push(c),
+                    }
+                }
+
+                let position = match placeholder.trim() {
+                    "" => {
+                        next_position += 1;
+                        next_position - 1
+                    }
+                    position => position.parse::<usize>().ok()?,
+                };
+
+                if !text.is_empty() {
+                    parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(FormatPart::Value(position));
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(FormatPart::Text(text));
+    }
+
+    Some(parts)
 }
 
 #[cfg(test)]
 mod test {
     use crate::string_creating::command::ValueProcessingCommand;
     use crate::string_creating::{ElementSelectingCommand, ValueExtractingCommand};
-    use crate::{load_inline_html, CssSelector, CssSelectorList, CssSelectorPath};
+    use crate::{
+        load_inline_html, CommandError, CompiledRegex, CssSelector, CssSelectorList,
+        CssSelectorPath,
+    };
 
     #[test]
     fn use_element_returns_self() {
@@ -447,6 +879,116 @@ mod test {
         assert_eq!(result.len(), 0);
     }
 
+    #[test]
+    fn get_style_returns_value_of_declaration() {
+        let root = load_inline_html(r#"<div style="color: red; width: 50% !important"></div>"#);
+        let command = ValueExtractingCommand::GetStyle("width");
+
+        let result = command.execute(&[root]).unwrap();
+
+        assert_eq!(result, vec![String::from("50%")]);
+    }
+
+    #[test]
+    fn get_style_returns_empty_on_missing_declaration() {
+        let root = load_inline_html(r#"<div style="color: red"></div>"#);
+        let command = ValueExtractingCommand::GetStyle("width");
+
+        let result = command.execute(&[root]).unwrap();
+
+        assert_eq!(result.len(), 0);
+    }
+
+    #[test]
+    fn get_inner_and_outer_html_return_markup() {
+        let root = load_inline_html(r#"<p class="x">Some <b>bold</b> text</p>"#);
+
+        assert_eq!(
+            ValueExtractingCommand::GetInnerHtml
+                .execute(&[rctree::Node::clone(&root)])
+                .unwrap(),
+            vec![String::from("Some <b>bold</b> text")]
+        );
+        assert_eq!(
+            ValueExtractingCommand::GetOuterHtml
+                .execute(&[root])
+                .unwrap(),
+            vec![String::from(r#"<p class="x">Some <b>bold</b> text</p>"#)]
+        );
+    }
+
+    #[test]
+    fn get_inner_html_returns_empty_value_for_empty_element() {
+        let root = load_inline_html(r#"<p></p>"#);
+
+        let result = ValueExtractingCommand::GetInnerHtml
+            .execute(&[root])
+            .unwrap();
+
+        assert_eq!(result, vec![String::new()]);
+    }
+
+    #[test]
+    fn get_tag_name_returns_name() {
+        let root = load_inline_html(r#"<section><h1>Title</h1></section>"#);
+
+        let result = ValueExtractingCommand::GetTagName.execute(&[root]).unwrap();
+
+        assert_eq!(result, vec![String::from("section")]);
+    }
+
+    #[test]
+    fn get_comments_returns_nested_comments() {
+        let root = load_inline_html(
+            r#"<body><!-- build: 1.2.3 --><div>Text <!-- commit: abc --></div></body>"#,
+        );
+
+        let result = ValueExtractingCommand::GetComments
+            .execute(&[root])
+            .unwrap();
+
+        assert_eq!(
+            result,
+            vec![String::from("build: 1.2.3"), String::from("commit: abc")]
+        );
+    }
+
+    #[test]
+    fn get_attr_names_returns_each_name() {
+        let root = load_inline_html(r#"<img src="" alt="" data-id="1">"#);
+
+        let result = ValueExtractingCommand::GetAttributeNames
+            .execute(&[root])
+            .unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                String::from("alt"),
+                String::from("data-id"),
+                String::from("src")
+            ]
+        );
+    }
+
+    #[test]
+    fn get_raw_text_does_not_add_separators() {
+        let root = load_inline_html(r#"<p>H<sub>2</sub>O <!-- water --> is <em>wet</em></p>"#);
+
+        assert_eq!(
+            ValueExtractingCommand::GetRawText
+                .execute(&[rctree::Node::clone(&root)])
+                .unwrap(),
+            vec![String::from("H2O  is wet")]
+        );
+        assert_eq!(
+            ValueExtractingCommand::GetTextContent
+                .execute(&[root])
+                .unwrap(),
+            vec![String::from("H 2 O   is  wet")]
+        );
+    }
+
     #[test]
     fn get_attr_returns_value_on_existing_attr() {
         let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
@@ -498,7 +1040,7 @@ mod test {
 
         let result = command.execute(&[root]).unwrap();
 
-        assert_eq!(result.len(), 0);
+        assert_eq!(result, vec![String::new()]);
     }
 
     #[test]
@@ -512,7 +1054,10 @@ mod test {
 
     #[test]
     fn regex_replaces_uses_indexed_group_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("(He)(l+)o", "-> $2 <-");
+        let command = ValueProcessingCommand::RegexReplace(
+            CompiledRegex::new("(He)(l+)o").unwrap(),
+            "-> $2 <-".into(),
+        );
 
         let mut result = command.execute(&[String::from("Hello")]).unwrap();
 
@@ -524,7 +1069,10 @@ mod test {
 
     #[test]
     fn regex_replaces_uses_named_groups_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("(?P<s>He)(?P<m>l+)o", "-> $m <-");
+        let command = ValueProcessingCommand::RegexReplace(
+            CompiledRegex::new("(?P<s>He)(?P<m>l+)o").unwrap(),
+            "-> $m <-".into(),
+        );
 
         let mut result = command.execute(&[String::from("Hello")]).unwrap();
 
@@ -536,7 +1084,8 @@ mod test {
 
     #[test]
     fn regex_replaces_uses_number_classes_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("\\d", "##");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("\\d").unwrap(), "##".into());
 
         let mut result = command
             .execute(&[String::from("And one, 2, three, 4")])
@@ -550,7 +1099,8 @@ mod test {
 
     #[test]
     fn regex_replaces_uses_whitespace_classes_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("\\s", "_");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("\\s").unwrap(), "_".into());
 
         let mut result = command
             .execute(&[String::from("And one, 2, three, 4")])
@@ -564,7 +1114,8 @@ mod test {
 
     #[test]
     fn regex_replaces_uses_nonword_classes_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("\\W", "_");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("\\W").unwrap(), "_".into());
 
         let mut result = command
             .execute(&[String::from("And one, 2, three, 4")])
@@ -578,7 +1129,8 @@ mod test {
 
     #[test]
     fn regex_replaces_changes_values_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("a", "e");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("a").unwrap(), "e".into());
 
         let mut result = command.execute(&[String::from("Hallo")]).unwrap();
 
@@ -590,7 +1142,8 @@ mod test {
 
     #[test]
     fn regex_replaces_changes_only_occurrences_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("a", "e");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("a").unwrap(), "e".into());
 
         let mut result = command.execute(&[String::from("Apples are good")]).unwrap();
 
@@ -602,7 +1155,8 @@ mod test {
 
     #[test]
     fn regex_replaces_changes_all_occurrences_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("A", "E");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("A").unwrap(), "E".into());
 
         let mut result = command.execute(&[String::from("Apples Are Good")]).unwrap();
 
@@ -614,7 +1168,8 @@ mod test {
 
     #[test]
     fn regex_replaces_changes_all_inputs_correctly() {
-        let command = ValueProcessingCommand::RegexReplace("a", "e");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("a").unwrap(), "e".into());
 
         let mut result = command
             .execute(&[String::from("Hallo"), String::from("apples are good")])
@@ -630,7 +1185,8 @@ mod test {
 
     #[test]
     fn regex_replaces_returns_empty_string_on_empty_input() {
-        let command = ValueProcessingCommand::RegexReplace("a", "e");
+        let command =
+            ValueProcessingCommand::RegexReplace(CompiledRegex::new("a").unwrap(), "e".into());
 
         let result = command.execute(&[]).unwrap();
 
@@ -709,7 +1265,7 @@ mod test {
 
     #[test]
     fn add_prefix_returns_prefixed_version_correctly() {
-        let command = ValueProcessingCommand::AddPrefix("a");
+        let command = ValueProcessingCommand::AddPrefix("a".into());
 
         let mut result = command.execute(&[String::from("b")]).unwrap();
 
@@ -721,7 +1277,7 @@ mod test {
 
     #[test]
     fn add_prefix_returns_empty_on_empty_input() {
-        let command = ValueProcessingCommand::AddPrefix("a");
+        let command = ValueProcessingCommand::AddPrefix("a".into());
 
         let result = command.execute(&[]).unwrap();
 
@@ -730,7 +1286,7 @@ mod test {
 
     #[test]
     fn add_suffix_returns_suffixed_version_correctly() {
-        let command = ValueProcessingCommand::AddSuffix("z");
+        let command = ValueProcessingCommand::AddSuffix("z".into());
 
         let mut result = command.execute(&[String::from("b")]).unwrap();
 
@@ -742,10 +1298,278 @@ mod test {
 
     #[test]
     fn add_suffix_returns_empty_on_empty_input() {
-        let command = ValueProcessingCommand::AddSuffix("z");
+        let command = ValueProcessingCommand::AddSuffix("z".into());
 
         let result = command.execute(&[]).unwrap();
 
         assert_eq!(result.len(), 0);
     }
+
+    fn values(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| String::from(*v)).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn trim_and_collapse_whitespace() {
+        let input = values(&["  Some \n  text\t"]);
+
+        assert_eq!(
+            ValueProcessingCommand::Trim.execute(&input).unwrap(),
+            values(&["Some \n  text"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::CollapseWhitespace
+                .execute(&input)
+                .unwrap(),
+            values(&["Some text"])
+        );
+    }
+
+    #[test]
+    fn substring_counts_characters() {
+        let input = values(&["Grüße aus Köln"]);
+
+        assert_eq!(
+            ValueProcessingCommand::Substring(2, Some(3))
+                .execute(&input)
+                .unwrap(),
+            values(&["üße"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Substring(10, None)
+                .execute(&input)
+                .unwrap(),
+            values(&["Köln"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Substring(20, None)
+                .execute(&input)
+                .unwrap(),
+            values(&[""])
+        );
+    }
+
+    #[test]
+    fn regex_extract_uses_capture_groups() {
+        let input = values(&["version v1.2.3", "no version", "mail: a@b"]);
+
+        assert_eq!(
+            ValueProcessingCommand::RegexExtract(CompiledRegex::new(r"v(\d+)\.").unwrap(), None)
+                .execute(&input)
+                .unwrap(),
+            values(&["1"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::RegexExtract(
+                CompiledRegex::new(r"(\w)@(\w)").unwrap(),
+                Some(2)
+            )
+            .execute(&input)
+            .unwrap(),
+            values(&["b"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::RegexExtract(CompiledRegex::new(r"\w@\w").unwrap(), None)
+                .execute(&input)
+                .unwrap(),
+            values(&["a@b"])
+        );
+    }
+
+    #[test]
+    fn split_and_join_work_on_all_values() {
+        let split = ValueProcessingCommand::Split(",".into())
+            .execute(&values(&["a,b", "c"]))
+            .unwrap();
+        assert_eq!(split, values(&["a", "b", "c"]));
+
+        let joined = ValueProcessingCommand::Join(" | ".into())
+            .execute(&split)
+            .unwrap();
+        assert_eq!(joined, values(&["a | b | c"]));
+
+        assert!(ValueProcessingCommand::Join(",".into())
+            .execute(&[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn slugify_creates_url_friendly_value() {
+        assert_eq!(
+            ValueProcessingCommand::Slugify
+                .execute(&values(&["  Hello, World! Ünïcode & more "]))
+                .unwrap(),
+            values(&["hello-world-ünïcode-more"])
+        );
+    }
+
+    #[test]
+    fn truncate_adds_ellipsis_only_when_cut() {
+        let input = values(&["short", "a longer text"]);
+
+        assert_eq!(
+            ValueProcessingCommand::Truncate(8, Some("…".into()))
+                .execute(&input)
+                .unwrap(),
+            values(&["short", "a longer…"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Truncate(3, None)
+                .execute(&input)
+                .unwrap(),
+            values(&["sho", "a l"])
+        );
+    }
+
+    #[test]
+    fn url_encode_and_decode_roundtrip() {
+        let encoded = ValueProcessingCommand::UrlEncode
+            .execute(&values(&["a b&c=ä/~"]))
+            .unwrap();
+        assert_eq!(encoded, values(&["a%20b%26c%3D%C3%A4%2F~"]));
+
+        assert_eq!(
+            ValueProcessingCommand::UrlDecode.execute(&encoded).unwrap(),
+            values(&["a b&c=ä/~"])
+        );
+    }
+
+    #[test]
+    fn url_decode_keeps_invalid_sequences() {
+        assert_eq!(
+            ValueProcessingCommand::UrlDecode
+                .execute(&values(&["100% %zz %4"]))
+                .unwrap(),
+            values(&["100% %zz %4"])
+        );
+    }
+
+    #[test]
+    fn html_escape_and_unescape() {
+        let escaped = ValueProcessingCommand::HtmlEscape
+            .execute(&values(&[r#"<a href="x">Tom & 'Jerry'</a>"#]))
+            .unwrap();
+        assert_eq!(
+            escaped,
+            values(&["&lt;a href=&quot;x&quot;&gt;Tom &amp; &#x27;Jerry&#x27;&lt;&#x2F;a&gt;"])
+        );
+
+        assert_eq!(
+            ValueProcessingCommand::HtmlUnescape
+                .execute(&escaped)
+                .unwrap(),
+            values(&[r#"<a href="x">Tom & 'Jerry'</a>"#])
+        );
+    }
+
+    #[test]
+    fn default_replaces_empty_results() {
+        let command = ValueProcessingCommand::Default("n/a".into());
+
+        assert_eq!(command.execute(&[]).unwrap(), values(&["n/a"]));
+        assert_eq!(
+            command.execute(&values(&["", ""])).unwrap(),
+            values(&["n/a"])
+        );
+        assert_eq!(
+            command.execute(&values(&["", "x"])).unwrap(),
+            values(&["", "x"])
+        );
+    }
+
+    #[test]
+    fn format_combines_values() {
+        let input = values(&["2023", "06", "01"]);
+
+        assert_eq!(
+            ValueProcessingCommand::Format("{2}.{1}.{0}".into())
+                .execute(&input)
+                .unwrap(),
+            values(&["01.06.2023"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Format("{}-{}/{{{3}}}".into())
+                .execute(&input)
+                .unwrap(),
+            values(&["2023-06/{}"])
+        );
+    }
+
+    #[test]
+    fn format_rejects_malformed_placeholders() {
+        let input = values(&["a", "b"]);
+
+        for template in ["{0", "{name}", "{1}}", "a } b", "{-1}"] {
+            assert!(
+                matches!(
+                    ValueProcessingCommand::Format(template.into()).execute(&input),
+                    Err(CommandError::InvalidFormatTemplate { .. })
+                ),
+                "{template} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn first_and_last_pick_single_value() {
+        let input = values(&["a", "b", "c"]);
+
+        assert_eq!(
+            ValueProcessingCommand::First.execute(&input).unwrap(),
+            values(&["a"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Last.execute(&input).unwrap(),
+            values(&["c"])
+        );
+        assert!(ValueProcessingCommand::First
+            .execute(&[])
+            .unwrap()
+            .is_empty());
+        assert!(ValueProcessingCommand::Last
+            .execute(&[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn count_includes_empty_values() {
+        assert_eq!(
+            ValueProcessingCommand::Count
+                .execute(&values(&["a", "", "c"]))
+                .unwrap(),
+            values(&["3"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Count.execute(&[]).unwrap(),
+            values(&["0"])
+        );
+    }
+
+    #[test]
+    fn unique_keeps_first_occurrence() {
+        assert_eq!(
+            ValueProcessingCommand::Unique
+                .execute(&values(&["b", "a", "b", "c", "a"]))
+                .unwrap(),
+            values(&["b", "a", "c"])
+        );
+    }
+
+    #[test]
+    fn sort_numerically_only_if_all_values_are_numbers() {
+        assert_eq!(
+            ValueProcessingCommand::Sort
+                .execute(&values(&["10", " 9", "-1.5", "100"]))
+                .unwrap(),
+            values(&["-1.5", " 9", "10", "100"])
+        );
+        assert_eq!(
+            ValueProcessingCommand::Sort
+                .execute(&values(&["10", "9", "b", "A"]))
+                .unwrap(),
+            values(&["10", "9", "A", "b"])
+        );
+    }
 }
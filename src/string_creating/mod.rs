@@ -1,5 +1,7 @@
 mod command;
 mod pipeline;
 
-pub(crate) use command::{ElementSelectingCommand, ValueExtractingCommand, ValueProcessingCommand};
+pub(crate) use command::{
+    format_template_parts, ElementSelectingCommand, ValueExtractingCommand, ValueProcessingCommand,
+};
 pub(crate) use pipeline::StringValueCreatingPipeline;
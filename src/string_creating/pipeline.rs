@@ -2,12 +2,16 @@ use crate::string_creating::command::ValueProcessingCommand;
 use crate::string_creating::{ElementSelectingCommand, ValueExtractingCommand};
 use crate::{CommandFailedSnafu, HtmlContent, PipelineError};
 use snafu::ResultExt;
+use std::ops::Range;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The spans are only used to report errors, they are not compared.
+#[derive(Debug, Clone, Eq)]
 pub(crate) struct StringValueCreatingPipeline<'a> {
     element_selector: ElementSelectingCommand<'a>,
     value_extractor: ValueExtractingCommand<'a>,
     value_processing: Vec<ValueProcessingCommand<'a>>,
+    spans: Vec<Range<usize>>,
+    strict: bool,
 }
 
 /// The command pipeline: a list of individual commands
@@ -21,6 +25,8 @@ impl<'a> StringValueCreatingPipeline<'a> {
             element_selector,
             value_extractor,
             value_processing: vec![],
+            spans: vec![],
+            strict: false,
         }
     }
 
@@ -33,9 +39,27 @@ impl<'a> StringValueCreatingPipeline<'a> {
             element_selector,
             value_extractor,
             value_processing,
+            spans: vec![],
+            strict: false,
         }
     }
 
+    /// adds the position of each command within the pipeline definition,
+    /// starting with the element selecting command
+    pub fn with_spans(self, spans: Vec<Range<usize>>) -> Self {
+        StringValueCreatingPipeline { spans, ..self }
+    }
+
+    /// lets rendering the values into a single string fail if there are multiple ones,
+    /// instead of only logging a warning
+    pub(crate) fn make_strict(&mut self) {
+        self.strict = true;
+    }
+
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
     /// execute the pipeline on the given nodes by
     /// running the first commands on those nodes and all the following commands
     /// on their predecessors result.
@@ -47,31 +71,52 @@ impl<'a> StringValueCreatingPipeline<'a> {
         let element = self
             .element_selector
             .execute(node)
-            .context(CommandFailedSnafu { index: 0_usize })?;
-
-        let mut intermediate = self
-            .value_extractor
-            .execute(&element)
-            .context(CommandFailedSnafu { index: 1_usize })?;
+            .with_context(|_| CommandFailedSnafu {
+                command: self.element_selector.name(),
+                index: 0_usize,
+                span: self.spans.first().cloned(),
+                spans: self.spans.clone(),
+            })?;
+
+        let mut intermediate =
+            self.value_extractor
+                .execute(&element)
+                .with_context(|_| CommandFailedSnafu {
+                    command: self.value_extractor.name(),
+                    index: 1_usize,
+                    span: self.spans.get(1).cloned(),
+                    spans: self.spans.clone(),
+                })?;
 
         for (command_index, processing_command) in self.value_processing.iter().enumerate() {
-            intermediate =
-                processing_command
-                    .execute(&intermediate)
-                    .context(CommandFailedSnafu {
-                        index: command_index + 2,
-                    })?
+            intermediate = processing_command
+                .execute(&intermediate)
+                .with_context(|_| CommandFailedSnafu {
+                    command: processing_command.name(),
+                    index: command_index + 2,
+                    span: self.spans.get(command_index + 2).cloned(),
+                    spans: self.spans.clone(),
+                })?
         }
 
         Ok(intermediate)
     }
 }
 
+impl<'a> PartialEq for StringValueCreatingPipeline<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.element_selector == other.element_selector
+            && self.value_extractor == other.value_extractor
+            && self.value_processing == other.value_processing
+            && self.strict == other.strict
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::string_creating::command::ValueProcessingCommand;
     use crate::string_creating::{ElementSelectingCommand, ValueExtractingCommand};
-    use crate::{load_inline_html, StringValueCreatingPipeline};
+    use crate::{load_inline_html, CompiledRegex, StringValueCreatingPipeline};
 
     #[test]
     fn get_attr_from_element_returns_correct_value() {
@@ -108,7 +153,10 @@ mod test {
         let pipeline = StringValueCreatingPipeline::with_value_processing(
             ElementSelectingCommand::UseElement,
             ValueExtractingCommand::GetAttribute("data-test"),
-            vec![ValueProcessingCommand::RegexReplace("f", "z")],
+            vec![ValueProcessingCommand::RegexReplace(
+                CompiledRegex::new("f").unwrap(),
+                "z".into(),
+            )],
         );
 
         let root = load_inline_html(r#"<div data-test="foo" class="bar"></div>"#);
@@ -126,8 +174,8 @@ mod test {
             ElementSelectingCommand::UseElement,
             ValueExtractingCommand::GetAttribute("data-test"),
             vec![
-                ValueProcessingCommand::RegexReplace("f", "z"),
-                ValueProcessingCommand::RegexReplace("o", "a"),
+                ValueProcessingCommand::RegexReplace(CompiledRegex::new("f").unwrap(), "z".into()),
+                ValueProcessingCommand::RegexReplace(CompiledRegex::new("o").unwrap(), "a".into()),
             ],
         );
 
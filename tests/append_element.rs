@@ -122,3 +122,56 @@ fn copy_title_to_meta_tag() -> Result<(), StreamingEditorError> {
 
     Ok(())
 }
+
+#[test]
+fn append_all_list_items_from_file() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{#list} | APPEND-ELEMENT{ SOURCE{"tests/source.html"} | ONLY{#second li} }"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<ul id="list">
+            <li id="item-1">1</li>
+            <li id="item-2">2</li>
+            <li id="item-3">3</li>
+        <li>a</li><li><!-- Some Comment -->b</li><li><em class="intense">c</em></li></ul>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn append_copy_of_own_child() -> Result<(), StreamingEditorError> {
+    let command =
+        r#"EXTRACT-ELEMENT{#third-para} | APPEND-ELEMENT{ KEEP{abbr} | SET-ATTR{class ↤ "copy"} }"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<p id="third-para">Third text of <abbr>HTML</abbr>, but no <abbr>CSS</abbr><abbr class="copy">HTML</abbr><abbr class="copy">CSS</abbr></p>"#
+        )
+    );
+
+    Ok(())
+}
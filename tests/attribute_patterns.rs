@@ -0,0 +1,58 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <div data-v-7ba5bd90="" class="card" onclick="openCard()">
+            <img src="placeholder.gif" data-src="photo.jpg" data-v-7ba5bd90="" aria-hidden="true">
+        </div>
+    </body>
+</html>"#;
+
+#[test]
+fn sanitize_exported_markup() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{div} | CLEAR-ATTR{on*, data-v-*} | FOR-EACH{img ↦ RENAME-ATTR{data-src ↦ src} | CLEAR-ATTR{~'^aria-'}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<div class="card">
+            <img data-v-7ba5bd90="" src="photo.jpg">
+        </div>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn keep_only_allowed_attributes() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{img} | CLEAR-ALL-ATTRS-EXCEPT{src, alt}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<img src="placeholder.gif">"#)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <nav>
+            <a href="/" class="nav-link active">Home</a>
+            <a href="/blog" class="nav-link" data-section="blog">Blog</a>
+        </nav>
+        <button class="btn  btn-default">Save</button>
+    </body>
+</html>"#;
+
+#[test]
+fn add_and_remove_classes() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{nav} | FOR-EACH{a ↦ REMOVE-CLASS{active} | ADD-CLASS{THIS | GET-ATTR{data-section}}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<nav>
+            <a class="nav-link" href="/">Home</a>
+            <a class="nav-link blog" data-section="blog" href="/blog">Blog</a>
+        </nav>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn replace_and_toggle_classes() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{button} | REPLACE-CLASS{btn-default ↤ btn-primary} | TOGGLE-CLASS{'disabled'}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<button class="btn btn-primary disabled">Save</button>"#)
+    );
+
+    Ok(())
+}
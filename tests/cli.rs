@@ -0,0 +1,54 @@
+use std::process::{Command, Output, Stdio};
+
+fn hse(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hse"))
+        .args(arguments)
+        .stdin(Stdio::null())
+        .output()
+        .expect("hse can be started")
+}
+
+#[test]
+fn repl_rejects_options_it_does_not_use() {
+    for arguments in [
+        &[
+            "--diff",
+            "-o",
+            "out.txt",
+            "repl",
+            "-i",
+            "tests/single_div.html",
+        ][..],
+        &["--check", "repl", "-i", "tests/single_div.html"],
+        &["--strict", "repl", "-i", "tests/single_div.html"],
+        &[
+            "--emit",
+            "json",
+            "--value",
+            "THIS",
+            "repl",
+            "-i",
+            "tests/single_div.html",
+        ],
+        &["ONLY{div}", "repl", "-i", "tests/single_div.html"],
+    ] {
+        let output = hse(arguments);
+
+        assert_eq!(output.status.code(), Some(2), "for {:?}", arguments);
+        assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("can't be used with the repl subcommand"));
+    }
+}
+
+#[test]
+fn repl_accepts_input_and_error_format() {
+    let output = hse(&[
+        "--error-format",
+        "json",
+        "repl",
+        "-i",
+        "tests/single_div.html",
+    ]);
+
+    assert_eq!(output.status.code(), Some(0));
+}
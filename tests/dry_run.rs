@@ -0,0 +1,93 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head><title>Title</title></head>
+    <body>
+        <p>Text</p>
+    </body>
+</html>"#;
+
+fn dry_run(command: &str) -> Result<DryRun, StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    hse.dry_run(command)
+}
+
+#[test]
+fn dry_run_shows_changes_as_unified_diff() -> Result<(), StreamingEditorError> {
+    let dry_run =
+        dry_run("WITH{head ↦ APPEND-ELEMENT{ NEW{meta} | SET-ATTR{name ↤ 'viewport'} } }")?;
+
+    assert!(dry_run.has_changes());
+    assert_eq!(
+        dry_run.unified_diff("index.html"),
+        r#"--- index.html
++++ index.html
+@@ -1,5 +1,5 @@
+ <html>
+-    <head><title>Title</title></head>
++    <head><title>Title</title><meta name="viewport"></head>
+     <body>
+         <p>Text</p>
+     </body>
+"#
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_without_changes_has_empty_diff() -> Result<(), StreamingEditorError> {
+    let dry_run = dry_run("WITH{#missing ↦ ADD-CLASS{x}}")?;
+
+    assert!(!dry_run.has_changes());
+    assert_eq!(dry_run.original(), format!("{}\n", HTML_INPUT));
+    assert_eq!(dry_run.unified_diff("index.html"), "");
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_result_has_one_line_per_node() -> Result<(), StreamingEditorError> {
+    let dry_run = dry_run("EXTRACT-ELEMENT{title, p}")?;
+
+    assert_eq!(dry_run.result(), "<title>Title</title>\n<p>Text</p>\n");
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_reports_failing_pipeline() {
+    match dry_run("EXTRACT-ELEMENT{p} | REQUIRE{a}") {
+        Err(StreamingEditorError::RunningPipelineFailed { .. }) => {}
+        Err(e) => panic!("expected a running error, got {:?}", e),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+}
+
+#[test]
+fn dry_run_diffs_large_input_with_many_changes() -> Result<(), StreamingEditorError> {
+    let html = format!(
+        "<html>\n<body>\n{}</body>\n</html>",
+        (0..15_000)
+            .map(|i| match i % 3 {
+                0 => format!("<div class=\"odd\"><span>Item {}</span></div>\n", i),
+                _ => format!("<div><span>Item {}</span></div>\n", i),
+            })
+            .collect::<String>()
+    );
+    let mut input = Box::new(html.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let dry_run = hse.dry_run(r#"WITH{div.odd ↦ SET-ATTR{class ↤ "x"}}"#)?;
+    let diff = dry_run.unified_diff("index.html");
+
+    let removed = diff.lines().filter(|l| l.starts_with("-<div")).count();
+    let added = diff.lines().filter(|l| l.starts_with("+<div")).count();
+    assert_eq!((removed, added), (5_000, 5_000));
+    assert!(diff.contains("\n-<div class=\"odd\"><span>Item 3</span></div>\n"));
+    assert!(diff.contains("\n+<div class=\"x\"><span>Item 3</span></div>\n"));
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <div id="target" title="not a tag"></div>
+    </body>
+</html>"#;
+
+fn json_error_of(command: &str) -> String {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    match hse.run(command) {
+        Err(e) => e.to_json(),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+}
+
+#[test]
+fn parsing_error_as_json() {
+    assert_eq!(
+        json_error_of("ONLY{#target} | SET-ATR{title ↤ \"x\"}"),
+        r#"{"kind":"parsing_pipeline_failed","code":"P001","line":1,"column":17,"expected":["an element processing command"],"suggestion":"SET-ATTR","message":"Failed to parse pipeline: unknown command `SET-ATR`","causes":[]}"#
+    );
+}
+
+#[test]
+fn running_error_as_json() {
+    assert_eq!(
+        json_error_of("ONLY{#target}\n| SET-TAG-NAME{THIS | GET-ATTR{title}}"),
+        r#"{"kind":"running_pipeline_failed","command_path":"RENAME-ELEMENT[1]","line":2,"column":3,"message":"Failed to run pipeline: RENAME-ELEMENT[1] failed at line 2, column 3","causes":["Command RENAME-ELEMENT at index 1 failed","Invalid tag name \"not a tag\""]}"#
+    );
+}
+
+#[test]
+fn loading_error_as_json() {
+    let json = json_error_of("INCLUDE{'tests/recipes/missing.hsp'}");
+
+    assert!(json.starts_with(r#"{"kind":"loading_pipeline_failed","message":"Failed to load pipeline definition","causes":["Failed to read included file tests/recipes/missing.hsp","#));
+}
+
+#[test]
+fn error_outside_the_editor_as_json() {
+    let cause = std::io::Error::new(std::io::ErrorKind::NotFound, "no such \"file\"");
+
+    assert_eq!(
+        error_json(
+            "opening_input_failed",
+            "Could not open input file in.html",
+            Some(&cause)
+        ),
+        r#"{"kind":"opening_input_failed","message":"Could not open input file in.html","causes":["no such \"file\""]}"#
+    );
+    assert_eq!(
+        error_json(
+            "input_would_change",
+            "The pipeline would change the input",
+            None
+        ),
+        r#"{"kind":"input_would_change","message":"The pipeline would change the input","causes":[]}"#
+    );
+}
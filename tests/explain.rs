@@ -0,0 +1,124 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <ul id="menu">
+            <li>Home</li>
+            <li>About</li>
+            <li><a href="/contact">Contact</a></li>
+        </ul>
+        <p>Text with <em>emphasis</em></p>
+    </body>
+</html>"#;
+
+fn explain(command: &str) -> (String, Result<usize, StreamingEditorError>) {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let mut explanation = String::new();
+    let hse = HtmlStreamingEditor::new(&mut input).with_explanation(&mut explanation);
+
+    let result = hse.run(command).map(|result| result.len());
+    (explanation, result)
+}
+
+#[test]
+fn explains_selected_and_changed_nodes() {
+    let (explanation, result) = explain("FOR-EACH{#menu ↦ CHILDREN{li} | LAST | ADD-CLASS{last}}");
+
+    assert!(matches!(result, Ok(1)));
+    assert_eq!(
+        explanation,
+        "FOR-EACH[0] at line 1, column 1: 1 → 1 node
+    changed: :root
+  CHILDREN[0] at line 1, column 18: 1 → 3 nodes
+      selected: ul#menu > li:nth-child(1), ul#menu > li:nth-child(2), ul#menu > li:nth-child(3)
+  LAST[1] at line 1, column 33: 3 → 1 node
+      selected: ul#menu > li:nth-child(3)
+  ADD-CLASS[2] at line 1, column 40: 1 → 1 node
+      changed: ul#menu > li:nth-child(3)
+"
+    );
+}
+
+#[test]
+fn explains_commands_of_sub_pipelines_indented() {
+    let (explanation, result) = explain(
+        "FOR-EACH{li ↦ IF{QUERY-ELEMENT{a} ↦ INSERT-AFTER{NEW{li}}}}\n| REMOVE-ELEMENT{em}",
+    );
+
+    assert!(result.is_ok());
+    assert_eq!(
+        explanation,
+        "FOR-EACH[0] at line 1, column 1: 1 → 1 node
+    changed: :root
+  IF[0] at line 1, column 15: 3 → 3 nodes
+      changed: ul#menu
+    INSERT-AFTER[0] at line 1, column 37: 1 → 1 node
+        changed: ul#menu
+      CREATE-ELEMENT[0] at line 1, column 50: 1 → 1 node
+          selected: li
+REMOVE-ELEMENT[1] at line 2, column 3: 1 → 1 node
+    changed: :root
+"
+    );
+}
+
+#[test]
+fn explains_commands_up_to_failure() {
+    let (explanation, result) =
+        explain("EXTRACT-ELEMENT{#menu} | FOR-EACH{li ↦ RENAME-ELEMENT{'not a tag'}} | FIRST");
+
+    assert!(matches!(
+        result,
+        Err(StreamingEditorError::RunningPipelineFailed { .. })
+    ));
+    assert_eq!(
+        explanation,
+        "EXTRACT-ELEMENT[0] at line 1, column 1: 1 → 1 node
+    selected: ul#menu
+FOR-EACH[1] at line 1, column 26: 1 node → failed
+  RENAME-ELEMENT[0] at line 1, column 40: 3 nodes → failed
+"
+    );
+}
+
+#[test]
+fn lists_only_the_first_nodes() {
+    let (explanation, _) = explain("EXTRACT-ELEMENT{li, a, p}");
+
+    assert_eq!(
+        explanation,
+        "EXTRACT-ELEMENT[0] at line 1, column 1: 1 → 5 nodes
+    selected: li, li, li, … (2 more)
+"
+    );
+}
+
+#[test]
+fn counts_changes_of_many_siblings_once() {
+    let html = format!(
+        "<ul id=\"list\">{}</ul>",
+        (0..3_000)
+            .map(|i| format!("<li>{}</li>", i))
+            .collect::<String>()
+    );
+    let mut input = Box::new(html.as_bytes());
+    let mut explanation = String::new();
+    let hse = HtmlStreamingEditor::new(&mut input).with_explanation(&mut explanation);
+
+    hse.run("FOR-EACH{li ↦ ADD-CLASS{x}} | FOR-EACH{li ↦ UNWRAP}")
+        .unwrap();
+
+    assert_eq!(
+        explanation,
+        "FOR-EACH[0] at line 1, column 1: 1 → 1 node
+    changed: ul#list
+  ADD-CLASS[0] at line 1, column 15: 3000 → 3000 nodes
+      changed: ul#list > li:nth-child(1), ul#list > li:nth-child(2), ul#list > li:nth-child(3), … (2997 more)
+FOR-EACH[1] at line 1, column 31: 1 → 1 node
+    changed: ul#list
+  UNWRAP[0] at line 1, column 45: 3000 → 3000 nodes
+      selected: ul#list > #text, ul#list > #text, ul#list > #text, … (2997 more)
+      changed: ul#list
+"
+    );
+}
@@ -0,0 +1,110 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <h1>Title</h1>
+        <nav>
+            <a href="/">Home</a>
+            <a href="/about">About, "us"</a>
+        </nav>
+    </body>
+</html>"#;
+
+fn extract(
+    command: &str,
+    values: &[&str],
+) -> Result<Vec<Vec<ExtractedValue>>, StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    hse.extract(command, values)
+}
+
+#[test]
+fn extract_one_value_per_node() -> Result<(), StreamingEditorError> {
+    let records = extract("ONLY{a}", &["THIS | GET-ATTR{href}"])?;
+
+    assert_eq!(
+        records,
+        vec![
+            vec![ExtractedValue::Single(String::from("/"))],
+            vec![ExtractedValue::Single(String::from("/about"))]
+        ]
+    );
+    assert_eq!(
+        format_records(&records, RecordFormat::Json)?,
+        "[\"/\",\"/about\"]\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn extract_multiple_values_per_node() -> Result<(), StreamingEditorError> {
+    let records = extract(
+        "ONLY{a}",
+        &["THIS | GET-ATTR{href}", "THIS | GET-TEXT-CONTENT"],
+    )?;
+
+    assert_eq!(
+        format_records(&records, RecordFormat::Csv)?,
+        "/,Home\r\n/about,\"About, \"\"us\"\"\"\r\n"
+    );
+    assert_eq!(
+        format_records(&records, RecordFormat::JsonLines)?,
+        "[\"/\",\"Home\"]\n[\"/about\",\"About, \\\"us\\\"\"]\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn extract_without_value_pipeline_uses_html() -> Result<(), StreamingEditorError> {
+    let records = extract("ONLY{h1}", &[])?;
+
+    assert_eq!(
+        records,
+        vec![vec![ExtractedValue::Single(String::from("<h1>Title</h1>"))]]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn multiple_or_missing_values_of_a_value_pipeline_are_kept() -> Result<(), StreamingEditorError> {
+    let records = extract(
+        "ONLY{nav}",
+        &[
+            "QUERY-ELEMENT{a} | GET-ATTR{href}",
+            "QUERY-ELEMENT{a} | GET-ATTR{href} | JOIN{' '}",
+            "QUERY-ELEMENT{img} | GET-ATTR{src}",
+        ],
+    )?;
+
+    assert_eq!(
+        format_records(&records, RecordFormat::Json)?,
+        "[[[\"/\",\"/about\"],\"/ /about\",null]]\n"
+    );
+    assert!(matches!(
+        format_records(&records, RecordFormat::Csv),
+        Err(StreamingEditorError::MultipleValuesInField {
+            record: 1,
+            value: 1,
+            count: 2,
+            ..
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_value_pipeline_is_reported() {
+    match extract("ONLY{a}", &["THIS | GET-ATR{href}"]) {
+        Err(StreamingEditorError::ParsingPipelineFailed { diagnostic, .. }) => {
+            assert_eq!(diagnostic.suggestion(), Some("GET-ATTR"));
+        }
+        Err(e) => panic!("expected a parsing error, got {:?}", e),
+        Ok(_) => panic!("expected the value pipeline to be invalid"),
+    }
+}
@@ -0,0 +1,84 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <ul id="list">
+            <li id="item-1" class="odd">1</li>
+            <li id="item-2">2</li>
+            <li id="item-3" class="odd">3</li>
+            <li id="item-4">4</li>
+        </ul>
+    </body>
+</html>"#;
+
+#[test]
+fn filtered_nodes_stay_part_of_document() -> Result<(), StreamingEditorError> {
+    let command =
+        r#"EXTRACT-ELEMENT{ul} | FOR-EACH{li ↦ FILTER{.odd} | SET-ATTR{data-test ↤ "x"}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<ul id="list">
+            <li class="odd" data-test="x" id="item-1">1</li>
+            <li id="item-2">2</li>
+            <li class="odd" data-test="x" id="item-3">3</li>
+            <li id="item-4">4</li>
+        </ul>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn slice_and_not_narrow_selection() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{li} | SLICE{2..4} | NOT{.odd}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<li id="item-2">2</li><li id="item-4">4</li>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn last_returns_only_last_element() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{li} | LAST"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(result_string, String::from(r#"<li id="item-4">4</li>"#));
+
+    Ok(())
+}
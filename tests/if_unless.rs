@@ -0,0 +1,66 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head>
+        <meta name="robots" content="noindex">
+    </head>
+    <body>
+        <img src="small.png" width="200">
+        <img src="large.png" width="1200">
+        <img src="unknown.png">
+    </body>
+</html>"#;
+
+#[test]
+fn set_loading_lazy_for_wide_images() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{body} | FOR-EACH{img ↦ IF{THIS | GET-ATTR{width} > "400" ↦ SET-ATTR{loading ↤ "lazy"}} ELSE{SET-ATTR{loading ↤ "eager"}}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<body>
+        <img loading="eager" src="small.png" width="200">
+        <img loading="lazy" src="large.png" width="1200">
+        <img loading="eager" src="unknown.png">
+    </body>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_meta_only_if_missing() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{head} | UNLESS{QUERY-ELEMENT{meta[name="robots"]} ↦ APPEND-ELEMENT{NEW{meta} | SET-ATTR{name ↤ "robots"}}} | UNLESS{QUERY-ELEMENT{meta[name="viewport"]} ↦ APPEND-ELEMENT{NEW{meta} | SET-ATTR{name ↤ "viewport"}}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<head>
+        <meta content="noindex" name="robots">
+    <meta name="viewport"></head>"#
+        )
+    );
+
+    Ok(())
+}
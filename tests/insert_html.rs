@@ -0,0 +1,60 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <div id="banner" data-html="<b>Sale</b> tonight">Placeholder</div>
+        <article><h1>Title</h1></article>
+    </body>
+</html>"#;
+
+#[test]
+fn set_inner_html_from_string() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{#banner} | SET-INNER-HTML{'<a href="/sale">Sale</a> <!-- generated -->'}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<div data-html="<b>Sale</b> tonight" id="banner"><a href="/sale">Sale</a> <!-- generated --></div>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn insert_parsed_html_from_attribute_as_sibling() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{body} | FOR-EACH{h1 ↦ INSERT-AFTER{PARSE-HTML{QUERY-ROOT{#banner} | GET-ATTR{data-html}}}} | APPEND-HTML{'<footer>End</footer>'}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<body>
+        <div data-html="<b>Sale</b> tonight" id="banner">Placeholder</div>
+        <article><h1>Title</h1><b>Sale</b> tonight</article>
+    <footer>End</footer></body>"#
+        )
+    );
+
+    Ok(())
+}
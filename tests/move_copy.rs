@@ -0,0 +1,86 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head><title>Title</title></head>
+    <body>
+        <script src="first.js"></script>
+        <style>p { color: red; }</style>
+        <p>Some text</p>
+        <script src="second.js"></script>
+        <p>More text</p>
+    </body>
+</html>"#;
+
+#[test]
+fn move_scripts_to_end_of_body() -> Result<(), StreamingEditorError> {
+    let command = r#"MOVE{script ↦ body} | EXTRACT-ELEMENT{body}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<body>
+        
+        <style>p { color: red; }</style>
+        <p>Some text</p>
+        
+        <p>More text</p>
+    <script src="first.js"></script><script src="second.js"></script></body>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn move_style_into_head() -> Result<(), StreamingEditorError> {
+    let command = r#"MOVE{body style ↦ head title, AFTER} | EXTRACT-ELEMENT{head}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<head><title>Title</title><style>p { color: red; }</style></head>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn copy_title_into_every_paragraph() -> Result<(), StreamingEditorError> {
+    let command = r#"COPY{title ↦ p, PREPEND} | EXTRACT-ELEMENT{p}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<p><title>Title</title>Some text</p><p><title>Title</title>More text</p>"#)
+    );
+
+    Ok(())
+}
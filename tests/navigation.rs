@@ -0,0 +1,66 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <figure id="first-figure">
+            <div><img src="first.png"></div>
+            <figcaption>First</figcaption>
+        </figure>
+        <figure id="second-figure">
+            <img src="second.png">
+        </figure>
+        <img src="standalone.png">
+    </body>
+</html>"#;
+
+#[test]
+fn mark_figures_containing_images() -> Result<(), StreamingEditorError> {
+    let command = r#"FOR-EACH{img ↦ CLOSEST{figure} | SET-ATTR{class ↤ "with-image"}} | EXTRACT-ELEMENT{figure}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<figure class="with-image" id="first-figure">
+            <div><img src="first.png"></div>
+            <figcaption>First</figcaption>
+        </figure><figure class="with-image" id="second-figure">
+            <img src="second.png">
+        </figure>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn select_caption_via_sibling() -> Result<(), StreamingEditorError> {
+    let command = r#"FOR-EACH{#first-figure img ↦ PARENT | NEXT-SIBLING | SET-ATTR{data-test ↤ "x"}} | EXTRACT-ELEMENT{figcaption}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<figcaption data-test="x">First</figcaption>"#)
+    );
+
+    Ok(())
+}
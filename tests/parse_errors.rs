@@ -0,0 +1,72 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <div id="target" title="old"></div>
+    </body>
+</html>"#;
+
+fn parse_error_of(command: &str) -> Box<PipelineDiagnostic> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    match hse.run(command) {
+        Err(StreamingEditorError::ParsingPipelineFailed { diagnostic, .. }) => diagnostic,
+        Err(e) => panic!("expected a parsing error, got {:?}", e),
+        Ok(_) => panic!("expected a parsing error"),
+    }
+}
+
+#[test]
+fn misspelled_command_is_shown_with_caret_and_suggestion() {
+    let diagnostic = parse_error_of("EXTRACT-ELEMENT{#target} | SET-ATR{title ↤ 'new'}");
+
+    assert_eq!(diagnostic.code(), DiagnosticCode::UnknownCommand);
+    assert_eq!(diagnostic.code().code(), "P001");
+    assert_eq!(
+        diagnostic.to_string(),
+        r#"unknown command `SET-ATR` [P001]
+ --> line 1, column 28
+  |
+1 | EXTRACT-ELEMENT{#target} | SET-ATR{title ↤ 'new'}
+  |                            ^^^^^^^
+  = expected an element processing command
+  = help: did you mean `SET-ATTR`?"#
+    );
+}
+
+#[test]
+fn position_counts_lines_of_pipeline_with_comments() {
+    let diagnostic = parse_error_of(
+        "# keep only the target\nEXTRACT-ELEMENT{#target}\n  | WITH{div ↦ SET-ATTR{title ↤ THIS | GET-ATTR{id} | TO-UPER}}",
+    );
+
+    assert_eq!(diagnostic.line(), 3);
+    assert_eq!(diagnostic.column(), 55);
+    assert_eq!(diagnostic.suggestion(), Some("TO-UPPER"));
+}
+
+#[test]
+fn unclosed_command_is_reported_as_unexpected_end() {
+    let diagnostic = parse_error_of("EXTRACT-ELEMENT{#target");
+
+    assert_eq!(diagnostic.code(), DiagnosticCode::UnexpectedEnd);
+    assert_eq!(diagnostic.code().code(), "P003");
+    assert!(diagnostic.expected().contains(&String::from("`}`")));
+}
+
+#[test]
+fn error_message_contains_diagnostic() {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let error = match hse.run("EXTRACT-ELEMENT{#target} | ADD-CLAS{x}") {
+        Err(e) => e,
+        Ok(_) => panic!("expected a parsing error"),
+    };
+
+    assert!(error
+        .to_string()
+        .starts_with("Failed to parse pipeline: unknown command `ADD-CLAS` [P001]\n"));
+    assert!(error.to_string().contains("did you mean `ADD-CLASS`?"));
+}
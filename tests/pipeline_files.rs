@@ -0,0 +1,104 @@
+use html_streaming_editor::*;
+use std::path::Path;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <div id="content"><script>track()</script><a data-track-id="1" href="/">Home</a></div>
+    </body>
+</html>"#;
+
+#[test]
+fn run_file_with_includes_and_definitions() -> Result<(), StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run_file(Path::new("tests/recipes/cleanup.hsp"))?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div class="cleaned" id="content"><a href="/">Home</a></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn run_with_comments_and_definitions() -> Result<(), StreamingEditorError> {
+    let command = r#"
+        # keep only the link
+        DEFINE link-only { ONLY{a} }
+        CALL{link-only} | SET-TEXT-CONTENT{'# not a comment'} # but this is
+    "#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<a data-track-id="1" href="/"># not a comment</a>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn include_cycle_fails() {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run_file(Path::new("tests/recipes/cycle_a.hsp"));
+
+    assert!(matches!(
+        result,
+        Err(StreamingEditorError::LoadingPipelineFailed {
+            source: PipelineSourceError::IncludeCycle { .. }
+        })
+    ));
+}
+
+#[test]
+fn missing_include_fails() {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(r#"INCLUDE{"tests/recipes/missing.hsp"}"#);
+
+    assert!(matches!(
+        result,
+        Err(StreamingEditorError::LoadingPipelineFailed {
+            source: PipelineSourceError::ReadingIncludeFailed { .. }
+        })
+    ));
+}
+
+#[test]
+fn parse_error_in_included_file_shows_its_path_and_line() {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run("ONLY{body}\n| INCLUDE{'tests/recipes/typo.hsp'}");
+
+    match result {
+        Err(StreamingEditorError::ParsingPipelineFailed { diagnostic, .. }) => {
+            assert_eq!(diagnostic.file(), Some(Path::new("tests/recipes/typo.hsp")));
+            assert_eq!((diagnostic.line(), diagnostic.column()), (4, 5));
+            assert!(diagnostic.to_string().contains(
+                " --> line 4, column 5 of tests/recipes/typo.hsp\n  |\n4 |   | REMOVE-ATR{data-track-id}\n"
+            ));
+        }
+        Err(e) => panic!("expected a parsing error, got {:?}", e),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+}
@@ -122,3 +122,31 @@ fn copy_title_to_meta_tag() -> Result<(), StreamingEditorError> {
 
     Ok(())
 }
+
+#[test]
+fn prepend_all_list_items_from_file_in_order() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{#list} | PREPEND-ELEMENT{ SOURCE{"tests/source.html"} | ONLY{#first li} }"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<ul id="list"><li>1</li><li>2</li><li>3</li>
+            <li id="item-1">1</li>
+            <li id="item-2">2</li>
+            <li id="item-3">3</li>
+        </ul>"#
+        )
+    );
+
+    Ok(())
+}
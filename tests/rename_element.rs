@@ -0,0 +1,62 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <center><b>Important</b> and <i>emphasized</i></center>
+        <div data-tag="section">Content</div>
+    </body>
+</html>"#;
+
+#[test]
+fn migrate_legacy_markup() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{center} | FOR-EACH{b ↦ RENAME-ELEMENT{strong}} | FOR-EACH{i ↦ SET-TAG-NAME{em}} | RENAME-ELEMENT{div} | ADD-CLASS{center}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<div class="center"><strong>Important</strong> and <em>emphasized</em></div>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rename_element_from_attribute() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{div} | RENAME-ELEMENT{THIS | GET-ATTR{data-tag}} | CLEAR-ATTR{data-tag}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(result_string, String::from(r#"<section>Content</section>"#));
+
+    Ok(())
+}
+
+#[test]
+fn rename_element_to_invalid_name_fails() {
+    let command = r#"EXTRACT-ELEMENT{center} | RENAME-ELEMENT{'<div>'}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    assert!(hse.run(command).is_err());
+}
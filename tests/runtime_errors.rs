@@ -0,0 +1,104 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <div id="target" title="not a tag"></div>
+    </body>
+</html>"#;
+
+fn run_failing(command: &str) -> StreamingEditorError {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    match hse.run(command) {
+        Err(e) => e,
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+}
+
+#[test]
+fn error_names_failing_command_with_its_path() {
+    let error = run_failing(
+        "EXTRACT-ELEMENT{#target} | IF{HAS-ATTR{title} ↦ ADD-CLASS{x} | SET-TAG-NAME{THIS | GET-ATTR{title}}}",
+    );
+
+    match &error {
+        StreamingEditorError::RunningPipelineFailed {
+            command_path,
+            line,
+            column,
+            source,
+            ..
+        } => {
+            assert_eq!(command_path, "IF[1] > RENAME-ELEMENT[1]");
+            assert_eq!(source.command_path(), "IF[1] > RENAME-ELEMENT[1]");
+            assert_eq!((*line, *column), (Some(1), Some(64)));
+            assert_eq!(source.span(), Some(65..101));
+        }
+        e => panic!("expected a running error, got {:?}", e),
+    }
+    assert_eq!(
+        error.to_string(),
+        "Failed to run pipeline: IF[1] > RENAME-ELEMENT[1] failed at line 1, column 64"
+    );
+}
+
+#[test]
+fn error_position_refers_to_pipeline_lines() {
+    let error = run_failing(
+        "EXTRACT-ELEMENT{#target}\n  | ADD-CLASS{x}\n  | IF{THIS ↦ APPEND-ELEMENT{LOAD-FILE{'tests/missing.html'}}}",
+    );
+
+    assert_eq!(
+        error.to_string(),
+        "Failed to run pipeline: IF[2] > APPEND-ELEMENT[0] > LOAD-FILE[0] failed at line 3, column 30"
+    );
+}
+
+#[test]
+fn error_position_refers_to_where_called_definition_is_written() {
+    let error = run_failing(
+        "DEFINE rename {\n  SET-TAG-NAME{THIS | GET-ATTR{title}}\n}\n\nEXTRACT-ELEMENT{#target}\n  | CALL{rename}",
+    );
+
+    assert_eq!(
+        error.to_string(),
+        "Failed to run pipeline: RENAME-ELEMENT[0] failed at line 2, column 3"
+    );
+}
+
+#[test]
+fn error_path_numbers_commands_as_written() {
+    let error = run_failing(
+        "DEFINE prepare {\n  ADD-CLASS{a}\n  | ADD-CLASS{b}\n}\nEXTRACT-ELEMENT{#target}\n  | CALL{prepare}\n  | SET-TAG-NAME{THIS | GET-ATTR{title}}",
+    );
+
+    match &error {
+        StreamingEditorError::RunningPipelineFailed {
+            command_path,
+            source,
+            ..
+        } => {
+            assert_eq!(command_path, "RENAME-ELEMENT[2]");
+            assert_eq!(source.command_path(), "RENAME-ELEMENT[3]");
+        }
+        e => panic!("expected a running error, got {:?}", e),
+    }
+    assert_eq!(
+        error.to_string(),
+        "Failed to run pipeline: RENAME-ELEMENT[2] failed at line 7, column 5"
+    );
+}
+
+#[test]
+fn error_in_included_file_names_it() {
+    let error = run_failing("EXTRACT-ELEMENT{#target} | INCLUDE{'tests/recipes/rename.hsp'}");
+
+    assert_eq!(
+        error.to_string(),
+        "Failed to run pipeline: RENAME-ELEMENT[1] failed at line 3, column 5 of tests/recipes/rename.hsp"
+    );
+    assert!(error
+        .to_json()
+        .contains(r#""command_path":"RENAME-ELEMENT[1]","file":"tests/recipes/rename.hsp","line":3,"column":5"#));
+}
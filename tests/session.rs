@@ -0,0 +1,157 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <ul id="menu">
+            <li>Home</li>
+            <li class="active">About</li>
+        </ul>
+        <p>Text</p>
+    </body>
+</html>"#;
+
+fn load() -> EditingSession {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+
+    EditingSession::load(&mut input).unwrap()
+}
+
+fn current_html(session: &EditingSession) -> Vec<String> {
+    session
+        .current()
+        .iter()
+        .map(|node| node.outer_html())
+        .collect()
+}
+
+#[test]
+fn query_shows_matches_without_changing_anything() -> Result<(), StreamingEditorError> {
+    let session = load();
+
+    let matches = session.query("#menu li")?;
+
+    assert_eq!(
+        matches
+            .iter()
+            .map(|m| (m.path(), m.html()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("ul#menu > li:nth-child(1)", "<li>Home</li>"),
+            (
+                "ul#menu > li:nth-child(2)",
+                r#"<li class="active">About</li>"#
+            ),
+        ]
+    );
+    assert!(session.commands().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn applied_commands_work_on_previous_result() -> Result<(), StreamingEditorError> {
+    let mut session = load();
+
+    assert_eq!(session.apply("EXTRACT-ELEMENT{#menu}")?, 1);
+    assert_eq!(session.apply("FOR-EACH{li ↦ REMOVE-CLASS{active}}")?, 1);
+    assert_eq!(session.query(".active")?, vec![]);
+    assert_eq!(
+        session.pipeline_definition(),
+        "EXTRACT-ELEMENT{#menu}\n| FOR-EACH{li ↦ REMOVE-CLASS{active}}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn undo_reverts_last_command() -> Result<(), StreamingEditorError> {
+    let mut session = load();
+    session.apply("EXTRACT-ELEMENT{li}")?;
+    session.apply("ADD-CLASS{item}")?;
+
+    assert_eq!(session.undo()?, Some(String::from("ADD-CLASS{item}")));
+    assert_eq!(
+        current_html(&session),
+        vec!["<li>Home</li>", r#"<li class="active">About</li>"#]
+    );
+    assert_eq!(session.undo()?, Some(String::from("EXTRACT-ELEMENT{li}")));
+    assert_eq!(session.undo()?, None);
+    assert_eq!(session.query("li")?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn failing_command_changes_nothing() -> Result<(), StreamingEditorError> {
+    let mut session = load();
+    session.apply("EXTRACT-ELEMENT{li}")?;
+
+    match session.apply("ADD-CLASS{changed} | EXPECT-COUNT{3}") {
+        Err(StreamingEditorError::RunningPipelineFailed { .. }) => {}
+        Err(e) => panic!("expected a running error, got {:?}", e),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+
+    assert_eq!(
+        current_html(&session),
+        vec!["<li>Home</li>", r#"<li class="active">About</li>"#]
+    );
+    assert_eq!(session.commands(), ["EXTRACT-ELEMENT{li}"]);
+
+    Ok(())
+}
+
+#[test]
+fn invalid_selector_is_reported() {
+    let session = load();
+
+    match session.query("li >") {
+        Err(StreamingEditorError::ParsingPipelineFailed { .. }) => {}
+        Err(e) => panic!("expected a parsing error, got {:?}", e),
+        Ok(_) => panic!("expected the selector to be invalid"),
+    }
+}
+
+#[test]
+fn definitions_and_includes_are_rejected() {
+    let mut session = load();
+
+    for definition in [
+        "DEFINE menu { EXTRACT-ELEMENT{li} } CALL{menu}",
+        "INCLUDE{\"tests/recipes/rename.hsp\"}",
+    ] {
+        match session.apply(definition) {
+            Err(StreamingEditorError::LoadingPipelineFailed {
+                source: PipelineSourceError::DirectiveInSession { .. },
+                ..
+            }) => {}
+            Err(e) => panic!("expected DEFINE/INCLUDE to be rejected, got {:?}", e),
+            Ok(_) => panic!("expected DEFINE/INCLUDE to be rejected"),
+        }
+    }
+    assert!(session.commands().is_empty());
+}
+
+#[test]
+fn saved_pipeline_definition_parses_and_gives_the_same_result() -> Result<(), StreamingEditorError>
+{
+    let mut session = load();
+
+    assert_eq!(session.apply("# the menu only")?, 1);
+    assert_eq!(session.apply("")?, 1);
+    session.apply("EXTRACT-ELEMENT{#menu} # keep it")?;
+    session.apply("FOR-EACH{li ↦ REMOVE-CLASS{active}} # no highlight")?;
+    assert_eq!(session.commands().len(), 2);
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+    let result = hse
+        .run(&session.pipeline_definition())?
+        .iter()
+        .map(|node| node.outer_html())
+        .collect::<Vec<_>>();
+
+    assert_eq!(result, current_html(&session));
+
+    Ok(())
+}
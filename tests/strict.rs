@@ -0,0 +1,164 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <ul id="menu">
+            <li>Home</li>
+            <li>About</li>
+        </ul>
+    </body>
+</html>"#;
+
+fn run(command: &str, strict: bool) -> Result<String, StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input).with_strict(strict);
+
+    let result = hse.run(command)?;
+    Ok(result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join(""))
+}
+
+fn error_of(command: &str, strict: bool) -> String {
+    match run(command, strict) {
+        Err(e) => e.to_string(),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+}
+
+#[test]
+fn remove_matching_nothing_is_ignored_by_default() -> Result<(), StreamingEditorError> {
+    let result_string = run(
+        "EXTRACT-ELEMENT{#menu} | REMOVE-ELEMENT{#cookie-banner}",
+        false,
+    )?;
+
+    assert!(result_string.starts_with(r#"<ul id="menu">"#));
+
+    Ok(())
+}
+
+#[test]
+fn remove_matching_nothing_fails_in_strict_mode() {
+    assert_eq!(
+        error_of(
+            "EXTRACT-ELEMENT{#menu} | REMOVE-ELEMENT{#cookie-banner}",
+            true
+        ),
+        "Failed to run pipeline: REMOVE-ELEMENT[1] failed at line 1, column 26"
+    );
+}
+
+#[test]
+fn strict_mode_applies_to_sub_pipelines() {
+    assert_eq!(
+        error_of(
+            "EXTRACT-ELEMENT{#menu} | FOR-EACH{li ↦ CHILDREN{a} | ADD-CLASS{link}}",
+            true
+        ),
+        "Failed to run pipeline: FOR-EACH[1] > CHILDREN[0] failed at line 1, column 40"
+    );
+}
+
+#[test]
+fn require_passes_if_count_matches() -> Result<(), StreamingEditorError> {
+    let result_string = run(
+        "EXTRACT-ELEMENT{#menu} | REQUIRE{li, 2} | REQUIRE{li, 1, 2} | EXTRACT-ELEMENT{li} | EXPECT-COUNT{2}",
+        false,
+    )?;
+
+    assert_eq!(result_string, "<li>Home</li><li>About</li>");
+
+    Ok(())
+}
+
+#[test]
+fn require_fails_if_count_deviates() {
+    let message = error_of("EXTRACT-ELEMENT{#menu} | REQUIRE{li, 3}", false);
+
+    assert_eq!(
+        message,
+        "Failed to run pipeline: REQUIRE[1] failed at line 1, column 26"
+    );
+}
+
+#[test]
+fn require_with_one_count_expects_exactly_that_many() {
+    let message = error_of("EXTRACT-ELEMENT{#menu} | REQUIRE{li, 1}", false);
+
+    assert_eq!(
+        message,
+        "Failed to run pipeline: REQUIRE[1] failed at line 1, column 26"
+    );
+}
+
+#[test]
+fn expect_count_fails_if_count_deviates() {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    match hse.run("EXTRACT-ELEMENT{li} | EXPECT-COUNT{1}") {
+        Err(StreamingEditorError::RunningPipelineFailed { source, .. }) => {
+            assert_eq!(
+                std::error::Error::source(source.as_ref())
+                    .unwrap()
+                    .to_string(),
+                "Found 2 elements, expected exactly 1"
+            );
+        }
+        Err(e) => panic!("expected a running error, got {:?}", e),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+}
+
+#[test]
+fn errors_within_for_each_are_reported() {
+    assert_eq!(
+        error_of(
+            "EXTRACT-ELEMENT{#menu} | FOR-EACH{li ↦ RENAME-ELEMENT{'not a tag'}}",
+            false
+        ),
+        "Failed to run pipeline: FOR-EACH[1] > RENAME-ELEMENT[0] failed at line 1, column 40"
+    );
+}
+
+#[test]
+fn multiple_values_are_concatenated_by_default() -> Result<(), StreamingEditorError> {
+    let result_string = run(
+        "EXTRACT-ELEMENT{#menu} | SET-ATTR{title ↤ QUERY-ELEMENT{li} | GET-TEXT-CONTENT}",
+        false,
+    )?;
+
+    assert!(result_string.starts_with(r#"<ul id="menu" title="HomeAbout">"#));
+
+    Ok(())
+}
+
+#[test]
+fn strict_mode_requires_combining_multiple_values() -> Result<(), StreamingEditorError> {
+    match run(
+        "EXTRACT-ELEMENT{#menu} | SET-ATTR{title ↤ QUERY-ELEMENT{li} | GET-TEXT-CONTENT}",
+        true,
+    ) {
+        Err(StreamingEditorError::RunningPipelineFailed { source, .. }) => {
+            assert_eq!(
+                std::error::Error::source(source.as_ref())
+                    .unwrap()
+                    .to_string(),
+                "Sub-Pipeline returned 2 values where one is expected, combine them with JOIN, FIRST, LAST or COUNT"
+            );
+        }
+        Err(e) => panic!("expected a running error, got {:?}", e),
+        Ok(_) => panic!("expected the pipeline to fail"),
+    }
+
+    let result_string = run(
+        "EXTRACT-ELEMENT{#menu} | SET-ATTR{title ↤ QUERY-ELEMENT{li} | GET-TEXT-CONTENT | JOIN{', '}}",
+        true,
+    )?;
+    assert!(result_string.starts_with(r#"<ul id="menu" title="Home, About">"#));
+
+    Ok(())
+}
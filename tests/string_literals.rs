@@ -0,0 +1,77 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <div id="target" title="old"></div>
+    </body>
+</html>"#;
+
+fn run_on_target(command: &str) -> Result<String, StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    Ok(result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join(""))
+}
+
+#[test]
+fn set_attr_with_both_quote_types() -> Result<(), StreamingEditorError> {
+    let result_string =
+        run_on_target(r#"EXTRACT-ELEMENT{#target} | SET-ATTR{title ↤ 'It\'s "quoted"\?'}"#)?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target" title="It's &quot;quoted&quot;?"></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn set_attr_to_empty_string() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(r#"EXTRACT-ELEMENT{#target} | SET-ATTR{title ↤ ''}"#)?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target" title=""></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn set_text_content_with_newline_escape() -> Result<(), StreamingEditorError> {
+    let result_string =
+        run_on_target(r#"EXTRACT-ELEMENT{#target} | SET-TEXT-CONTENT{'line 1\nline 2'}"#)?;
+
+    assert_eq!(
+        result_string,
+        String::from("<div id=\"target\" title=\"old\">line 1\nline 2</div>")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn append_multiline_raw_html() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r##"EXTRACT-ELEMENT{#target} | APPEND-HTML{r#"<script type="application/json">
+{"key": "it's \n raw"}
+</script>"#}"##,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<div id="target" title="old"><script type="application/json">
+{"key": "it's \n raw"}
+</script></div>"#
+        )
+    );
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <div class="teaser" style="display:none; color: #333">Hidden by the CMS</div>
+        <img src="photo.jpg" style="width: 1200px;border:0">
+    </body>
+</html>"#;
+
+#[test]
+fn remove_display_and_limit_image_width() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{body} | FOR-EACH{.teaser ↦ REMOVE-STYLE{display}} | FOR-EACH{img ↦ SET-STYLE{max-width ↤ '100% !important'}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<body>
+        <div class="teaser" style="color: #333">Hidden by the CMS</div>
+        <img src="photo.jpg" style="width: 1200px; border:0; max-width: 100% !important">
+    </body>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn copy_style_value_into_attribute() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{img} | SET-ATTR{data-width ↤ THIS | GET-STYLE{width}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<img data-width="1200px" src="photo.jpg" style="width: 1200px;border:0">"#)
+    );
+
+    Ok(())
+}
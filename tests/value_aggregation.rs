@@ -0,0 +1,69 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body>
+        <ul>
+            <li>pear</li>
+            <li>apple</li>
+            <li></li>
+            <li>pear</li>
+        </ul>
+        <div id="target"></div>
+    </body>
+</html>"#;
+
+fn run_on_target(command: &str) -> Result<String, StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    Ok(result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join(""))
+}
+
+#[test]
+fn values_are_concatenated_without_explicit_join() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-ATTR{title ↤ QUERY-ROOT{li} | GET-TEXT-CONTENT}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target" title="pearapplepear"></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sort_unique_and_join_list_items() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-ATTR{title ↤ QUERY-ROOT{li} | GET-TEXT-CONTENT | SORT | UNIQUE | JOIN{', '}}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target" title=", apple, pear"></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn count_first_and_last_list_items() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-ATTR{data-count ↤ QUERY-ROOT{li} | GET-TEXT-CONTENT | COUNT} | SET-ATTR{data-first ↤ QUERY-ROOT{li} | GET-TEXT-CONTENT | FIRST} | SET-ATTR{data-last ↤ QUERY-ROOT{li} | GET-TEXT-CONTENT | LAST}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<div data-count="4" data-first="pear" data-last="pear" id="target"></div>"#
+        )
+    );
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head><!-- build: 2023-06-01 --><title>Title</title></head>
+    <body>
+        <h2 class="note" data-id="7">H<sub>2</sub>O</h2>
+        <div id="target"></div>
+    </body>
+</html>"#;
+
+#[test]
+fn copy_build_comment_into_attribute() -> Result<(), StreamingEditorError> {
+    let command = r#"FOR-EACH{#target ↦ SET-ATTR{data-build ↤ QUERY-ROOT{head} | GET-COMMENTS | REGEX-REPLACE{'^build: (.*)$' ↤ '$1'}}} | EXTRACT-ELEMENT{#target}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div data-build="2023-06-01" id="target"></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_heading() -> Result<(), StreamingEditorError> {
+    let command = r#"FOR-EACH{#target ↦ SET-TEXT-CONTENT{QUERY-ROOT{h2} | GET-TAG-NAME} | APPEND-TEXT-CONTENT{QUERY-ROOT{h2} | GET-RAW-TEXT | ADD-PREFIX{': '}} | SET-ATTR{title ↤ QUERY-ROOT{h2} | GET-INNER-HTML}} | EXTRACT-ELEMENT{#target}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target" title="H&lt;sub&gt;2&lt;/sub&gt;O">h2: H2O</div>"#)
+    );
+
+    Ok(())
+}
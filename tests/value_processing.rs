@@ -0,0 +1,77 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <body class="page  home dark">
+        <h2>  Hello,   World! </h2>
+        <a href="/search?q=a b">A very long link text</a>
+        <div id="target"></div>
+    </body>
+</html>"#;
+
+fn run_on_target(command: &str) -> Result<String, StreamingEditorError> {
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    Ok(result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join(""))
+}
+
+#[test]
+fn slugify_heading_into_id() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-ATTR{data-slug ↤ QUERY-ROOT{h2} | GET-TEXT-CONTENT | SLUGIFY}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div data-slug="hello-world" id="target"></div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn split_and_join_classes() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-TEXT-CONTENT{QUERY-ROOT{body} | GET-ATTR{class} | COLLAPSE-WHITESPACE | SPLIT{' '} | JOIN{', '} | ADD-PREFIX{'['} | ADD-SUFFIX{']'}}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target">[page, home, dark]</div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn truncate_and_format_link() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-TEXT-CONTENT{QUERY-ROOT{a} | GET-TEXT-CONTENT | TRUNCATE{6, "…"} | FORMAT{"Link: {0}"}} | SET-ATTR{data-query ↤ QUERY-ROOT{a} | GET-ATTR{href} | REGEX-EXTRACT{'q=(.*)$'} | URL-ENCODE}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div data-query="a%20b" id="target">Link: A very…</div>"#)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn default_on_missing_attribute() -> Result<(), StreamingEditorError> {
+    let result_string = run_on_target(
+        r#"FOR-EACH{#target ↦ SET-ATTR{title ↤ QUERY-ROOT{h2} | GET-ATTR{title} | DEFAULT{'untitled'}}} | EXTRACT-ELEMENT{#target}"#,
+    )?;
+
+    assert_eq!(
+        result_string,
+        String::from(r#"<div id="target" title="untitled"></div>"#)
+    );
+
+    Ok(())
+}
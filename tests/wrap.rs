@@ -0,0 +1,66 @@
+use html_streaming_editor::*;
+
+const HTML_INPUT: &str = r#"<html>
+    <head></head>
+    <body>
+        <header><h1>Title</h1></header>
+        <p>Some <font color="red">legacy</font> text</p>
+        <table><tr><td>1</td></tr></table>
+    </body>
+</html>"#;
+
+#[test]
+fn wrap_table_and_unwrap_font() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{body} | FOR-EACH{table ↦ WRAP{NEW{div} | SET-ATTR{class ↤ "table-scroll"}}} | FOR-EACH{font ↦ UNWRAP}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<body>
+        <header><h1>Title</h1></header>
+        <p>Some legacy text</p>
+        <div class="table-scroll"><table><tr><td>1</td></tr></table></div>
+    </body>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn insert_banner_after_header() -> Result<(), StreamingEditorError> {
+    let command = r#"EXTRACT-ELEMENT{body} | FOR-EACH{header ↦ INSERT-AFTER{NEW{aside} | SET-TEXT-CONTENT{"Banner"}}}"#;
+
+    let mut input = Box::new(HTML_INPUT.as_bytes());
+    let hse = HtmlStreamingEditor::new(&mut input);
+
+    let result = hse.run(command)?;
+    let result_string = result
+        .iter()
+        .map(|n| n.outer_html())
+        .collect::<Vec<_>>()
+        .join("");
+
+    assert_eq!(
+        result_string,
+        String::from(
+            r#"<body>
+        <header><h1>Title</h1></header><aside>Banner</aside>
+        <p>Some <font color="red">legacy</font> text</p>
+        <table><tr><td>1</td></tr></table>
+    </body>"#
+        )
+    );
+
+    Ok(())
+}